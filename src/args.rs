@@ -0,0 +1,79 @@
+//! Command-line parsing shared by the game and the tool binaries.
+
+/// Reads `--seed N` or `--seed=N`.
+pub fn parse_seed_arg(args: &[String]) -> Option<u32> {
+    arg_value(args, "--seed").and_then(|value| value.parse().ok())
+}
+
+/// Parses the arguments of a tool that takes nothing but an optional
+/// `--seed N`, falling back to `default`.
+pub fn parse_seed_only(args: &[String], default: u32) -> Result<u32, String> {
+    let mut seed = default;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--seed" => {
+                seed = iter
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .ok_or("--seed needs a number")?;
+            }
+            other => return Err(format!("unexpected argument {}", other)),
+        }
+    }
+    Ok(seed)
+}
+
+/// The value of `name`, given as `name value` or `name=value`.
+pub fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if let Some(value) = arg
+            .strip_prefix(name)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(value);
+        }
+        if arg == name {
+            return iter.next().map(String::as_str);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_seed_arg_accepts_both_forms() {
+        let split = strings(&["--seed", "42"]);
+        let joined = strings(&["--seed=7"]);
+        assert_eq!(parse_seed_arg(&split), Some(42));
+        assert_eq!(parse_seed_arg(&joined), Some(7));
+        assert_eq!(parse_seed_arg(&[]), None);
+        assert_eq!(parse_seed_arg(&strings(&["--seed=x"])), None);
+    }
+
+    #[test]
+    fn parse_seed_only_rejects_anything_else() {
+        assert_eq!(parse_seed_only(&[], 1), Ok(1));
+        assert_eq!(parse_seed_only(&strings(&["--seed", "9"]), 1), Ok(9));
+        assert!(parse_seed_only(&strings(&["--seed"]), 1).is_err());
+        assert!(parse_seed_only(&strings(&["--seed", "x"]), 1).is_err());
+        assert!(parse_seed_only(&strings(&["--fast"]), 1).is_err());
+    }
+
+    #[test]
+    fn arg_value_reads_replay_paths() {
+        let args = strings(&["--record", "run.replay", "--replay=old.replay"]);
+        assert_eq!(arg_value(&args, "--record"), Some("run.replay"));
+        assert_eq!(arg_value(&args, "--replay"), Some("old.replay"));
+        assert_eq!(arg_value(&args, "--seed"), None);
+        assert_eq!(arg_value(&strings(&["--recorder"]), "--record"), None);
+    }
+}
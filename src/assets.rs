@@ -1,19 +1,193 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the directory that holds `assets/`.
+pub const ASSET_ROOT_ENV: &str = "FLAGHACK2_ROOT";
+
+#[derive(Debug)]
 pub struct RasterImage {
     pub width: u16,
     pub height: u16,
     pub pixels: Vec<u8>,
 }
 
-pub fn load_png_rgba(path: &str) -> RasterImage {
-    let img = image::open(path).expect("Failed to open PNG");
-    let rgba = img.to_rgba8();
+#[derive(Debug)]
+pub enum AssetError {
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    Decode {
+        path: PathBuf,
+        error: image::ImageError,
+    },
+    TooLarge {
+        path: PathBuf,
+        width: u32,
+        height: u32,
+    },
+    /// No candidate directory contained `assets/`.
+    RootNotFound(Vec<PathBuf>),
+    NoTiles(PathBuf),
+    MissingTile(PathBuf),
+    TileSize {
+        path: PathBuf,
+        expected: u16,
+        width: u32,
+        height: u32,
+    },
+    Write {
+        path: PathBuf,
+        error: image::ImageError,
+    },
+    ManifestParse {
+        path: PathBuf,
+        error: toml::de::Error,
+    },
+    ManifestSerialize(toml::ser::Error),
+    /// The manifest describes a different grid than the tiles on disk.
+    ManifestMismatch(PathBuf),
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::Io { path, error } => {
+                write!(f, "cannot read {}: {}", path.display(), error)
+            }
+            AssetError::Decode { path, error } => {
+                write!(f, "cannot decode {}: {}", path.display(), error)
+            }
+            AssetError::TooLarge {
+                path,
+                width,
+                height,
+            } => write!(
+                f,
+                "{} is {}x{}, larger than {} pixels",
+                path.display(),
+                width,
+                height,
+                u16::MAX
+            ),
+            AssetError::RootNotFound(searched) => {
+                let searched: Vec<String> = searched
+                    .iter()
+                    .map(|dir| dir.display().to_string())
+                    .collect();
+                write!(
+                    f,
+                    "no assets directory in {}; set {} to the game directory",
+                    searched.join(", "),
+                    ASSET_ROOT_ENV
+                )
+            }
+            AssetError::NoTiles(dir) => write!(f, "no map tiles in {}", dir.display()),
+            AssetError::MissingTile(path) => write!(f, "missing tile {}", path.display()),
+            AssetError::TileSize {
+                path,
+                expected,
+                width,
+                height,
+            } => write!(
+                f,
+                "tile {} is {}x{}, expected {}x{}",
+                path.display(),
+                width,
+                height,
+                expected,
+                expected
+            ),
+            AssetError::Write { path, error } => {
+                write!(f, "cannot write {}: {}", path.display(), error)
+            }
+            AssetError::ManifestParse { path, error } => {
+                write!(
+                    f,
+                    "map manifest parse error in {}: {}",
+                    path.display(),
+                    error
+                )
+            }
+            AssetError::ManifestSerialize(error) => {
+                write!(f, "map manifest serialize error: {}", error)
+            }
+            AssetError::ManifestMismatch(path) => write!(
+                f,
+                "map manifest {} does not match the tiles beside it",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+/// Finds the directory that holds `assets/`. A configured root wins; then
+/// the executable's directory and its parents (which covers `target/debug`
+/// during development); then the working directory.
+pub fn find_asset_root(
+    configured: Option<PathBuf>,
+    exe: Option<&Path>,
+    cwd: &Path,
+) -> Result<PathBuf, AssetError> {
+    let mut candidates = Vec::new();
+    candidates.extend(configured);
+    if let Some(exe) = exe {
+        candidates.extend(exe.ancestors().skip(1).map(Path::to_path_buf));
+    }
+    candidates.push(cwd.to_path_buf());
+    match candidates.iter().find(|dir| dir.join("assets").is_dir()) {
+        Some(root) => Ok(root.clone()),
+        None => Err(AssetError::RootNotFound(candidates)),
+    }
+}
+
+/// `find_asset_root` for this process, configured through `ASSET_ROOT_ENV`.
+pub fn asset_root() -> Result<PathBuf, AssetError> {
+    let configured = std::env::var_os(ASSET_ROOT_ENV).map(PathBuf::from);
+    let exe = std::env::current_exe().ok();
+    let cwd = std::env::current_dir().unwrap_or_default();
+    find_asset_root(configured, exe.as_deref(), &cwd)
+}
+
+pub fn load_png_rgba<P: AsRef<Path>>(path: P) -> Result<RasterImage, AssetError> {
+    let path = path.as_ref();
+    let image = image::open(path).map_err(|error| match error {
+        image::ImageError::IoError(error) => AssetError::Io {
+            path: path.to_path_buf(),
+            error,
+        },
+        error => AssetError::Decode {
+            path: path.to_path_buf(),
+            error,
+        },
+    })?;
+    let rgba = image.to_rgba8();
     let (width, height) = rgba.dimensions();
+    let (Ok(short_width), Ok(short_height)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(AssetError::TooLarge {
+            path: path.to_path_buf(),
+            width,
+            height,
+        });
+    };
 
-    RasterImage {
-        width: width.try_into().expect("PNG width exceeds u16"),
-        height: height.try_into().expect("PNG height exceeds u16"),
+    Ok(RasterImage {
+        width: short_width,
+        height: short_height,
         pixels: rgba.into_raw(),
-    }
+    })
+}
+
+pub fn save_png_rgba<P: AsRef<Path>>(path: P, raster: &RasterImage) -> image::ImageResult<()> {
+    image::save_buffer(
+        path,
+        &raster.pixels,
+        raster.width as u32,
+        raster.height as u32,
+        image::ColorType::Rgba8,
+    )
 }
 
 pub fn scale_to_fit(raw_w: f32, raw_h: f32, max_size: f32) -> (f32, f32) {
@@ -36,7 +210,7 @@ mod tests {
     fn load_png_rgba_reads_pixels() {
         let png_path =
             std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/png/signifiersmark.png");
-        let raster = load_png_rgba(png_path.to_str().expect("Invalid path"));
+        let raster = load_png_rgba(png_path).expect("signifier mark loads");
 
         assert!(raster.width > 0);
         assert!(raster.height > 0);
@@ -50,7 +224,7 @@ mod tests {
     fn signifier_mark_has_transparency() {
         let png_path =
             std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/png/signifiersmark.png");
-        let raster = load_png_rgba(png_path.to_str().expect("Invalid path"));
+        let raster = load_png_rgba(png_path).expect("signifier mark loads");
 
         let mut has_transparent = false;
         let mut has_opaque = false;
@@ -74,4 +248,27 @@ mod tests {
         );
         assert!(has_opaque, "expected opaque pixels in signifier mark");
     }
+
+    #[test]
+    fn load_png_rgba_reports_missing_file() {
+        let err = load_png_rgba("no/such/image.png").unwrap_err();
+        assert!(matches!(err, AssetError::Io { .. }));
+        assert!(err.to_string().contains("no/such/image.png"));
+    }
+
+    #[test]
+    fn asset_root_prefers_configured_then_exe() {
+        let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let exe = manifest.join("target/debug/flaghack2");
+        let elsewhere = std::env::temp_dir();
+
+        let root = find_asset_root(None, Some(&exe), &elsewhere).expect("root above exe");
+        assert_eq!(root, manifest);
+        let root = find_asset_root(Some(manifest.to_path_buf()), None, &elsewhere)
+            .expect("configured root");
+        assert_eq!(root, manifest);
+
+        let err = find_asset_root(None, None, &elsewhere.join("flaghack2-missing")).unwrap_err();
+        assert!(matches!(err, AssetError::RootNotFound(ref searched) if searched.len() == 1));
+    }
 }
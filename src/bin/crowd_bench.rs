@@ -0,0 +1,80 @@
+//! Times simulation steps of Stress Test worlds with large hippie crowds.
+//!
+//! Usage: crowd_bench [--seed N]
+//!
+//! The crowd sizes go well past the game's default Stress Test count, to
+//! show how hippie updates scale. Build with `--release` for meaningful
+//! numbers.
+
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use flaghack2::args::parse_seed_only;
+use flaghack2::constants::SIM_STEP;
+use flaghack2::movement;
+use flaghack2::world::{ClassChoice, InputFrame, World, WorldConfig};
+use macroquad::prelude::Rect;
+
+const USAGE: &str = "usage: crowd_bench [--seed N]";
+const CROWD_SIZES: [usize; 3] = [100, 500, 2000];
+/// Ten seconds of play at the fixed step.
+const STEPS: u32 = 600;
+
+/// Walks in a square and drops and picks up flags now and then.
+fn input_for(step: u32) -> InputFrame {
+    let leg = step / 120 % 4;
+    InputFrame {
+        movement: movement::InputState {
+            up: leg == 0,
+            right: leg == 1,
+            down: leg == 2,
+            left: leg == 3,
+        },
+        place_flag: step.is_multiple_of(45),
+        pickup_flag: step % 45 == 20,
+        ..InputFrame::default()
+    }
+}
+
+fn run(size: usize, seed: u32) {
+    let mut config = WorldConfig::default();
+    config.tuning.stress_test.hippie_count = size;
+    let field = Rect::new(0.0, 0.0, 9000.0, 6000.0);
+
+    let start = Instant::now();
+    let mut world = World::new_with_config(ClassChoice::StressTest, field, seed, config);
+    let build = start.elapsed();
+
+    let mut slowest = Duration::ZERO;
+    let start = Instant::now();
+    for step in 0..STEPS {
+        let step_start = Instant::now();
+        world.step(SIM_STEP, input_for(step));
+        slowest = slowest.max(step_start.elapsed());
+    }
+    let total = start.elapsed();
+
+    println!(
+        "{:>5} hippies: build {:>10.3?}  step {:>10.3?} avg  {:>10.3?} max",
+        world.hippies.len(),
+        build,
+        total / STEPS,
+        slowest,
+    );
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let seed = match parse_seed_only(&args, 1) {
+        Ok(seed) => seed,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for size in CROWD_SIZES {
+        run(size, seed);
+    }
+    ExitCode::SUCCESS
+}
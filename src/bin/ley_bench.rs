@@ -0,0 +1,93 @@
+//! Times ley-line and sigil detection on dense flag clusters.
+//!
+//! Usage: ley_bench [--seed N]
+//!
+//! Each cluster drops every flag within `LEY_MAX_DISTANCE` of every other,
+//! the worst case for the proximity graph. Build with `--release` for
+//! meaningful numbers.
+
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use flaghack2::args::parse_seed_only;
+use flaghack2::constants::LEY_MAX_DISTANCE;
+use flaghack2::flags::{self, Flag};
+use flaghack2::ley_lines::LeyGraph;
+use flaghack2::rng::Rng;
+use flaghack2::spatial::SpatialHash;
+use macroquad::prelude::{vec2, Vec2};
+
+const USAGE: &str = "usage: ley_bench [--seed N]";
+const CLUSTER_SIZES: [usize; 3] = [50, 200, 1000];
+/// Flags added and then removed one at a time after each full build.
+const EDITS: usize = 50;
+
+/// A point in the disk whose diameter is `LEY_MAX_DISTANCE`.
+fn cluster_point(rng: &mut Rng) -> Vec2 {
+    let angle = rng.range(0.0, std::f32::consts::TAU);
+    let radius = LEY_MAX_DISTANCE * 0.5 * rng.next_f32().sqrt();
+    vec2(angle.cos(), angle.sin()) * radius
+}
+
+fn per_edit(total: Duration) -> Duration {
+    total / EDITS as u32
+}
+
+fn run(size: usize, rng: &mut Rng) {
+    let flags: Vec<Flag> = (0..size)
+        .map(|_| flags::make_flag(cluster_point(rng)))
+        .collect();
+    let mut spatial =
+        SpatialHash::from_positions(LEY_MAX_DISTANCE, flags.iter().map(|flag| flag.pos));
+
+    let start = Instant::now();
+    let mut graph = LeyGraph::build(&flags, &spatial, LEY_MAX_DISTANCE);
+    let build = start.elapsed();
+    let start = Instant::now();
+    let state = graph.state();
+    let output = start.elapsed();
+
+    let start = Instant::now();
+    for _ in 0..EDITS {
+        let pos = cluster_point(rng);
+        spatial.push(pos);
+        graph.insert(pos, &spatial);
+    }
+    let insert = start.elapsed();
+
+    let start = Instant::now();
+    for _ in 0..EDITS {
+        let index = rng.next_u32() as usize % graph.len();
+        spatial.swap_remove(index);
+        graph.swap_remove(index, &spatial);
+    }
+    let remove = start.elapsed();
+
+    println!(
+        "{:>5} flags: build {:>10.3?}  state {:>10.3?}  insert {:>10.3?}/flag  remove {:>10.3?}/flag  ({} lines, {} sigils)",
+        size,
+        build,
+        output,
+        per_edit(insert),
+        per_edit(remove),
+        state.lines.len(),
+        state.sigil_centers.len(),
+    );
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let seed = match parse_seed_only(&args, 1) {
+        Ok(seed) => seed,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut rng = Rng::new(seed);
+    for size in CLUSTER_SIZES {
+        run(size, &mut rng);
+    }
+    ExitCode::SUCCESS
+}
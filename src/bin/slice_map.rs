@@ -0,0 +1,92 @@
+//! Cuts a large map image into the tile grid the game loads.
+//!
+//! Usage: slice_map <source.png> [--out DIR] [--tile-size PX] [--levels N]
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use flaghack2::constants::MAP_TILE_DIR;
+use flaghack2::tile_slicer::{self, DEFAULT_TILE_SIZE};
+
+const USAGE: &str = "usage: slice_map <source.png> [--out DIR] [--tile-size PX] [--levels N]";
+
+struct Options {
+    source: PathBuf,
+    out_dir: PathBuf,
+    tile_size: u16,
+    levels: usize,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut source = None;
+    let mut out_dir = PathBuf::from(MAP_TILE_DIR);
+    let mut tile_size = DEFAULT_TILE_SIZE;
+    let mut levels = 0;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", name))
+        };
+        match arg.as_str() {
+            "--out" => out_dir = PathBuf::from(value("--out")?),
+            "--tile-size" => {
+                tile_size = value("--tile-size")?
+                    .parse()
+                    .ok()
+                    .filter(|size| *size >= 2 && size % 2 == 0)
+                    .ok_or("--tile-size must be an even number of pixels up to 65534")?;
+            }
+            "--levels" => {
+                levels = value("--levels")?
+                    .parse()
+                    .map_err(|_| "--levels must be a number".to_string())?;
+            }
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            path if source.is_none() => source = Some(PathBuf::from(path)),
+            extra => return Err(format!("unexpected argument {}", extra)),
+        }
+    }
+    Ok(Options {
+        source: source.ok_or("missing source image")?,
+        out_dir,
+        tile_size,
+        levels,
+    })
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    match tile_slicer::slice_map(
+        &options.source,
+        &options.out_dir,
+        options.tile_size,
+        options.levels,
+    ) {
+        Ok(manifest) => {
+            println!(
+                "wrote {}x{} tiles of {}px ({}x{} map, {} extra levels) to {}",
+                manifest.columns,
+                manifest.rows,
+                manifest.tile_size,
+                manifest.width,
+                manifest.height,
+                manifest.levels,
+                options.out_dir.display()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -33,6 +33,12 @@ impl CameraState {
     }
 }
 
+impl Default for CameraState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub fn view_size(screen: Vec2, zoom: f32) -> Vec2 {
     vec2(screen.x / zoom, screen.y / zoom)
 }
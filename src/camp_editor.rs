@@ -0,0 +1,319 @@
+use macroquad::prelude::*;
+
+use crate::camps::{CampConfig, CampSpawns};
+use crate::geom;
+
+pub const CAMP_EDITOR_COLORS: [Color; 6] = [
+    Color::new(0.1, 0.6, 0.2, 1.0),
+    Color::new(0.12, 0.55, 0.24, 1.0),
+    Color::new(0.11, 0.58, 0.23, 1.0),
+    Color::new(0.2, 0.45, 0.75, 1.0),
+    Color::new(0.75, 0.55, 0.15, 1.0),
+    Color::new(0.65, 0.25, 0.55, 1.0),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditorField {
+    Name,
+    Notice,
+}
+
+/// Why the polygon being edited would not make a usable camp region.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PolygonWarning {
+    TooFewVertices,
+    SelfIntersecting,
+    TriangulationFailed,
+}
+
+impl PolygonWarning {
+    pub fn message(self) -> &'static str {
+        match self {
+            PolygonWarning::TooFewVertices => "needs at least three vertices",
+            PolygonWarning::SelfIntersecting => "edges cross; triangulation will fail",
+            PolygonWarning::TriangulationFailed => "triangulation failed",
+        }
+    }
+}
+
+/// Editing state for one camp polygon. Positions are in map coordinates;
+/// the dungeon scene converts mouse input and draws the result.
+#[derive(Clone, Debug)]
+pub struct CampEditor {
+    pub name: String,
+    pub notice_text: String,
+    pub color: Color,
+    pub vertices: Vec<Vec2>,
+    /// Spawns of the camp being edited, carried through to the export.
+    pub spawns: CampSpawns,
+    pub selected: Option<usize>,
+    pub focus: EditorField,
+    /// Name the camp was loaded with, so an export after renaming replaces
+    /// the original file.
+    pub source_name: Option<String>,
+    color_index: usize,
+    dragging: Option<usize>,
+    triangles: Vec<[Vec2; 3]>,
+}
+
+impl CampEditor {
+    pub fn new() -> Self {
+        Self {
+            name: String::new(),
+            notice_text: String::new(),
+            color: CAMP_EDITOR_COLORS[0],
+            vertices: Vec::new(),
+            spawns: CampSpawns::default(),
+            selected: None,
+            focus: EditorField::Name,
+            source_name: None,
+            color_index: 0,
+            dragging: None,
+            triangles: Vec::new(),
+        }
+    }
+
+    pub fn from_camp(camp: &CampConfig) -> Self {
+        let mut editor = Self {
+            name: camp.name.clone(),
+            notice_text: camp.notice_text.clone(),
+            color: camp.color,
+            vertices: camp.vertices.clone(),
+            spawns: camp.spawns.clone(),
+            source_name: Some(camp.name.clone()),
+            color_index: CAMP_EDITOR_COLORS
+                .iter()
+                .position(|color| *color == camp.color)
+                .unwrap_or(0),
+            ..Self::new()
+        };
+        editor.retriangulate();
+        editor
+    }
+
+    pub fn triangles(&self) -> &[[Vec2; 3]] {
+        &self.triangles
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.dragging.is_some()
+    }
+
+    /// Nearest vertex within `radius` of `pos`.
+    pub fn vertex_at(&self, pos: Vec2, radius: f32) -> Option<usize> {
+        self.vertices
+            .iter()
+            .enumerate()
+            .map(|(index, vertex)| (index, vertex.distance(pos)))
+            .filter(|(_, distance)| *distance <= radius)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index)
+    }
+
+    /// Grabs the vertex under the cursor, or inserts a new one after the
+    /// selected vertex and grabs that.
+    pub fn press(&mut self, pos: Vec2, radius: f32) {
+        let index = match self.vertex_at(pos, radius) {
+            Some(index) => index,
+            None => {
+                let index = self.selected.map_or(self.vertices.len(), |i| i + 1);
+                self.vertices.insert(index, pos);
+                self.retriangulate();
+                index
+            }
+        };
+        self.selected = Some(index);
+        self.dragging = Some(index);
+    }
+
+    pub fn drag_to(&mut self, pos: Vec2) {
+        if let Some(index) = self.dragging {
+            self.vertices[index] = pos;
+            self.retriangulate();
+        }
+    }
+
+    pub fn release(&mut self) {
+        self.dragging = None;
+    }
+
+    pub fn delete_at(&mut self, pos: Vec2, radius: f32) -> bool {
+        match self.vertex_at(pos, radius) {
+            Some(index) => {
+                self.delete_vertex(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn delete_selected(&mut self) -> bool {
+        match self.selected {
+            Some(index) => {
+                self.delete_vertex(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn cycle_color(&mut self, step: isize) {
+        let count = CAMP_EDITOR_COLORS.len() as isize;
+        self.color_index = (self.color_index as isize + step).rem_euclid(count) as usize;
+        self.color = CAMP_EDITOR_COLORS[self.color_index];
+    }
+
+    pub fn next_field(&mut self) {
+        self.focus = match self.focus {
+            EditorField::Name => EditorField::Notice,
+            EditorField::Notice => EditorField::Name,
+        };
+    }
+
+    pub fn type_char(&mut self, c: char) {
+        if !c.is_control() {
+            self.focused_text().push(c);
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        self.focused_text().pop();
+    }
+
+    pub fn warning(&self) -> Option<PolygonWarning> {
+        let count = self.vertices.len();
+        if count < 3 {
+            Some(PolygonWarning::TooFewVertices)
+        } else if geom::polygon_self_intersects(&self.vertices) {
+            Some(PolygonWarning::SelfIntersecting)
+        } else if self.triangles.len() != count - 2 {
+            Some(PolygonWarning::TriangulationFailed)
+        } else {
+            None
+        }
+    }
+
+    /// The camp as it would be written to a camp file. An empty notice falls
+    /// back to the camp name.
+    pub fn to_camp(&self) -> CampConfig {
+        let name = self.name.trim().to_string();
+        let notice_text = match self.notice_text.trim() {
+            "" => name.clone(),
+            text => text.to_string(),
+        };
+        CampConfig {
+            name,
+            notice_text,
+            color: self.color,
+            vertices: self.vertices.clone(),
+            spawns: self.spawns.clone(),
+        }
+    }
+
+    fn focused_text(&mut self) -> &mut String {
+        match self.focus {
+            EditorField::Name => &mut self.name,
+            EditorField::Notice => &mut self.notice_text,
+        }
+    }
+
+    fn delete_vertex(&mut self, index: usize) {
+        self.vertices.remove(index);
+        self.dragging = None;
+        self.selected = match self.vertices.len() {
+            0 => None,
+            len => Some(index.saturating_sub(1).min(len - 1)),
+        };
+        self.retriangulate();
+    }
+
+    fn retriangulate(&mut self) {
+        self.triangles = geom::triangulate_polygon(&self.vertices);
+    }
+}
+
+impl Default for CampEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square_editor() -> CampEditor {
+        let mut editor = CampEditor::new();
+        for pos in [
+            vec2(0.0, 0.0),
+            vec2(10.0, 0.0),
+            vec2(10.0, 10.0),
+            vec2(0.0, 10.0),
+        ] {
+            editor.press(pos, 1.0);
+            editor.release();
+        }
+        editor
+    }
+
+    #[test]
+    fn clicks_add_vertices_and_triangulate_live() {
+        let mut editor = CampEditor::new();
+        editor.press(vec2(0.0, 0.0), 1.0);
+        editor.release();
+        assert_eq!(editor.warning(), Some(PolygonWarning::TooFewVertices));
+
+        let editor = square_editor();
+        assert_eq!(editor.vertices.len(), 4);
+        assert_eq!(editor.triangles().len(), 2);
+        assert_eq!(editor.warning(), None);
+        assert_eq!(editor.selected, Some(3));
+    }
+
+    #[test]
+    fn dragging_and_deleting_vertices() {
+        let mut editor = square_editor();
+        editor.press(vec2(10.5, 0.5), 1.0);
+        assert!(editor.is_dragging());
+        editor.drag_to(vec2(12.0, -2.0));
+        editor.release();
+        assert_eq!(editor.vertices[1], vec2(12.0, -2.0));
+        assert_eq!(editor.vertices.len(), 4);
+
+        assert!(editor.delete_at(vec2(12.0, -2.0), 1.0));
+        assert_eq!(editor.vertices.len(), 3);
+        assert_eq!(editor.triangles().len(), 1);
+        assert!(!editor.delete_at(vec2(50.0, 50.0), 1.0));
+    }
+
+    #[test]
+    fn warns_when_edges_cross() {
+        let mut editor = square_editor();
+        editor.vertices.swap(1, 2);
+        editor.retriangulate();
+        assert_eq!(editor.warning(), Some(PolygonWarning::SelfIntersecting));
+    }
+
+    #[test]
+    fn export_uses_text_fields_and_keeps_spawns() {
+        let camp = crate::camps::camp_configs().remove(1);
+        let mut editor = CampEditor::from_camp(&camp);
+        assert_eq!(editor.to_camp(), camp);
+
+        editor.name.clear();
+        for c in "Dome Town\n".chars() {
+            editor.type_char(c);
+        }
+        editor.next_field();
+        editor.notice_text.clear();
+        editor.type_char('x');
+        editor.backspace();
+        editor.cycle_color(-1);
+
+        let exported = editor.to_camp();
+        assert_eq!(exported.name, "Dome Town");
+        assert_eq!(exported.notice_text, "Dome Town");
+        assert_eq!(exported.color, CAMP_EDITOR_COLORS[0]);
+        assert_eq!(exported.spawns, camp.spawns);
+    }
+}
@@ -1,90 +1,279 @@
 use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
 
 use crate::geom;
-use crate::scenery::{ScenerySpawn, TENT_VARIANT_COUNT};
+use crate::scenery::ScenerySpawn;
 
-#[derive(Clone, Debug)]
+pub const CAMP_DIR: &str = "assets/camps";
+
+/// One camp as written in a camp file. Files in `CAMP_DIR` are loaded in
+/// file-name order, which also fixes each camp's index.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct CampConfig {
-    pub name: &'static str,
-    pub vertices: Vec<Vec2>,
+    pub name: String,
+    pub notice_text: String,
+    #[serde(with = "rgba")]
     pub color: Color,
-    pub notice_text: &'static str,
+    #[serde(with = "geom::xy_list")]
+    pub vertices: Vec<Vec2>,
+    #[serde(default)]
     pub spawns: CampSpawns,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CampSpawns {
-    pub scenery: Vec<ScenerySpawn>,
+    #[serde(with = "geom::xy_list")]
     pub flags: Vec<Vec2>,
+    #[serde(with = "geom::xy_list")]
     pub hippies: Vec<Vec2>,
+    pub scenery: Vec<ScenerySpawn>,
 }
 
-const T3MPCAMP_NAME: &str = "t3mpcamp";
-const T3MPCAMP_NOTICE: &str = "t3mpcamp.com";
-const T3MPCAMP_COLOR: Color = Color::new(0.1, 0.6, 0.2, 1.0);
-const T3MPCAMP_VERTICES: [Vec2; 5] = [
-    Vec2::new(4858.0, 3168.0),
-    Vec2::new(5042.0, 3107.0),
-    Vec2::new(5123.0, 3345.0),
-    Vec2::new(5054.0, 3367.0),
-    Vec2::new(4911.0, 3322.0),
-];
-const T3MPCAMP_CAMPFIRE_POS: Vec2 = Vec2::new(4982.0, 3233.0);
-const T3MPCAMP_TENT_SPACING: f32 = 14.0;
-const T3MPCAMP_ROW1_START: Vec2 = Vec2::new(4926.0, 3300.0);
-const T3MPCAMP_ROW1_END: Vec2 = Vec2::new(5000.0, 3300.0);
-const T3MPCAMP_ROW2_START: Vec2 = Vec2::new(4926.0, 3317.0);
-const T3MPCAMP_ROW2_END: Vec2 = Vec2::new(5000.0, 3317.0);
-const T3MPCAMP_CAMPFIRE_SCALE: f32 = 1.5;
-
-const GEORGIA_PEANUTS_NAME: &str = "Georgia Peanuts";
-const GEORGIA_PEANUTS_NOTICE: &str = "Georgia Peanuts";
-const GEORGIA_PEANUTS_COLOR: Color = Color::new(0.12, 0.55, 0.24, 1.0);
-const GEORGIA_PEANUTS_VERTICES: [Vec2; 4] = [
-    Vec2::new(5123.0, 3345.0),
-    Vec2::new(5042.0, 3107.0),
-    Vec2::new(5255.0, 3037.0),
-    Vec2::new(5329.0, 3274.0),
-];
+#[derive(Debug)]
+pub enum CampError {
+    Io(std::io::Error),
+    Parse {
+        path: PathBuf,
+        error: toml::de::Error,
+    },
+    Serialize(toml::ser::Error),
+    MissingName,
+    BadPolygon {
+        camp: String,
+        reason: &'static str,
+    },
+    SpawnOutOfMap {
+        camp: String,
+        kind: &'static str,
+        pos: Vec2,
+    },
+    DuplicateName(String),
+}
+
+impl fmt::Display for CampError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CampError::Io(err) => write!(f, "camp io error: {}", err),
+            CampError::Parse { path, error } => {
+                write!(f, "camp parse error in {}: {}", path.display(), error)
+            }
+            CampError::Serialize(err) => write!(f, "camp serialize error: {}", err),
+            CampError::MissingName => write!(f, "camp has no name"),
+            CampError::BadPolygon { camp, reason } => {
+                write!(f, "camp {} has a bad polygon: {}", camp, reason)
+            }
+            CampError::SpawnOutOfMap { camp, kind, pos } => write!(
+                f,
+                "camp {} has a {} spawn outside the map at ({}, {})",
+                camp, kind, pos.x, pos.y
+            ),
+            CampError::DuplicateName(name) => write!(f, "camp {} is defined twice", name),
+        }
+    }
+}
 
-const DEBUSSY_BUS_NAME: &str = "DeBussy Bus Station";
-const DEBUSSY_BUS_NOTICE: &str = "DeBussy Bus Station";
-const DEBUSSY_BUS_COLOR: Color = Color::new(0.11, 0.58, 0.23, 1.0);
-const DEBUSSY_BUS_VERTICES: [Vec2; 4] = [
-    Vec2::new(4850.0, 3134.0),
-    Vec2::new(4784.0, 2933.0),
-    Vec2::new(4913.0, 2894.0),
-    Vec2::new(4975.0, 3092.0),
+impl std::error::Error for CampError {}
+
+impl From<std::io::Error> for CampError {
+    fn from(err: std::io::Error) -> Self {
+        CampError::Io(err)
+    }
+}
+
+/// The camps shipped in `CAMP_DIR`, compiled in so headless worlds, saves and
+/// replays do not depend on the working directory.
+const BUILTIN_CAMPS: [(&str, &str); 3] = [
+    (
+        "01-t3mpcamp.toml",
+        include_str!("../assets/camps/01-t3mpcamp.toml"),
+    ),
+    (
+        "02-georgia-peanuts.toml",
+        include_str!("../assets/camps/02-georgia-peanuts.toml"),
+    ),
+    (
+        "03-debussy-bus-station.toml",
+        include_str!("../assets/camps/03-debussy-bus-station.toml"),
+    ),
 ];
 
 pub fn camp_configs() -> Vec<CampConfig> {
-    let mut camps = Vec::new();
-
-    camps.push(CampConfig {
-        name: T3MPCAMP_NAME,
-        vertices: T3MPCAMP_VERTICES.to_vec(),
-        color: T3MPCAMP_COLOR,
-        notice_text: T3MPCAMP_NOTICE,
-        spawns: t3mpcamp_spawns(),
-    });
-
-    camps.push(CampConfig {
-        name: GEORGIA_PEANUTS_NAME,
-        vertices: GEORGIA_PEANUTS_VERTICES.to_vec(),
-        color: GEORGIA_PEANUTS_COLOR,
-        notice_text: GEORGIA_PEANUTS_NOTICE,
-        spawns: georgia_peanuts_spawns(),
-    });
-
-    camps.push(CampConfig {
-        name: DEBUSSY_BUS_NAME,
-        vertices: DEBUSSY_BUS_VERTICES.to_vec(),
-        color: DEBUSSY_BUS_COLOR,
-        notice_text: DEBUSSY_BUS_NOTICE,
-        spawns: debussy_bus_spawns(),
-    });
-
-    camps
+    BUILTIN_CAMPS
+        .iter()
+        .map(|(file, text)| {
+            CampConfig::from_toml(Path::new(CAMP_DIR).join(file), text)
+                .expect("built-in camp file should parse")
+        })
+        .collect()
+}
+
+impl CampConfig {
+    /// Parses a camp file. `path` is only used to label errors.
+    pub fn from_toml<P: Into<PathBuf>>(path: P, text: &str) -> Result<Self, CampError> {
+        toml::from_str(text).map_err(|error| CampError::Parse {
+            path: path.into(),
+            error,
+        })
+    }
+
+    pub fn to_toml(&self) -> Result<String, CampError> {
+        toml::to_string(self).map_err(CampError::Serialize)
+    }
+
+    /// Checks that the camp is named, that the polygon is a usable simple
+    /// polygon, and that it and every spawn lie inside the map.
+    pub fn validate(&self, field: Rect) -> Result<(), CampError> {
+        if self.name.trim().is_empty() {
+            return Err(CampError::MissingName);
+        }
+        let bad_polygon = |reason| CampError::BadPolygon {
+            camp: self.name.clone(),
+            reason,
+        };
+        if self.vertices.len() < 3 {
+            return Err(bad_polygon("fewer than three vertices"));
+        }
+        if self
+            .vertices
+            .iter()
+            .any(|v| !v.x.is_finite() || !v.y.is_finite())
+        {
+            return Err(bad_polygon("non-finite vertex"));
+        }
+        if self.vertices.iter().any(|v| !field.contains(*v)) {
+            return Err(bad_polygon("vertex outside the map"));
+        }
+        if geom::polygon_area(&self.vertices).abs() < f32::EPSILON {
+            return Err(bad_polygon("zero area"));
+        }
+        if geom::polygon_self_intersects(&self.vertices) {
+            return Err(bad_polygon("edges cross each other"));
+        }
+
+        let scenery = self
+            .spawns
+            .scenery
+            .iter()
+            .map(|spawn| ("scenery", spawn.pos));
+        let flags = self.spawns.flags.iter().map(|pos| ("flag", *pos));
+        let hippies = self.spawns.hippies.iter().map(|pos| ("hippie", *pos));
+        for (kind, pos) in scenery.chain(flags).chain(hippies) {
+            if !field.contains(pos) {
+                return Err(CampError::SpawnOutOfMap {
+                    camp: self.name.clone(),
+                    kind,
+                    pos,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn load_camp_file<P: AsRef<Path>>(path: P, field: Rect) -> Result<CampConfig, CampError> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)?;
+    let camp = CampConfig::from_toml(path, &text)?;
+    camp.validate(field)?;
+    Ok(camp)
+}
+
+/// Camps read from a directory, and the files that were left out.
+#[derive(Debug, Default)]
+pub struct CampDir {
+    pub camps: Vec<CampConfig>,
+    pub skipped: Vec<(PathBuf, CampError)>,
+}
+
+/// Loads every `.toml` file in `dir`, sorted by file name. A file that fails
+/// to load or validate, or repeats an earlier camp's name, is skipped and
+/// reported; the rest still load.
+pub fn load_camp_dir<P: AsRef<Path>>(dir: P, field: Rect) -> Result<CampDir, CampError> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) == Some("toml") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut loaded = CampDir::default();
+    for path in paths {
+        match load_camp_file(&path, field) {
+            Ok(camp) if loaded.camps.iter().any(|other| other.name == camp.name) => {
+                loaded
+                    .skipped
+                    .push((path, CampError::DuplicateName(camp.name)));
+            }
+            Ok(camp) => loaded.camps.push(camp),
+            Err(err) => loaded.skipped.push((path, err)),
+        }
+    }
+    Ok(loaded)
+}
+
+/// File name for a new camp file. Names sort after the numbered built-in
+/// files, so existing camps keep their indices.
+pub fn camp_file_name(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "camp.toml".to_string()
+    } else {
+        format!("{}.toml", slug)
+    }
+}
+
+/// Finds the file in `dir` that defines the camp called `name`. Files that
+/// fail to parse are skipped.
+pub fn find_camp_file<P: AsRef<Path>>(dir: P, name: &str) -> Result<Option<PathBuf>, CampError> {
+    let dir = dir.as_ref();
+    if !dir.exists() {
+        return Ok(None);
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+            continue;
+        }
+        let text = std::fs::read_to_string(&path)?;
+        if let Ok(camp) = CampConfig::from_toml(&path, &text) {
+            if camp.name == name {
+                return Ok(Some(path));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Writes `camp` into `dir`, replacing the file that defines the camp named
+/// `replaces` (its old name when renaming). Returns the path written.
+pub fn export_camp<P: AsRef<Path>>(
+    camp: &CampConfig,
+    replaces: &str,
+    dir: P,
+    field: Rect,
+) -> Result<PathBuf, CampError> {
+    camp.validate(field)?;
+    let dir = dir.as_ref();
+    let path = match find_camp_file(dir, replaces)? {
+        Some(path) => path,
+        None => dir.join(camp_file_name(&camp.name)),
+    };
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(&path, camp.to_toml()?)?;
+    Ok(path)
 }
 
 pub fn collect_scenery_spawns(camps: &[CampConfig]) -> Vec<ScenerySpawn> {
@@ -107,102 +296,19 @@ pub fn collect_camp_vertices(camps: &[CampConfig]) -> Vec<Vec<Vec2>> {
     camps.iter().map(|camp| camp.vertices.clone()).collect()
 }
 
-fn t3mpcamp_spawns() -> CampSpawns {
-    let mut spawns = CampSpawns::default();
-
-    spawns.scenery.push(ScenerySpawn::campfire(
-        T3MPCAMP_CAMPFIRE_POS,
-        T3MPCAMP_CAMPFIRE_SCALE,
-    ));
-
-    let rows = [
-        (T3MPCAMP_ROW1_START, T3MPCAMP_ROW1_END),
-        (T3MPCAMP_ROW2_START, T3MPCAMP_ROW2_END),
-    ];
-    let mut tent_index = 0;
-    for (_row_index, (start, end)) in rows.iter().enumerate() {
-        let positions = geom::line_points(*start, *end, T3MPCAMP_TENT_SPACING);
-        for pos in positions {
-            let variant = (tent_index % TENT_VARIANT_COUNT as usize) as u8;
-            spawns.scenery.push(ScenerySpawn::tent(pos, variant));
-            tent_index += 1;
-        }
-    }
-
-    spawns.hippies = vec![
-        T3MPCAMP_CAMPFIRE_POS + vec2(-28.0, -16.0),
-        T3MPCAMP_CAMPFIRE_POS + vec2(26.0, 14.0),
-        T3MPCAMP_CAMPFIRE_POS + vec2(-12.0, 22.0),
-        T3MPCAMP_CAMPFIRE_POS + vec2(18.0, -24.0),
-    ];
-
-    spawns
-}
-
-fn georgia_peanuts_spawns() -> CampSpawns {
-    let mut spawns = CampSpawns::default();
-
-    spawns.scenery.extend([
-        ScenerySpawn::campfire(vec2(5200.0, 3180.0), 1.0),
-        ScenerySpawn::campfire(vec2(5280.0, 3230.0), 1.0),
-        ScenerySpawn::chair(vec2(5170.0, 3210.0), 0.4),
-        ScenerySpawn::chair(vec2(5190.0, 3160.0), -0.3),
-        ScenerySpawn::chair(vec2(5255.0, 3220.0), 0.6),
-        ScenerySpawn::chair(vec2(5295.0, 3250.0), -0.2),
-        ScenerySpawn::tent(vec2(5150.0, 3300.0), 0),
-        ScenerySpawn::tent(vec2(5180.0, 3320.0), 1),
-        ScenerySpawn::tent(vec2(5210.0, 3290.0), 2),
-        ScenerySpawn::tent(vec2(5230.0, 3300.0), 3),
-        ScenerySpawn::tent(vec2(5190.0, 3265.0), 4),
-    ]);
-
-    spawns.flags = vec![
-        vec2(5090.0, 3200.0),
-        vec2(5160.0, 3220.0),
-        vec2(5200.0, 3180.0),
-        vec2(5220.0, 3100.0),
-        vec2(5270.0, 3150.0),
-    ];
-
-    spawns.hippies = vec![
-        vec2(5175.0, 3190.0),
-        vec2(5215.0, 3205.0),
-        vec2(5265.0, 3235.0),
-    ];
+/// Colors are written as `[r, g, b, a]` arrays.
+mod rgba {
+    use macroquad::prelude::Color;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-    spawns
-}
-
-fn debussy_bus_spawns() -> CampSpawns {
-    let mut spawns = CampSpawns::default();
-
-    spawns.scenery.extend([
-        ScenerySpawn::campfire(vec2(4860.0, 3050.0), 1.0),
-        ScenerySpawn::campfire(vec2(4935.0, 3055.0), 1.0),
-        ScenerySpawn::chair(vec2(4880.0, 2990.0), 0.3),
-        ScenerySpawn::chair(vec2(4825.0, 3000.0), -0.4),
-        ScenerySpawn::chair(vec2(4910.0, 3005.0), 0.5),
-        ScenerySpawn::tent(vec2(4870.0, 3080.0), 0),
-        ScenerySpawn::tent(vec2(4895.0, 3020.0), 1),
-        ScenerySpawn::tent(vec2(4925.0, 2965.0), 2),
-        ScenerySpawn::tent(vec2(4950.0, 3060.0), 3),
-    ]);
-
-    spawns.flags = vec![
-        vec2(4840.0, 3040.0),
-        vec2(4860.0, 3050.0),
-        vec2(4890.0, 2960.0),
-        vec2(4920.0, 3040.0),
-        vec2(4940.0, 3070.0),
-    ];
-
-    spawns.hippies = vec![
-        vec2(4860.0, 3050.0),
-        vec2(4895.0, 3020.0),
-        vec2(4935.0, 3055.0),
-    ];
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        [color.r, color.g, color.b, color.a].serialize(serializer)
+    }
 
-    spawns
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let [r, g, b, a] = <[f32; 4]>::deserialize(deserializer)?;
+        Ok(Color::new(r, g, b, a))
+    }
 }
 
 #[cfg(test)]
@@ -214,7 +320,7 @@ mod tests {
         let camps = camp_configs();
         let georgia = camps
             .iter()
-            .find(|camp| camp.name == GEORGIA_PEANUTS_NAME)
+            .find(|camp| camp.name == "Georgia Peanuts")
             .expect("Georgia Peanuts camp");
 
         let campfires = georgia
@@ -248,7 +354,7 @@ mod tests {
         let camps = camp_configs();
         let bus = camps
             .iter()
-            .find(|camp| camp.name == DEBUSSY_BUS_NAME)
+            .find(|camp| camp.name == "DeBussy Bus Station")
             .expect("DeBussy Bus Station camp");
 
         let campfires = bus
@@ -276,4 +382,142 @@ mod tests {
         assert!(!bus.spawns.flags.is_empty());
         assert!(!bus.spawns.hippies.is_empty());
     }
+
+    fn test_field() -> Rect {
+        Rect::new(0.0, 0.0, 9000.0, 6000.0)
+    }
+
+    fn square_camp() -> CampConfig {
+        CampConfig::from_toml(
+            "square.toml",
+            r#"
+name = "Square"
+notice_text = "Square"
+color = [0.1, 0.2, 0.3, 1.0]
+vertices = [[100.0, 100.0], [200.0, 100.0], [200.0, 200.0], [100.0, 200.0]]
+
+[spawns]
+hippies = [[150.0, 150.0]]
+
+[[spawns.scenery]]
+kind = "tent"
+pos = [120.0, 120.0]
+variant = 2
+"#,
+        )
+        .expect("parse camp")
+    }
+
+    #[test]
+    fn builtin_camps_are_valid_and_ordered() {
+        let camps = camp_configs();
+        let names: Vec<_> = camps.iter().map(|camp| camp.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["t3mpcamp", "Georgia Peanuts", "DeBussy Bus Station"]
+        );
+        for camp in &camps {
+            camp.validate(test_field()).expect("valid camp");
+        }
+    }
+
+    #[test]
+    fn camp_file_fills_spawn_defaults() {
+        let camp = square_camp();
+        assert!(camp.spawns.flags.is_empty());
+        let tent = &camp.spawns.scenery[0];
+        assert_eq!(tent.kind, crate::scenery::SceneryKind::Tent);
+        assert_eq!(tent.scale, 1.0);
+        assert_eq!(tent.variant, 2);
+        assert!(camp.validate(test_field()).is_ok());
+    }
+
+    #[test]
+    fn validation_rejects_bad_polygons_and_out_of_map_spawns() {
+        let mut bowtie = square_camp();
+        bowtie.vertices.swap(1, 2);
+        assert!(matches!(
+            bowtie.validate(test_field()),
+            Err(CampError::BadPolygon { .. })
+        ));
+
+        let mut line = square_camp();
+        line.vertices.truncate(2);
+        assert!(matches!(
+            line.validate(test_field()),
+            Err(CampError::BadPolygon { .. })
+        ));
+
+        let mut stray = square_camp();
+        stray.spawns.flags.push(vec2(-5.0, 150.0));
+        match stray.validate(test_field()) {
+            Err(CampError::SpawnOutOfMap { kind, .. }) => assert_eq!(kind, "flag"),
+            other => panic!("expected out-of-map error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn camp_dir_loads_sorted_and_skips_bad_files() {
+        let dir = std::env::temp_dir().join(format!("flaghack2-camps-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let text = toml::to_string(&square_camp()).expect("serialize camp");
+        let mut second = square_camp();
+        second.name = "Another".to_string();
+        std::fs::write(dir.join("b.toml"), &text).expect("write camp");
+        std::fs::write(
+            dir.join("a.toml"),
+            toml::to_string(&second).expect("serialize camp"),
+        )
+        .expect("write camp");
+        std::fs::write(dir.join("notes.txt"), "ignored").expect("write notes");
+
+        let loaded = load_camp_dir(&dir, test_field()).expect("load camps");
+        let names: Vec<_> = loaded.camps.iter().map(|camp| camp.name.as_str()).collect();
+        assert_eq!(names, ["Another", "Square"]);
+        assert!(loaded.skipped.is_empty());
+
+        std::fs::write(dir.join("c.toml"), &text).expect("write camp");
+        std::fs::write(dir.join("d.toml"), "name = ").expect("write camp");
+        let loaded = load_camp_dir(&dir, test_field()).expect("load camps");
+        let names: Vec<_> = loaded.camps.iter().map(|camp| camp.name.as_str()).collect();
+        assert_eq!(names, ["Another", "Square"]);
+        match loaded.skipped.as_slice() {
+            [(duplicate, CampError::DuplicateName(_)), (broken, CampError::Parse { .. })] => {
+                assert_eq!(duplicate, &dir.join("c.toml"));
+                assert_eq!(broken, &dir.join("d.toml"));
+            }
+            other => panic!("expected two skipped files, got {:?}", other),
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn export_names_new_files_and_replaces_existing_ones() {
+        assert_eq!(
+            camp_file_name("  DeBussy Bus Station!"),
+            "debussy-bus-station.toml"
+        );
+        assert_eq!(camp_file_name("***"), "camp.toml");
+
+        let dir =
+            std::env::temp_dir().join(format!("flaghack2-camp-export-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let mut camp = square_camp();
+        let path = export_camp(&camp, "Square", &dir, test_field()).expect("export camp");
+        assert_eq!(path, dir.join("square.toml"));
+
+        std::fs::rename(&path, dir.join("01-square.toml")).expect("rename camp");
+        camp.name = "Renamed".to_string();
+        let path = export_camp(&camp, "Square", &dir, test_field()).expect("export camp");
+        assert_eq!(path, dir.join("01-square.toml"));
+        let loaded = load_camp_dir(&dir, test_field()).expect("load camps");
+        assert_eq!(loaded.camps, vec![camp.clone()]);
+
+        camp.name = " ".to_string();
+        assert!(matches!(
+            export_camp(&camp, "Renamed", &dir, test_field()),
+            Err(CampError::MissingName)
+        ));
+        std::fs::remove_dir_all(&dir).ok();
+    }
 }
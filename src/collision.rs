@@ -0,0 +1,360 @@
+use macroquad::prelude::*;
+
+use crate::geom;
+use crate::scale;
+use crate::scenery::{SceneryItem, SceneryKind, DOME_DOOR_HALF_ANGLE, DOME_RADIUS, DOME_SQUASH};
+
+/// Wall pieces around a dome's ground ring, doorway included.
+const DOME_WALL_SEGMENTS: usize = 24;
+const DOME_WALL_THICKNESS: f32 = 8.0 * scale::MODEL_SCALE;
+/// Overlaps are pushed apart this many times per move so a walker squeezed
+/// between two obstacles settles instead of bouncing into one of them.
+const RESOLVE_PASSES: usize = 3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Circle {
+        center: Vec2,
+        radius: f32,
+    },
+    /// A `size` rectangle turned by `rotation` radians about its center.
+    Rect {
+        center: Vec2,
+        size: Vec2,
+        rotation: f32,
+    },
+    /// A simple polygon in either winding.
+    Polygon(Vec<Vec2>),
+}
+
+/// A solid shape plus the bounding circle used to skip it cheaply.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collider {
+    pub shape: Shape,
+    center: Vec2,
+    reach: f32,
+}
+
+impl Collider {
+    pub fn new(shape: Shape) -> Self {
+        let (center, reach) = match &shape {
+            Shape::Circle { center, radius } => (*center, *radius),
+            Shape::Rect { center, size, .. } => (*center, size.length() * 0.5),
+            Shape::Polygon(vertices) => {
+                let (min, max) = geom::polygon_bounds(vertices).unwrap_or_default();
+                ((min + max) * 0.5, (max - min).length() * 0.5)
+            }
+        };
+        Self {
+            shape,
+            center,
+            reach,
+        }
+    }
+
+    /// Center and radius of a circle that encloses the shape.
+    pub fn bounding_circle(&self) -> (Vec2, f32) {
+        (self.center, self.reach)
+    }
+
+    /// The move that takes a circle at `pos` with `radius` out of this
+    /// shape along the contact normal, or `None` when they do not overlap.
+    pub fn push_out(&self, pos: Vec2, radius: f32) -> Option<Vec2> {
+        let reach = self.reach + radius;
+        if pos.distance_squared(self.center) >= reach * reach {
+            return None;
+        }
+        match &self.shape {
+            Shape::Circle {
+                center,
+                radius: size,
+            } => {
+                let delta = pos - *center;
+                let dist = delta.length();
+                let overlap = size + radius - dist;
+                if overlap <= 0.0 {
+                    return None;
+                }
+                let normal = if dist > 0.0 { delta / dist } else { Vec2::Y };
+                Some(normal * overlap)
+            }
+            Shape::Rect {
+                center,
+                size,
+                rotation,
+            } => {
+                let turn = Vec2::from_angle(*rotation);
+                let local = vec2(turn.x, -turn.y).rotate(pos - *center);
+                push_out_of_box(local, *size * 0.5, radius).map(|push| turn.rotate(push))
+            }
+            Shape::Polygon(vertices) => push_out_of_polygon(vertices, pos, radius),
+        }
+    }
+}
+
+fn push_out_of_box(local: Vec2, half: Vec2, radius: f32) -> Option<Vec2> {
+    let closest = local.clamp(-half, half);
+    if closest != local {
+        let delta = local - closest;
+        let dist = delta.length();
+        if dist >= radius {
+            return None;
+        }
+        return Some(delta / dist * (radius - dist));
+    }
+    // Inside: leave through the nearest face.
+    let depth = half - local.abs();
+    let sign = |value: f32| if value < 0.0 { -1.0 } else { 1.0 };
+    Some(if depth.x < depth.y {
+        vec2(sign(local.x) * (depth.x + radius), 0.0)
+    } else {
+        vec2(0.0, sign(local.y) * (depth.y + radius))
+    })
+}
+
+fn push_out_of_polygon(vertices: &[Vec2], pos: Vec2, radius: f32) -> Option<Vec2> {
+    let mut closest = *vertices.first()?;
+    let mut best = f32::INFINITY;
+    for (index, start) in vertices.iter().enumerate() {
+        let end = vertices[(index + 1) % vertices.len()];
+        let point = closest_on_segment(pos, *start, end);
+        let dist = pos.distance_squared(point);
+        if dist < best {
+            best = dist;
+            closest = point;
+        }
+    }
+    let dist = best.sqrt();
+    if geom::point_in_polygon(pos, vertices) {
+        let inward = closest - pos;
+        let normal = if dist > 0.0 { inward / dist } else { Vec2::Y };
+        return Some(inward + normal * radius);
+    }
+    if dist >= radius {
+        return None;
+    }
+    let normal = if dist > 0.0 {
+        (pos - closest) / dist
+    } else {
+        Vec2::Y
+    };
+    Some(normal * (radius - dist))
+}
+
+fn closest_on_segment(point: Vec2, start: Vec2, end: Vec2) -> Vec2 {
+    let edge = end - start;
+    let length_sq = edge.length_squared();
+    if length_sq <= f32::EPSILON {
+        return start;
+    }
+    let t = ((point - start).dot(edge) / length_sq).clamp(0.0, 1.0);
+    start + edge * t
+}
+
+/// Ground footprints of one scenery item, with the scale and rotation it is
+/// drawn with. The crow stands on its base and adds nothing of its own.
+pub fn scenery_colliders(item: &SceneryItem) -> Vec<Collider> {
+    let s = scale::MODEL_SCALE * item.scale;
+    let pos = item.pos;
+    let shapes = match item.kind {
+        SceneryKind::Tree => vec![Shape::Circle {
+            center: pos + vec2(0.0, 4.0 * s),
+            radius: 5.0 * s,
+        }],
+        SceneryKind::Tent => vec![Shape::Rect {
+            center: pos - vec2(0.0, 5.0 * s),
+            size: vec2(50.0 * s, 10.0 * s),
+            rotation: item.drawn_rotation(),
+        }],
+        SceneryKind::Chair => vec![Shape::Rect {
+            center: pos,
+            size: vec2(20.0 * s, 12.0 * s),
+            rotation: item.drawn_rotation(),
+        }],
+        SceneryKind::Campfire => vec![Shape::Circle {
+            center: pos,
+            radius: 14.0 * s,
+        }],
+        SceneryKind::CrowBase => {
+            let (bottom, top, depth) = (140.0 * s, 85.0 * s, 56.0 * s);
+            vec![Shape::Polygon(vec![
+                pos + vec2(-bottom, 0.0),
+                pos + vec2(bottom, 0.0),
+                pos + vec2(top, -depth),
+                pos + vec2(-top, -depth),
+            ])]
+        }
+        SceneryKind::Crow => Vec::new(),
+        SceneryKind::Dome => dome_walls(pos, item.scale),
+    };
+    shapes.into_iter().map(Collider::new).collect()
+}
+
+/// Thin wall pieces around the dome's ground ring, leaving out those in
+/// the doorway at the front.
+fn dome_walls(center: Vec2, item_scale: f32) -> Vec<Shape> {
+    let radius = vec2(DOME_RADIUS, DOME_RADIUS * DOME_SQUASH) * item_scale;
+    let ring_point = |angle: f32| center + vec2(angle.cos(), angle.sin()) * radius;
+    let step = std::f32::consts::TAU / DOME_WALL_SEGMENTS as f32;
+    let front = std::f32::consts::FRAC_PI_2;
+
+    (0..DOME_WALL_SEGMENTS)
+        .filter_map(|index| {
+            let start = index as f32 * step;
+            let middle = start + step * 0.5;
+            let from_front = (middle - front).abs();
+            if from_front < DOME_DOOR_HALF_ANGLE {
+                return None;
+            }
+            let (a, b) = (ring_point(start), ring_point(start + step));
+            let edge = b - a;
+            Some(Shape::Rect {
+                center: (a + b) * 0.5,
+                size: vec2(edge.length(), DOME_WALL_THICKNESS * item_scale),
+                rotation: edge.y.atan2(edge.x),
+            })
+        })
+        .collect()
+}
+
+pub fn build_colliders(items: &[SceneryItem]) -> Vec<Collider> {
+    items.iter().flat_map(scenery_colliders).collect()
+}
+
+/// Moves a circle at `pos` out of every collider it overlaps. Only the part
+/// of a move that runs into an obstacle is undone, so walkers slide along
+/// walls instead of stopping dead.
+pub fn resolve_circle<'a>(
+    pos: Vec2,
+    radius: f32,
+    colliders: impl IntoIterator<Item = &'a Collider> + Clone,
+) -> Vec2 {
+    let mut pos = pos;
+    for _ in 0..RESOLVE_PASSES {
+        let mut moved = false;
+        for collider in colliders.clone() {
+            if let Some(push) = collider.push_out(pos, radius) {
+                pos += push;
+                moved = true;
+            }
+        }
+        if !moved {
+            break;
+        }
+    }
+    pos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(kind: SceneryKind, pos: Vec2) -> SceneryItem {
+        SceneryItem {
+            kind,
+            pos,
+            scale: 1.0,
+            rotation: 0.0,
+            variant: 0,
+            decorations: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn shapes_push_overlapping_circles_out() {
+        let circle = Collider::new(Shape::Circle {
+            center: Vec2::ZERO,
+            radius: 10.0,
+        });
+        let push = circle.push_out(vec2(8.0, 0.0), 4.0).expect("overlap");
+        assert!((push - vec2(6.0, 0.0)).length() < 0.001);
+        assert_eq!(circle.push_out(vec2(20.0, 0.0), 4.0), None);
+
+        let rect = Collider::new(Shape::Rect {
+            center: Vec2::ZERO,
+            size: vec2(20.0, 10.0),
+            rotation: std::f32::consts::FRAC_PI_2,
+        });
+        // Turned a quarter, the rect is tall: its side is 5 units out.
+        let push = rect.push_out(vec2(4.0, 0.0), 2.0).expect("inside");
+        assert!((push - vec2(3.0, 0.0)).length() < 0.001);
+
+        let square = Collider::new(Shape::Polygon(vec![
+            vec2(0.0, 0.0),
+            vec2(10.0, 0.0),
+            vec2(10.0, 10.0),
+            vec2(0.0, 10.0),
+        ]));
+        let push = square.push_out(vec2(5.0, 11.0), 2.0).expect("touching");
+        assert!((push - vec2(0.0, 1.0)).length() < 0.001);
+    }
+
+    #[test]
+    fn resolution_slides_along_walls() {
+        let wall = [Collider::new(Shape::Rect {
+            center: vec2(0.0, 10.0),
+            size: vec2(100.0, 4.0),
+            rotation: 0.0,
+        })];
+        // Moving diagonally into the wall keeps the sideways part.
+        let resolved = resolve_circle(vec2(5.0, 7.0), 3.0, &wall);
+        assert!((resolved - vec2(5.0, 5.0)).length() < 0.001);
+    }
+
+    #[test]
+    fn domes_block_walls_but_not_the_doorway() {
+        let dome = item(SceneryKind::Dome, vec2(500.0, 500.0));
+        let walls = scenery_colliders(&dome);
+        assert!(walls.len() < DOME_WALL_SEGMENTS);
+
+        let back = vec2(500.0, 500.0 - DOME_RADIUS * DOME_SQUASH);
+        assert!(walls.iter().any(|wall| wall.push_out(back, 2.0).is_some()));
+        let door = vec2(500.0, 500.0 + DOME_RADIUS * DOME_SQUASH);
+        assert!(walls.iter().all(|wall| wall.push_out(door, 2.0).is_none()));
+        // The middle of the dome is open floor.
+        assert_eq!(resolve_circle(dome.pos, 2.0, &walls), dome.pos);
+    }
+
+    #[test]
+    fn colliders_scale_with_items() {
+        let mut tree = item(SceneryKind::Tree, Vec2::ZERO);
+        let probe = vec2(7.0 * scale::MODEL_SCALE, 4.0 * scale::MODEL_SCALE);
+        assert!(build_colliders(&[tree.clone()])[0]
+            .push_out(probe, 0.1)
+            .is_none());
+        tree.scale = 2.0;
+        assert!(build_colliders(&[tree])[0].push_out(probe, 0.1).is_some());
+        assert!(scenery_colliders(&item(SceneryKind::Crow, Vec2::ZERO)).is_empty());
+    }
+
+    #[test]
+    fn colliders_follow_the_drawn_transform() {
+        let mut dome = item(SceneryKind::Dome, vec2(500.0, 500.0));
+        dome.scale = 2.0;
+        let walls = scenery_colliders(&dome);
+        let back = vec2(500.0, 500.0 - DOME_RADIUS * DOME_SQUASH * 2.0);
+        assert!(walls.iter().any(|wall| wall.push_out(back, 2.0).is_some()));
+        let old_back = vec2(500.0, 500.0 - DOME_RADIUS * DOME_SQUASH);
+        assert!(walls
+            .iter()
+            .all(|wall| wall.push_out(old_back, 2.0).is_none()));
+
+        let rotation_of = |item: &SceneryItem| match scenery_colliders(item)[0].shape {
+            Shape::Rect { rotation, .. } => rotation,
+            _ => panic!("expected a rect"),
+        };
+        // Tents are drawn upright, chairs turn.
+        let mut tent = item(SceneryKind::Tent, Vec2::ZERO);
+        tent.rotation = 1.0;
+        assert_eq!(rotation_of(&tent), tent.drawn_rotation());
+        assert_eq!(rotation_of(&tent), 0.0);
+        let mut chair = item(SceneryKind::Chair, Vec2::ZERO);
+        chair.rotation = 1.0;
+        chair.scale = 2.0;
+        assert_eq!(rotation_of(&chair), 1.0);
+        let Shape::Rect { size, .. } = scenery_colliders(&chair)[0].shape else {
+            panic!("expected a rect");
+        };
+        assert_eq!(size, vec2(40.0, 24.0) * scale::MODEL_SCALE);
+    }
+}
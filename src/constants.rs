@@ -16,12 +16,21 @@ pub const STARTING_FLAG_INVENTORY: u32 = 10;
 pub const STRESS_TEST_HIPPIE_COUNT: usize = 100;
 pub const STRESS_TEST_FLAGS_PER_HIPPIE: u8 = 1;
 pub const LEY_MAX_DISTANCE: f32 = 150.0;
+pub const SPATIAL_CELL_SIZE: f32 = LEY_MAX_DISTANCE;
 pub const LEY_COLOR_PURPLE: Color = Color::new(0.55, 0.25, 0.95, 1.0);
 pub const LEY_COLOR_PINK: Color = Color::new(1.0, 0.35, 0.75, 1.0);
 pub const LEY_COLOR_CYCLE_SPEED: f32 = 0.9;
 pub const PENTAGRAM_COLOR_RED: Color = Color::new(1.0, 0.15, 0.05, 1.0);
 pub const PENTAGRAM_COLOR_ORANGE: Color = Color::new(1.0, 0.55, 0.0, 1.0);
 pub const PENTAGRAM_COLOR_CYCLE_SPEED: f32 = 1.2;
+pub const TRIANGLE_COLOR_GREEN: Color = Color::new(0.1, 0.8, 0.3, 1.0);
+pub const TRIANGLE_COLOR_LIME: Color = Color::new(0.7, 1.0, 0.2, 1.0);
+pub const SQUARE_COLOR_BLUE: Color = Color::new(0.15, 0.35, 1.0, 1.0);
+pub const SQUARE_COLOR_CYAN: Color = Color::new(0.2, 0.9, 1.0, 1.0);
+pub const HEXAGRAM_COLOR_GOLD: Color = Color::new(1.0, 0.8, 0.1, 1.0);
+pub const HEXAGRAM_COLOR_WHITE: Color = Color::new(1.0, 0.97, 0.85, 1.0);
+pub const HEPTAGRAM_COLOR_VIOLET: Color = Color::new(0.6, 0.1, 0.9, 1.0);
+pub const HEPTAGRAM_COLOR_TEAL: Color = Color::new(0.1, 0.75, 0.7, 1.0);
 pub const LEY_SPARKLE_SPEED: f32 = 3.5;
 pub const LEY_SPARKLE_STRENGTH: f32 = 0.35;
 pub const LEY_SPARKLE_SPATIAL: f32 = 0.02;
@@ -39,6 +48,27 @@ pub const PENTAGRAM_SPARKLE_MAX_RADIUS_FACTOR: f32 = 0.9;
 pub const PENTAGRAM_SPARKLE_HUE_SPEED: f32 = 0.35;
 pub const FLAGIC_MAX: u8 = 100;
 pub const FLAGIC_GAIN_RATE: f32 = 5.0;
+/// Share of `FLAGIC_GAIN_RATE` a pentagram scoring 0 still gives; a perfect
+/// one gives all of it.
+pub const FLAGIC_SCORE_FLOOR: f32 = 0.4;
+/// Extra gain, as a share of the best star's, for each further pentagram the
+/// player stands in at once.
+pub const FLAGIC_OVERLAP_BONUS: f32 = 0.5;
+pub const WIND_START_STRENGTH: f32 = 0.6;
+pub const SPELL_CALM_COST: u8 = 20;
+pub const SPELL_CALM_COOLDOWN: f32 = 8.0;
+pub const SPELL_CALM_RADIUS: f32 = 200.0 * scale::MODEL_SCALE;
+pub const SPELL_SUMMON_COST: u8 = 15;
+pub const SPELL_SUMMON_COOLDOWN: f32 = 5.0;
+pub const SPELL_SUMMON_RADIUS: f32 = 600.0 * scale::MODEL_SCALE;
+pub const SPELL_GUST_COST: u8 = 5;
+pub const SPELL_GUST_COOLDOWN: f32 = 2.0;
+/// Radians the wind turns by, counterclockwise on screen.
+pub const SPELL_GUST_TURN: f32 = std::f32::consts::FRAC_PI_2;
+pub const SPELL_HASTE_COST: u8 = 25;
+pub const SPELL_HASTE_COOLDOWN: f32 = 12.0;
+pub const SPELL_HASTE_DURATION: f32 = 3.0;
+pub const SPELL_HASTE_MULTIPLIER: f32 = 1.8;
 pub const REGION_NOTICE_DURATION: f32 = 4.0;
 pub const REGION_NOTICE_FADE: f32 = 0.5;
 pub const REGION_NOTICE_SIZE: f32 = 54.0;
@@ -56,10 +86,39 @@ pub const HIPPIE_FLAG_DROP_INTERVAL: f32 = 30.0;
 pub const HIPPIE_FLAG_DROP_CHANCE: f32 = 0.25;
 pub const HIPPIE_FLAG_IGNORE_DURATION: f32 = 30.0;
 pub const HIPPIE_COLLISION_RADIUS: f32 = 10.0 * scale::MODEL_SCALE;
+pub const PLAYER_COLLISION_RADIUS: f32 = 12.0 * scale::MODEL_SCALE;
+pub const NAV_CELL_SIZE: f32 = 32.0 * scale::MODEL_SCALE;
+pub const NAV_MAX_EXPANSIONS: usize = 20_000;
+pub const NAV_REPATH_INTERVAL: f32 = 0.5;
+pub const NAV_REPATH_DISTANCE: f32 = 4.0 * NAV_CELL_SIZE;
 pub const CAMERA_ZOOM_MIN: f32 = camera::DEFAULT_ZOOM * 0.25;
 pub const CAMERA_ZOOM_MAX: f32 = camera::DEFAULT_ZOOM * 2.0;
 pub const CAMERA_ZOOM_STEP: f32 = 0.1;
+pub const SETTINGS_ZOOM_STEP: f32 = 0.05;
+pub const SETTINGS_VOLUME_STEP: f32 = 0.1;
+pub const SETTINGS_VISIBLE_ROWS: usize = 12;
 pub const MAP_TILE_DIR: &str = "assets/map/tiles";
+pub const SIGNIFIER_MARK_PATH: &str = "assets/png/signifiersmark.png";
+pub const TILE_CACHE_CAPACITY: usize = 64;
+pub const TILE_PREFETCH_MARGIN: usize = 1;
+pub const TILE_MAX_IN_FLIGHT: usize = 8;
+pub const MINIMAP_SIZE: f32 = 200.0;
+pub const MINIMAP_MARGIN: f32 = 12.0;
+pub const WORLD_MAP_ZOOM_MIN: f32 = 1.0;
+pub const WORLD_MAP_ZOOM_MAX: f32 = 16.0;
+pub const WORLD_MAP_PAN_SPEED: f32 = 600.0;
+pub const WAYPOINT_ARROW_INSET: f32 = 28.0;
+pub const WAYPOINT_COLOR: Color = Color::new(0.3, 0.9, 1.0, 1.0);
+pub const MOVE_PATH_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.6);
+pub const TILE_PLACEHOLDER_COLOR: Color = Color::new(0.16, 0.15, 0.13, 1.0);
+pub const SIM_STEP: f32 = 1.0 / 60.0;
+pub const SIM_MAX_STEPS_PER_FRAME: u32 = 5;
+pub const AUTOSAVE_INTERVAL: f32 = 30.0;
+pub const TUNING_POLL_INTERVAL: f32 = 1.0;
+pub const CAMP_EDITOR_HANDLE_RADIUS: f32 = 8.0;
+pub const SCENERY_EDITOR_PICK_RADIUS: f32 = 16.0;
+pub const SCENERY_EDITOR_ROTATE_STEP: f32 = 0.1;
+pub const SCENERY_EDITOR_SCALE_FACTOR: f32 = 1.1;
 pub const MAP_TRAVEL_MINUTES: f32 = 10.0;
 pub const SPEED_MULTIPLIER: f32 = 4.0;
 pub const PLAYER_SPAWN_POS: Vec2 = Vec2::new(5015.0, 3292.0);
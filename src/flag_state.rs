@@ -1,10 +1,19 @@
 use macroquad::prelude::{Rect, Vec2};
 
+use crate::constants::{LEY_MAX_DISTANCE, SPATIAL_CELL_SIZE};
 use crate::flags;
+use crate::ley_lines::{LeyGraph, LeyState};
+use crate::spatial::SpatialHash;
 
 #[derive(Clone, Debug)]
 pub struct FlagState {
     ground: Vec<flags::Flag>,
+    /// Ground flag positions, indexed like `ground`.
+    spatial: SpatialHash,
+    /// Ley lines over `ground`, updated with every ground edit.
+    ley: LeyGraph,
+    /// Set by ground edits until `take_ground_changed` reads it.
+    ground_changed: bool,
     player: u32,
     total: u32,
 }
@@ -12,8 +21,14 @@ pub struct FlagState {
 impl FlagState {
     pub fn new(ground: Vec<flags::Flag>, player: u32, total: u32) -> Self {
         debug_assert!(total >= ground.len() as u32 + player);
+        let spatial =
+            SpatialHash::from_positions(SPATIAL_CELL_SIZE, ground.iter().map(|flag| flag.pos));
+        let ley = LeyGraph::build(&ground, &spatial, LEY_MAX_DISTANCE);
         Self {
             ground,
+            spatial,
+            ley,
+            ground_changed: true,
             player,
             total,
         }
@@ -23,10 +38,35 @@ impl FlagState {
         &self.ground
     }
 
+    pub fn spatial_index(&self) -> &SpatialHash {
+        &self.spatial
+    }
+
+    pub fn ley_state(&self) -> LeyState {
+        self.ley.state()
+    }
+
+    /// Rebuilds the ley graph when the line length changes.
+    pub fn set_ley_max_distance(&mut self, max_distance: f32) {
+        if self.ley.max_distance() != max_distance {
+            self.ley = LeyGraph::build(&self.ground, &self.spatial, max_distance);
+            self.ground_changed = true;
+        }
+    }
+
+    /// Whether the ground flags changed since the last call.
+    pub fn take_ground_changed(&mut self) -> bool {
+        std::mem::take(&mut self.ground_changed)
+    }
+
     pub fn player_inventory(&self) -> u32 {
         self.player
     }
 
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
     pub fn current_total(&self, hippie_flags: u32) -> u32 {
         self.ground.len() as u32 + self.player + hippie_flags
     }
@@ -36,11 +76,24 @@ impl FlagState {
     }
 
     pub fn try_place_from_player(&mut self, origin: Vec2, offset: Vec2, field: Rect) -> bool {
-        flags::try_place_flag(&mut self.ground, &mut self.player, origin, offset, field)
+        let placed = flags::try_place_flag(
+            &mut self.ground,
+            &mut self.spatial,
+            &mut self.player,
+            origin,
+            offset,
+            field,
+        );
+        if placed {
+            self.ground_pushed(self.ground[self.ground.len() - 1].pos);
+        }
+        placed
     }
 
     pub fn try_pickup_to_player(&mut self, origin: Vec2, radius: f32) -> bool {
-        if flags::try_pickup_flag(&mut self.ground, origin, radius) {
+        if let Some(index) = flags::nearest_flag_index(&self.ground, &self.spatial, origin, radius)
+        {
+            self.remove_ground(index);
             self.player = self.player.saturating_add(1);
             true
         } else {
@@ -64,17 +117,23 @@ impl FlagState {
         pos: Vec2,
         radius: f32,
     ) -> bool {
+        // Picks the same flags as sweeping the whole list with swap_remove:
+        // when the last flag is swapped into a freed slot and is in range
+        // too, it is taken next.
+        let mut within = self.spatial.within(pos, radius);
+        let mut next = 0;
         let mut picked = false;
-        let mut index = 0;
-        while index < self.ground.len() && *hippie_flags < capacity {
-            let flag_pos = self.ground[index].pos;
-            if flag_pos.distance(pos) <= radius {
-                self.ground.swap_remove(index);
-                *hippie_flags = hippie_flags.saturating_add(1);
-                picked = true;
-                continue;
+        while next < within.len() && *hippie_flags < capacity {
+            let index = within[next];
+            let last = self.ground.len() - 1;
+            self.remove_ground(index);
+            *hippie_flags = hippie_flags.saturating_add(1);
+            picked = true;
+            if last != index && within.last() == Some(&last) {
+                within.pop();
+            } else {
+                next += 1;
             }
-            index += 1;
         }
         picked
     }
@@ -86,7 +145,7 @@ impl FlagState {
         }
         *hippie_flags -= drop;
         for _ in 0..drop {
-            self.ground.push(flags::make_flag(pos));
+            self.push_ground(flags::make_flag(pos));
         }
         drop
     }
@@ -109,18 +168,37 @@ impl FlagState {
             if *hippie_flags < capacity {
                 *hippie_flags = hippie_flags.saturating_add(1);
             } else {
-                self.ground.push(flags::make_flag(pos));
+                self.push_ground(flags::make_flag(pos));
             }
             remaining -= 1;
         }
 
         stolen
     }
+
+    fn push_ground(&mut self, flag: flags::Flag) {
+        self.spatial.push(flag.pos);
+        self.ground.push(flag);
+        self.ground_pushed(flag.pos);
+    }
+
+    fn ground_pushed(&mut self, pos: Vec2) {
+        self.ley.insert(pos, &self.spatial);
+        self.ground_changed = true;
+    }
+
+    fn remove_ground(&mut self, index: usize) {
+        self.spatial.swap_remove(index);
+        self.ground.swap_remove(index);
+        self.ley.swap_remove(index, &self.spatial);
+        self.ground_changed = true;
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ley_lines;
     use macroquad::prelude::vec2;
 
     #[test]
@@ -179,6 +257,74 @@ mod tests {
         assert!(picked);
         assert_eq!(hippie_flags, 2);
         assert_eq!(state.ground_flags().len(), 1);
+        assert_eq!(
+            state.spatial_index().within(vec2(100.0, 100.0), 1.0),
+            vec![0]
+        );
+    }
+
+    #[test]
+    fn spatial_index_follows_ground_edits() {
+        let mut state = FlagState::new(vec![flags::make_flag(vec2(0.0, 0.0))], 3, 4);
+        let field = Rect::new(0.0, 0.0, 1000.0, 1000.0);
+        state.try_place_from_player(vec2(500.0, 500.0), Vec2::ZERO, field);
+        let mut hippie_flags = 2;
+        state.drop_from_hippie(&mut hippie_flags, 1, vec2(300.0, 300.0));
+        state.steal_from_player_to_hippie(&mut hippie_flags, 2, vec2(800.0, 20.0), 1);
+        state.try_pickup_to_player(vec2(1.0, 0.0), 5.0);
+
+        let index = state.spatial_index();
+        assert_eq!(index.len(), state.ground_flags().len());
+        for (i, flag) in state.ground_flags().iter().enumerate() {
+            assert_eq!(index.within(flag.pos, 0.5), vec![i]);
+        }
+    }
+
+    #[test]
+    fn ley_state_follows_ground_edits() {
+        let mut state = FlagState::new(Vec::new(), 5, 6);
+        assert!(state.take_ground_changed());
+        assert!(!state.take_ground_changed());
+
+        let field = Rect::new(-100.0, -100.0, 200.0, 200.0);
+        for i in 0..5 {
+            let angle = i as f32 * std::f32::consts::TAU / 5.0;
+            let corner = vec2(angle.cos(), angle.sin()) * 45.0;
+            state.try_place_from_player(corner, Vec2::ZERO, field);
+        }
+        let mut hippie_flags = 1;
+        state.drop_from_hippie(&mut hippie_flags, 1, vec2(400.0, 400.0));
+        assert!(state.take_ground_changed());
+        assert_eq!(state.ley_state().pentagram_centers.len(), 1);
+
+        state.try_pickup_to_player(vec2(45.0, 0.0), 5.0);
+        state.set_ley_max_distance(60.0);
+        assert_eq!(
+            state.ley_state(),
+            ley_lines::compute_ley_state(state.ground_flags(), 60.0)
+        );
+        assert!(state.ley_state().pentagram_centers.is_empty());
+    }
+
+    #[test]
+    fn transfer_ground_to_hippie_keeps_sweep_order() {
+        let positions = [
+            vec2(0.0, 0.0),
+            vec2(100.0, 100.0),
+            vec2(1.0, 0.0),
+            vec2(2.0, 0.0),
+        ];
+        let mut state = FlagState::new(
+            positions.iter().map(|pos| flags::make_flag(*pos)).collect(),
+            0,
+            4,
+        );
+        let mut hippie_flags = 0;
+        assert!(state.transfer_ground_to_hippie(&mut hippie_flags, 2, vec2(0.0, 0.0), 5.0));
+        // The sweep takes the first flag, then the last one swapped into
+        // its slot, which leaves the third flag in front.
+        let left: Vec<Vec2> = state.ground_flags().iter().map(|flag| flag.pos).collect();
+        assert_eq!(left, vec![positions[2], positions[1]]);
     }
 
     #[test]
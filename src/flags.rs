@@ -1,6 +1,7 @@
+use crate::rng::Rng;
 use crate::scale;
+use crate::spatial::SpatialHash;
 use macroquad::prelude::{Rect, Vec2};
-use macroquad::rand::gen_range;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Flag {
@@ -65,7 +66,7 @@ pub fn spawn_initial_flags(count: usize, field: Rect, padding: f32) -> Vec<Flag>
     flags
 }
 
-pub fn spawn_random_flags(count: usize, field: Rect, padding: f32) -> Vec<Flag> {
+pub fn spawn_random_flags(count: usize, field: Rect, padding: f32, rng: &mut Rng) -> Vec<Flag> {
     if count == 0 {
         return Vec::new();
     }
@@ -77,8 +78,8 @@ pub fn spawn_random_flags(count: usize, field: Rect, padding: f32) -> Vec<Flag>
 
     let mut flags = Vec::with_capacity(count);
     for _ in 0..count {
-        let x = gen_range(min_x, max_x);
-        let y = gen_range(min_y, max_y);
+        let x = rng.range(min_x, max_x);
+        let y = rng.range(min_y, max_y);
         let pos = Vec2::new(x, y);
         flags.push(Flag {
             pos,
@@ -89,9 +90,15 @@ pub fn spawn_random_flags(count: usize, field: Rect, padding: f32) -> Vec<Flag>
     flags
 }
 
-pub fn try_pickup_flag(flags: &mut Vec<Flag>, origin: Vec2, radius: f32) -> bool {
-    if let Some(index) = nearest_flag_index(flags, origin, radius) {
+pub fn try_pickup_flag(
+    flags: &mut Vec<Flag>,
+    spatial: &mut SpatialHash,
+    origin: Vec2,
+    radius: f32,
+) -> bool {
+    if let Some(index) = nearest_flag_index(flags, spatial, origin, radius) {
         flags.swap_remove(index);
+        spatial.swap_remove(index);
         true
     } else {
         false
@@ -100,6 +107,7 @@ pub fn try_pickup_flag(flags: &mut Vec<Flag>, origin: Vec2, radius: f32) -> bool
 
 pub fn try_place_flag(
     flags: &mut Vec<Flag>,
+    spatial: &mut SpatialHash,
     inventory: &mut u32,
     origin: Vec2,
     offset: Vec2,
@@ -119,6 +127,7 @@ pub fn try_place_flag(
         pos,
         phase: phase_from_position(pos),
     });
+    spatial.push(pos);
     true
 }
 
@@ -157,12 +166,18 @@ pub fn cloth_offset(time: f32, wind: Wind, phase: f32) -> Vec2 {
     along + perp
 }
 
-fn nearest_flag_index(flags: &[Flag], origin: Vec2, radius: f32) -> Option<usize> {
+/// `spatial` must index `flags` by position.
+pub fn nearest_flag_index(
+    flags: &[Flag],
+    spatial: &SpatialHash,
+    origin: Vec2,
+    radius: f32,
+) -> Option<usize> {
     let mut best_index = None;
     let mut best_dist = radius * radius;
 
-    for (i, flag) in flags.iter().enumerate() {
-        let dist_sq = origin.distance_squared(flag.pos);
+    for i in spatial.within(origin, radius) {
+        let dist_sq = origin.distance_squared(flags[i].pos);
         if dist_sq <= best_dist {
             best_dist = dist_sq;
             best_index = Some(i);
@@ -180,6 +195,10 @@ fn phase_from_position(pos: Vec2) -> f32 {
 mod tests {
     use super::*;
 
+    fn index(flags: &[Flag]) -> SpatialHash {
+        SpatialHash::from_positions(16.0, flags.iter().map(|flag| flag.pos))
+    }
+
     #[test]
     fn spawn_initial_flags_returns_requested_count() {
         let field = Rect::new(0.0, 0.0, 400.0, 300.0);
@@ -197,6 +216,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn spawn_random_flags_is_deterministic_per_seed() {
+        let field = Rect::new(0.0, 0.0, 400.0, 300.0);
+        let a = spawn_random_flags(8, field, 10.0, &mut Rng::new(9));
+        let b = spawn_random_flags(8, field, 10.0, &mut Rng::new(9));
+        assert_eq!(a, b);
+        for flag in a {
+            assert!(flag.pos.x >= 10.0 && flag.pos.x <= 390.0);
+            assert!(flag.pos.y >= 10.0 && flag.pos.y <= 290.0);
+        }
+    }
+
     #[test]
     fn try_pickup_flag_removes_nearest() {
         let mut flags = vec![
@@ -209,10 +240,12 @@ mod tests {
                 phase: 1.0,
             },
         ];
-        let picked = try_pickup_flag(&mut flags, Vec2::new(12.0, 10.0), 10.0);
+        let mut spatial = index(&flags);
+        let picked = try_pickup_flag(&mut flags, &mut spatial, Vec2::new(12.0, 10.0), 10.0);
         assert!(picked);
         assert_eq!(flags.len(), 1);
         assert_eq!(flags[0].pos, Vec2::new(30.0, 10.0));
+        assert_eq!(spatial.within(Vec2::new(30.0, 10.0), 1.0), vec![0]);
     }
 
     #[test]
@@ -221,7 +254,8 @@ mod tests {
             pos: Vec2::new(100.0, 100.0),
             phase: 0.0,
         }];
-        let picked = try_pickup_flag(&mut flags, Vec2::new(0.0, 0.0), 10.0);
+        let mut spatial = index(&flags);
+        let picked = try_pickup_flag(&mut flags, &mut spatial, Vec2::new(0.0, 0.0), 10.0);
         assert!(!picked);
         assert_eq!(flags.len(), 1);
     }
@@ -232,8 +266,10 @@ mod tests {
         let mut inventory = 1;
         let field = Rect::new(0.0, 0.0, 200.0, 200.0);
 
+        let mut spatial = SpatialHash::new(16.0);
         let placed = try_place_flag(
             &mut flags,
+            &mut spatial,
             &mut inventory,
             Vec2::new(50.0, 50.0),
             Vec2::new(10.0, 0.0),
@@ -243,6 +279,7 @@ mod tests {
         assert!(placed);
         assert_eq!(inventory, 0);
         assert_eq!(flags.len(), 1);
+        assert_eq!(spatial.len(), 1);
     }
 
     #[test]
@@ -251,8 +288,10 @@ mod tests {
         let mut inventory = 0;
         let field = Rect::new(0.0, 0.0, 200.0, 200.0);
 
+        let mut spatial = SpatialHash::new(16.0);
         let placed = try_place_flag(
             &mut flags,
+            &mut spatial,
             &mut inventory,
             Vec2::new(50.0, 50.0),
             Vec2::new(10.0, 0.0),
@@ -152,6 +152,49 @@ pub fn point_in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
     !(has_neg && has_pos)
 }
 
+/// Reports whether any two non-adjacent edges of the closed polygon cross or
+/// touch.
+pub fn polygon_self_intersects(vertices: &[Vec2]) -> bool {
+    let count = vertices.len();
+    if count < 4 {
+        return false;
+    }
+
+    for i in 0..count {
+        let a1 = vertices[i];
+        let a2 = vertices[(i + 1) % count];
+        for j in (i + 2)..count {
+            if i == 0 && j == count - 1 {
+                continue;
+            }
+            let b1 = vertices[j];
+            let b2 = vertices[(j + 1) % count];
+            if segments_intersect(a1, a2, b1, b2) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn segments_intersect(a1: Vec2, a2: Vec2, b1: Vec2, b2: Vec2) -> bool {
+    let d1 = cross_2d(a2 - a1, b1 - a1);
+    let d2 = cross_2d(a2 - a1, b2 - a1);
+    let d3 = cross_2d(b2 - b1, a1 - b1);
+    let d4 = cross_2d(b2 - b1, a2 - b1);
+    if d1 * d2 < 0.0 && d3 * d4 < 0.0 {
+        return true;
+    }
+
+    let on_segment = |p: Vec2, q: Vec2, r: Vec2| {
+        r.x >= p.x.min(q.x) && r.x <= p.x.max(q.x) && r.y >= p.y.min(q.y) && r.y <= p.y.max(q.y)
+    };
+    (d1.abs() < POLYGON_EPSILON && on_segment(a1, a2, b1))
+        || (d2.abs() < POLYGON_EPSILON && on_segment(a1, a2, b2))
+        || (d3.abs() < POLYGON_EPSILON && on_segment(b1, b2, a1))
+        || (d4.abs() < POLYGON_EPSILON && on_segment(b1, b2, a2))
+}
+
 fn is_polygon_convex(vertices: &[Vec2]) -> bool {
     let count = vertices.len();
     if count < 4 {
@@ -189,7 +232,7 @@ fn triangle_area(a: Vec2, b: Vec2, c: Vec2) -> f32 {
     cross_2d(b - a, c - a) * 0.5
 }
 
-fn polygon_area(vertices: &[Vec2]) -> f32 {
+pub fn polygon_area(vertices: &[Vec2]) -> f32 {
     let count = vertices.len();
     let mut area = 0.0;
     for i in 0..count {
@@ -203,6 +246,40 @@ fn cross_2d(a: Vec2, b: Vec2) -> f32 {
     a.x * b.y - a.y * b.x
 }
 
+/// Serializes a point as `[x, y]`, the form data files write positions in.
+pub mod xy {
+    use macroquad::prelude::{vec2, Vec2};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(pos: &Vec2, serializer: S) -> Result<S::Ok, S::Error> {
+        [pos.x, pos.y].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec2, D::Error> {
+        let [x, y] = <[f32; 2]>::deserialize(deserializer)?;
+        Ok(vec2(x, y))
+    }
+}
+
+/// Like `xy`, for a list of points.
+pub mod xy_list {
+    use macroquad::prelude::{vec2, Vec2};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(points: &[Vec2], serializer: S) -> Result<S::Ok, S::Error> {
+        points
+            .iter()
+            .map(|pos| [pos.x, pos.y])
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Vec2>, D::Error> {
+        let points = Vec::<[f32; 2]>::deserialize(deserializer)?;
+        Ok(points.into_iter().map(|[x, y]| vec2(x, y)).collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -222,4 +299,22 @@ mod tests {
         assert_eq!(pts.last(), Some(&vec2(10.0, 0.0)));
         assert!(pts.len() >= 2);
     }
+
+    #[test]
+    fn self_intersection_detects_bowtie() {
+        let square = vec![
+            vec2(0.0, 0.0),
+            vec2(10.0, 0.0),
+            vec2(10.0, 10.0),
+            vec2(0.0, 10.0),
+        ];
+        let bowtie = vec![
+            vec2(0.0, 0.0),
+            vec2(10.0, 10.0),
+            vec2(10.0, 0.0),
+            vec2(0.0, 10.0),
+        ];
+        assert!(!polygon_self_intersects(&square));
+        assert!(polygon_self_intersects(&bowtie));
+    }
 }
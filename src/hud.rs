@@ -1,8 +1,24 @@
 use macroquad::prelude::*;
 
 use crate::constants;
+use crate::spells::{Spell, Spellbook, ALL_SPELLS};
+use crate::tuning::SpellTuning;
 
-pub fn draw_hud(flag_count: u32, speed: f32, player_pos: Vec2, total_flags: u32, flagic: u8) {
+const SPELL_SLOT_SIZE: Vec2 = vec2(120.0, 40.0);
+const SPELL_SLOT_GAP: f32 = 8.0;
+const SPELL_SLOT_BACKGROUND: Color = Color::new(0.0, 0.0, 0.0, 0.7);
+const SPELL_SLOT_COOLDOWN: Color = Color::new(1.0, 1.0, 1.0, 0.2);
+
+/// Draws the bottom bar. `star_scores` are those of the pentagrams the player
+/// stands in, best first; the bar shows them only while there are any.
+pub fn draw_hud(
+    flag_count: u32,
+    speed: f32,
+    player_pos: Vec2,
+    total_flags: u32,
+    flagic: u8,
+    star_scores: &[f32],
+) {
     let y = screen_height() - constants::HUD_HEIGHT;
     draw_rectangle(0.0, y, screen_width(), constants::HUD_HEIGHT, BLACK);
 
@@ -18,6 +34,16 @@ pub fn draw_hud(flag_count: u32, speed: f32, player_pos: Vec2, total_flags: u32,
     let flagic_text = format!("Flagic: {}", flagic);
     draw_text(&flagic_text, 480.0, y + 32.0, 20.0, constants::ACCENT);
 
+    if let Some(star_text) = format_star_scores(star_scores) {
+        draw_text(
+            &star_text,
+            600.0,
+            y + 32.0,
+            20.0,
+            constants::PENTAGRAM_COLOR_ORANGE,
+        );
+    }
+
     let coords = format_player_coords(player_pos);
     let metrics = measure_text(&coords, None, 20, 1.0);
     let x = screen_width() - metrics.width - 16.0;
@@ -28,6 +54,61 @@ fn format_player_coords(pos: Vec2) -> String {
     format!("X: {:.0}  Y: {:.0}", pos.x, pos.y)
 }
 
+/// Draws a slot per spell just above the bottom bar, with `keys` naming the
+/// hotkey for each in `ALL_SPELLS` order. Slots that cannot be cast yet are
+/// dimmed, and a cooling spell's slot fills back up as the cooldown runs.
+pub fn draw_spell_bar(spellbook: &Spellbook, flagic: u8, tuning: &SpellTuning, keys: &[String]) {
+    let top = screen_height() - constants::HUD_HEIGHT - SPELL_SLOT_GAP - SPELL_SLOT_SIZE.y;
+    for (spell, key) in ALL_SPELLS.into_iter().zip(keys) {
+        let x = 16.0 + spell.index() as f32 * (SPELL_SLOT_SIZE.x + SPELL_SLOT_GAP);
+        let (w, h) = (SPELL_SLOT_SIZE.x, SPELL_SLOT_SIZE.y);
+        draw_rectangle(x, top, w, h, SPELL_SLOT_BACKGROUND);
+
+        let left = spellbook.cooldown_left(spell);
+        let cooldown = spell.cooldown(tuning);
+        if left > 0.0 && cooldown > 0.0 {
+            let filled = h * (1.0 - left / cooldown).clamp(0.0, 1.0);
+            draw_rectangle(x, top + h - filled, w, filled, SPELL_SLOT_COOLDOWN);
+        }
+
+        let ready = spellbook.check(spell, flagic, tuning).is_ok();
+        let color = if ready { constants::ACCENT } else { GRAY };
+        draw_rectangle_lines(x, top, w, h, 2.0, color);
+        let (title, detail) = spell_slot_text(key, spell, spellbook, tuning);
+        draw_text(&title, x + 8.0, top + 17.0, 18.0, color);
+        draw_text(&detail, x + 8.0, top + 34.0, 16.0, color);
+    }
+}
+
+/// The hotkey and name of a spell, then its cooldown while it runs or its
+/// flagic cost.
+fn spell_slot_text(
+    key: &str,
+    spell: Spell,
+    spellbook: &Spellbook,
+    tuning: &SpellTuning,
+) -> (String, String) {
+    let title = format!("{} {}", key, spell.name());
+    let left = spellbook.cooldown_left(spell);
+    let detail = if left > 0.0 {
+        format!("{:.1}s", left)
+    } else {
+        format!("{} flagic", spell.cost(tuning))
+    };
+    (title, detail)
+}
+
+/// The best star's score as a percentage, and how many stars overlap.
+fn format_star_scores(scores: &[f32]) -> Option<String> {
+    let best = scores.first()?;
+    let percent = (best.clamp(0.0, 1.0) * 100.0).round();
+    Some(if scores.len() > 1 {
+        format!("Star: {:.0}% x{}", percent, scores.len())
+    } else {
+        format!("Star: {:.0}%", percent)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -37,4 +118,29 @@ mod tests {
         let text = format_player_coords(vec2(12.4, 13.6));
         assert_eq!(text, "X: 12  Y: 14");
     }
+
+    #[test]
+    fn star_scores_show_best_and_overlaps() {
+        assert_eq!(format_star_scores(&[]), None);
+        assert_eq!(format_star_scores(&[0.874]).as_deref(), Some("Star: 87%"));
+        assert_eq!(
+            format_star_scores(&[0.9, 0.5]).as_deref(),
+            Some("Star: 90% x2")
+        );
+    }
+
+    #[test]
+    fn spell_slots_show_cost_until_cast() {
+        let tuning = SpellTuning::default();
+        let mut book = Spellbook::default();
+        let (title, detail) = spell_slot_text("Key1", Spell::Calm, &book, &tuning);
+        assert_eq!(title, "Key1 Calm");
+        assert_eq!(detail, format!("{} flagic", tuning.calm_cost));
+
+        let mut flagic = 100;
+        book.commit(Spell::Calm, &mut flagic, &tuning);
+        book.tick(tuning.calm_cooldown - 1.3);
+        let (_, detail) = spell_slot_text("Key1", Spell::Calm, &book, &tuning);
+        assert_eq!(detail, "1.3s");
+    }
 }
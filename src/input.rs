@@ -0,0 +1,788 @@
+use macroquad::prelude::{
+    is_key_down, is_key_pressed, is_key_released, is_mouse_button_down, is_mouse_button_pressed,
+    is_mouse_button_released, KeyCode, MouseButton,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::movement;
+use crate::spells::Spell;
+
+pub const KEYBINDS_PATH: &str = "config/keybinds.toml";
+
+/// Everything a key or mouse button can mean, shared by the menus and the
+/// dungeon scene.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    MoveUpLeft,
+    MoveUpRight,
+    MoveDownLeft,
+    MoveDownRight,
+    Confirm,
+    Back,
+    Quit,
+    PlaceFlag,
+    PickupFlag,
+    PanCamera,
+    Save,
+    Load,
+    Settings,
+    ToggleEditor,
+    ToggleMinimap,
+    WorldMap,
+    ClickToMove,
+    CastCalm,
+    CastSummon,
+    CastGust,
+    CastHaste,
+    /// Pick up, drag and drop a handle in the map editors.
+    EditorGrab,
+    /// Remove the handle under the cursor.
+    EditorRemove,
+    EditorDeleteSelected,
+    EditorNextField,
+    EditorPrevColor,
+    EditorNextColor,
+    EditorBackspace,
+    EditorNextCamp,
+    EditorNextKind,
+    EditorRotateLeft,
+    EditorRotateRight,
+    EditorShrink,
+    EditorGrow,
+    EditorNextVariant,
+    EditorToggleCrystal,
+}
+
+pub const ALL_ACTIONS: [Action; 40] = [
+    Action::MoveUp,
+    Action::MoveDown,
+    Action::MoveLeft,
+    Action::MoveRight,
+    Action::MoveUpLeft,
+    Action::MoveUpRight,
+    Action::MoveDownLeft,
+    Action::MoveDownRight,
+    Action::Confirm,
+    Action::Back,
+    Action::Quit,
+    Action::PlaceFlag,
+    Action::PickupFlag,
+    Action::PanCamera,
+    Action::Save,
+    Action::Load,
+    Action::Settings,
+    Action::ToggleEditor,
+    Action::ToggleMinimap,
+    Action::WorldMap,
+    Action::ClickToMove,
+    Action::CastCalm,
+    Action::CastSummon,
+    Action::CastGust,
+    Action::CastHaste,
+    Action::EditorGrab,
+    Action::EditorRemove,
+    Action::EditorDeleteSelected,
+    Action::EditorNextField,
+    Action::EditorPrevColor,
+    Action::EditorNextColor,
+    Action::EditorBackspace,
+    Action::EditorNextCamp,
+    Action::EditorNextKind,
+    Action::EditorRotateLeft,
+    Action::EditorRotateRight,
+    Action::EditorShrink,
+    Action::EditorGrow,
+    Action::EditorNextVariant,
+    Action::EditorToggleCrystal,
+];
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::MoveUpLeft => "move_up_left",
+            Action::MoveUpRight => "move_up_right",
+            Action::MoveDownLeft => "move_down_left",
+            Action::MoveDownRight => "move_down_right",
+            Action::Confirm => "confirm",
+            Action::Back => "back",
+            Action::Quit => "quit",
+            Action::PlaceFlag => "place_flag",
+            Action::PickupFlag => "pickup_flag",
+            Action::PanCamera => "pan_camera",
+            Action::Save => "save",
+            Action::Load => "load",
+            Action::Settings => "settings",
+            Action::ToggleEditor => "toggle_editor",
+            Action::ToggleMinimap => "toggle_minimap",
+            Action::WorldMap => "world_map",
+            Action::ClickToMove => "click_to_move",
+            Action::CastCalm => "cast_calm",
+            Action::CastSummon => "cast_summon",
+            Action::CastGust => "cast_gust",
+            Action::CastHaste => "cast_haste",
+            Action::EditorGrab => "editor_grab",
+            Action::EditorRemove => "editor_remove",
+            Action::EditorDeleteSelected => "editor_delete_selected",
+            Action::EditorNextField => "editor_next_field",
+            Action::EditorPrevColor => "editor_prev_color",
+            Action::EditorNextColor => "editor_next_color",
+            Action::EditorBackspace => "editor_backspace",
+            Action::EditorNextCamp => "editor_next_camp",
+            Action::EditorNextKind => "editor_next_kind",
+            Action::EditorRotateLeft => "editor_rotate_left",
+            Action::EditorRotateRight => "editor_rotate_right",
+            Action::EditorShrink => "editor_shrink",
+            Action::EditorGrow => "editor_grow",
+            Action::EditorNextVariant => "editor_next_variant",
+            Action::EditorToggleCrystal => "editor_toggle_crystal",
+        }
+    }
+
+    /// The action that casts `spell`.
+    pub fn cast(spell: Spell) -> Self {
+        match spell {
+            Spell::Calm => Action::CastCalm,
+            Spell::Summon => Action::CastSummon,
+            Spell::Gust => Action::CastGust,
+            Spell::Haste => Action::CastHaste,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        ALL_ACTIONS
+            .iter()
+            .copied()
+            .find(|action| action.name() == name)
+    }
+}
+
+/// Actions that must keep at least one binding, so every menu can still be
+/// confirmed, left and quit.
+pub const ESSENTIAL_ACTIONS: [Action; 3] = [Action::Back, Action::Confirm, Action::Quit];
+
+/// What `ActionMap::toggle_binding` did.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BindingChange {
+    /// The binding was added. `shared_with` lists other actions it already
+    /// triggers.
+    Added {
+        shared_with: Vec<Action>,
+    },
+    Removed,
+    /// The binding was the last one of an essential action and was kept.
+    KeptLast,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+const MOUSE_PREFIX: &str = "Mouse";
+
+const NAMED_KEYS: [KeyCode; 81] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Space,
+    KeyCode::Enter,
+    KeyCode::Escape,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Apostrophe,
+    KeyCode::Comma,
+    KeyCode::Minus,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Semicolon,
+    KeyCode::Equal,
+    KeyCode::LeftBracket,
+    KeyCode::Backslash,
+    KeyCode::RightBracket,
+    KeyCode::GraveAccent,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::LeftShift,
+    KeyCode::RightShift,
+    KeyCode::LeftControl,
+    KeyCode::RightControl,
+    KeyCode::LeftAlt,
+    KeyCode::RightAlt,
+    KeyCode::KpEnter,
+];
+
+const MOUSE_BUTTONS: [MouseButton; 3] =
+    [MouseButton::Left, MouseButton::Middle, MouseButton::Right];
+
+impl Binding {
+    pub fn name(self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Mouse(button) => format!("{}{:?}", MOUSE_PREFIX, button),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Some(button) = name.strip_prefix(MOUSE_PREFIX) {
+            return MOUSE_BUTTONS
+                .iter()
+                .copied()
+                .find(|candidate| format!("{:?}", candidate) == button)
+                .map(Binding::Mouse);
+        }
+        NAMED_KEYS
+            .iter()
+            .copied()
+            .find(|key| format!("{:?}", key) == name)
+            .map(Binding::Key)
+    }
+
+    fn is_down(self) -> bool {
+        match self {
+            Binding::Key(key) => is_key_down(key),
+            Binding::Mouse(button) => is_mouse_button_down(button),
+        }
+    }
+
+    fn is_pressed(self) -> bool {
+        match self {
+            Binding::Key(key) => is_key_pressed(key),
+            Binding::Mouse(button) => is_mouse_button_pressed(button),
+        }
+    }
+
+    fn is_released(self) -> bool {
+        match self {
+            Binding::Key(key) => is_key_released(key),
+            Binding::Mouse(button) => is_mouse_button_released(button),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum KeybindError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    UnknownAction(String),
+    UnknownBinding { action: String, binding: String },
+}
+
+impl fmt::Display for KeybindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeybindError::Io(err) => write!(f, "keybind io error: {}", err),
+            KeybindError::Parse(err) => write!(f, "keybind parse error: {}", err),
+            KeybindError::Serialize(err) => write!(f, "keybind serialize error: {}", err),
+            KeybindError::UnknownAction(name) => write!(f, "unknown action '{}'", name),
+            KeybindError::UnknownBinding { action, binding } => {
+                write!(f, "unknown key '{}' for action '{}'", binding, action)
+            }
+        }
+    }
+}
+
+impl std::error::Error for KeybindError {}
+
+impl From<std::io::Error> for KeybindError {
+    fn from(err: std::io::Error) -> Self {
+        KeybindError::Io(err)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeybindFile {
+    bindings: BTreeMap<String, Vec<String>>,
+}
+
+/// Maps each action to the keys and mouse buttons that trigger it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActionMap {
+    bindings: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        use Binding::{Key, Mouse};
+
+        // Editor keys stay off keys other actions already use. The camp
+        // editor's also stay off printable keys so typing a camp name never
+        // triggers them.
+        let defaults: [(Action, Vec<Binding>); 40] = [
+            (
+                Action::MoveUp,
+                vec![Key(KeyCode::W), Key(KeyCode::K), Key(KeyCode::Up)],
+            ),
+            (
+                Action::MoveDown,
+                vec![Key(KeyCode::S), Key(KeyCode::J), Key(KeyCode::Down)],
+            ),
+            (
+                Action::MoveLeft,
+                vec![Key(KeyCode::A), Key(KeyCode::H), Key(KeyCode::Left)],
+            ),
+            (
+                Action::MoveRight,
+                vec![Key(KeyCode::D), Key(KeyCode::L), Key(KeyCode::Right)],
+            ),
+            (Action::MoveUpLeft, vec![Key(KeyCode::Y)]),
+            (Action::MoveUpRight, vec![Key(KeyCode::U)]),
+            (Action::MoveDownLeft, vec![Key(KeyCode::B)]),
+            (Action::MoveDownRight, vec![Key(KeyCode::N)]),
+            (
+                Action::Confirm,
+                vec![Key(KeyCode::Enter), Key(KeyCode::KpEnter)],
+            ),
+            (Action::Back, vec![Key(KeyCode::Escape)]),
+            (Action::Quit, vec![Key(KeyCode::Q)]),
+            (Action::PlaceFlag, vec![Mouse(MouseButton::Left)]),
+            (Action::PickupFlag, vec![Mouse(MouseButton::Right)]),
+            (Action::PanCamera, vec![Mouse(MouseButton::Middle)]),
+            (Action::Save, vec![Key(KeyCode::F5)]),
+            (Action::Load, vec![Key(KeyCode::F9)]),
+            (Action::Settings, vec![Key(KeyCode::Tab)]),
+            (Action::ToggleEditor, vec![Key(KeyCode::F2)]),
+            (Action::ToggleMinimap, vec![Key(KeyCode::M)]),
+            (Action::WorldMap, vec![Key(KeyCode::G)]),
+            (Action::ClickToMove, vec![Key(KeyCode::C)]),
+            (Action::CastCalm, vec![Key(KeyCode::Key1)]),
+            (Action::CastSummon, vec![Key(KeyCode::Key2)]),
+            (Action::CastGust, vec![Key(KeyCode::Key3)]),
+            (Action::CastHaste, vec![Key(KeyCode::Key4)]),
+            (Action::EditorGrab, vec![Mouse(MouseButton::Left)]),
+            (Action::EditorRemove, vec![Mouse(MouseButton::Right)]),
+            (Action::EditorDeleteSelected, vec![Key(KeyCode::Delete)]),
+            (Action::EditorNextField, vec![Key(KeyCode::F4)]),
+            (Action::EditorPrevColor, vec![Key(KeyCode::PageUp)]),
+            (Action::EditorNextColor, vec![Key(KeyCode::PageDown)]),
+            (Action::EditorBackspace, vec![Key(KeyCode::Backspace)]),
+            (Action::EditorNextCamp, vec![Key(KeyCode::F3)]),
+            (Action::EditorNextKind, vec![Key(KeyCode::T)]),
+            (Action::EditorRotateLeft, vec![Key(KeyCode::LeftBracket)]),
+            (Action::EditorRotateRight, vec![Key(KeyCode::RightBracket)]),
+            (Action::EditorShrink, vec![Key(KeyCode::Minus)]),
+            (Action::EditorGrow, vec![Key(KeyCode::Equal)]),
+            (Action::EditorNextVariant, vec![Key(KeyCode::V)]),
+            (Action::EditorToggleCrystal, vec![Key(KeyCode::X)]),
+        ];
+        Self {
+            bindings: defaults.into_iter().collect(),
+        }
+    }
+}
+
+impl ActionMap {
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn set_bindings(&mut self, action: Action, bindings: Vec<Binding>) {
+        self.bindings.insert(action, bindings);
+    }
+
+    /// Adds `binding` to `action`, or removes it if it is already bound.
+    /// The last binding of an `ESSENTIAL_ACTIONS` entry is never removed.
+    pub fn toggle_binding(&mut self, action: Action, binding: Binding) -> BindingChange {
+        let shared_with: Vec<Action> = self
+            .bindings
+            .iter()
+            .filter(|(other, bindings)| **other != action && bindings.contains(&binding))
+            .map(|(other, _)| *other)
+            .collect();
+        let bindings = self.bindings.entry(action).or_default();
+        match bindings.iter().position(|b| *b == binding) {
+            Some(_) if bindings.len() == 1 && ESSENTIAL_ACTIONS.contains(&action) => {
+                BindingChange::KeptLast
+            }
+            Some(index) => {
+                bindings.remove(index);
+                BindingChange::Removed
+            }
+            None => {
+                bindings.push(binding);
+                BindingChange::Added { shared_with }
+            }
+        }
+    }
+
+    /// Name of the first binding, for compact help text.
+    pub fn primary(&self, action: Action) -> String {
+        self.bindings(action)
+            .first()
+            .map_or_else(|| "unbound".to_string(), |binding| binding.name())
+    }
+
+    /// Human-readable list of bindings for help text, e.g. `W/K/Up`.
+    pub fn describe(&self, action: Action) -> String {
+        self.bindings(action)
+            .iter()
+            .map(|binding| binding.name())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    pub fn is_down(&self, action: Action) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|binding| binding.is_down())
+    }
+
+    pub fn is_pressed(&self, action: Action) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|binding| binding.is_pressed())
+    }
+
+    pub fn is_released(&self, action: Action) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|binding| binding.is_released())
+    }
+
+    pub fn movement(&self) -> movement::InputState {
+        movement_from_actions(|action| self.is_down(action))
+    }
+
+    pub fn to_toml(&self) -> Result<String, KeybindError> {
+        let file = KeybindFile {
+            bindings: self
+                .bindings
+                .iter()
+                .map(|(action, bindings)| {
+                    (
+                        action.name().to_string(),
+                        bindings.iter().map(|binding| binding.name()).collect(),
+                    )
+                })
+                .collect(),
+        };
+        toml::to_string(&file).map_err(KeybindError::Serialize)
+    }
+
+    /// Parses a keybind file on top of the defaults, so a file that only
+    /// lists a few actions keeps the stock bindings for the rest.
+    pub fn from_toml(text: &str) -> Result<Self, KeybindError> {
+        let file: KeybindFile = toml::from_str(text).map_err(KeybindError::Parse)?;
+        let mut map = Self::default();
+        for (name, names) in file.bindings {
+            let action = Action::from_name(&name)
+                .ok_or_else(|| KeybindError::UnknownAction(name.clone()))?;
+            let bindings = names
+                .iter()
+                .map(|binding| {
+                    Binding::from_name(binding).ok_or_else(|| KeybindError::UnknownBinding {
+                        action: name.clone(),
+                        binding: binding.clone(),
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            map.set_bindings(action, bindings);
+        }
+        Ok(map)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, KeybindError> {
+        let text = std::fs::read_to_string(path)?;
+        Self::from_toml(&text)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), KeybindError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+
+    /// Loads the keybind file, writing the defaults out first if it does not
+    /// exist yet. Broken files fall back to the defaults.
+    pub fn load_or_create<P: AsRef<Path>>(path: P) -> (Self, Option<KeybindError>) {
+        let path = path.as_ref();
+        if !path.exists() {
+            let map = Self::default();
+            let err = map.save(path).err();
+            return (map, err);
+        }
+        match Self::load(path) {
+            Ok(map) => (map, None),
+            Err(err) => (Self::default(), Some(err)),
+        }
+    }
+}
+
+/// The key or mouse button pressed this frame, if it has a binding name.
+/// Used by the settings scene to capture new bindings.
+pub fn pressed_binding() -> Option<Binding> {
+    if let Some(button) = MOUSE_BUTTONS
+        .iter()
+        .copied()
+        .find(|button| is_mouse_button_pressed(*button))
+    {
+        return Some(Binding::Mouse(button));
+    }
+    macroquad::prelude::get_last_key_pressed()
+        .filter(|key| NAMED_KEYS.contains(key))
+        .map(Binding::Key)
+}
+
+/// Folds the eight movement actions into the four-way movement input, so
+/// vi diagonals press two directions at once.
+pub fn movement_from_actions(is_down: impl Fn(Action) -> bool) -> movement::InputState {
+    let up_left = is_down(Action::MoveUpLeft);
+    let up_right = is_down(Action::MoveUpRight);
+    let down_left = is_down(Action::MoveDownLeft);
+    let down_right = is_down(Action::MoveDownRight);
+    movement::InputState {
+        up: is_down(Action::MoveUp) || up_left || up_right,
+        down: is_down(Action::MoveDown) || down_left || down_right,
+        left: is_down(Action::MoveLeft) || up_left || down_left,
+        right: is_down(Action::MoveRight) || up_right || down_right,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_cover_every_action() {
+        let map = ActionMap::default();
+        for action in ALL_ACTIONS {
+            assert!(!map.bindings(action).is_empty(), "{:?}", action);
+        }
+    }
+
+    #[test]
+    fn defaults_include_vi_and_arrow_keys() {
+        let map = ActionMap::default();
+        assert!(map
+            .bindings(Action::MoveLeft)
+            .contains(&Binding::Key(KeyCode::H)));
+        assert!(map
+            .bindings(Action::MoveDown)
+            .contains(&Binding::Key(KeyCode::J)));
+        assert!(map
+            .bindings(Action::MoveUp)
+            .contains(&Binding::Key(KeyCode::Up)));
+        assert_eq!(
+            map.bindings(Action::MoveUpLeft),
+            &[Binding::Key(KeyCode::Y)]
+        );
+    }
+
+    #[test]
+    fn action_and_binding_names_round_trip() {
+        for action in ALL_ACTIONS {
+            assert_eq!(Action::from_name(action.name()), Some(action));
+        }
+        for key in NAMED_KEYS {
+            let binding = Binding::Key(key);
+            assert_eq!(Binding::from_name(&binding.name()), Some(binding));
+        }
+        for button in MOUSE_BUTTONS {
+            let binding = Binding::Mouse(button);
+            assert_eq!(Binding::from_name(&binding.name()), Some(binding));
+        }
+        assert_eq!(Binding::from_name("NotAKey"), None);
+    }
+
+    #[test]
+    fn toml_round_trip_preserves_bindings() {
+        let mut map = ActionMap::default();
+        map.set_bindings(
+            Action::PlaceFlag,
+            vec![
+                Binding::Key(KeyCode::Space),
+                Binding::Mouse(MouseButton::Left),
+            ],
+        );
+        let text = map.to_toml().expect("serialize keybinds");
+        assert_eq!(ActionMap::from_toml(&text).expect("parse keybinds"), map);
+    }
+
+    #[test]
+    fn partial_file_keeps_default_bindings() {
+        let map = ActionMap::from_toml("[bindings]\nquit = [\"Escape\"]\n").expect("parse");
+        assert_eq!(map.bindings(Action::Quit), &[Binding::Key(KeyCode::Escape)]);
+        assert_eq!(
+            map.bindings(Action::Save),
+            ActionMap::default().bindings(Action::Save)
+        );
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        assert!(matches!(
+            ActionMap::from_toml("[bindings]\nfly = [\"F\"]\n"),
+            Err(KeybindError::UnknownAction(_))
+        ));
+        assert!(matches!(
+            ActionMap::from_toml("[bindings]\nquit = [\"Hyper\"]\n"),
+            Err(KeybindError::UnknownBinding { .. })
+        ));
+    }
+
+    #[test]
+    fn toggle_binding_adds_then_removes() {
+        let mut map = ActionMap::default();
+        let space = Binding::Key(KeyCode::Space);
+        map.toggle_binding(Action::Confirm, space);
+        assert!(map.bindings(Action::Confirm).contains(&space));
+        map.toggle_binding(Action::Confirm, space);
+        assert!(!map.bindings(Action::Confirm).contains(&space));
+        assert_eq!(
+            map.bindings(Action::Confirm),
+            ActionMap::default().bindings(Action::Confirm)
+        );
+    }
+
+    #[test]
+    fn editor_keys_do_not_clash_with_other_keys() {
+        let map = ActionMap::default();
+        let editor = [
+            Action::EditorDeleteSelected,
+            Action::EditorNextField,
+            Action::EditorPrevColor,
+            Action::EditorNextColor,
+            Action::EditorBackspace,
+            Action::EditorNextCamp,
+            Action::EditorNextKind,
+            Action::EditorRotateLeft,
+            Action::EditorRotateRight,
+            Action::EditorShrink,
+            Action::EditorGrow,
+            Action::EditorNextVariant,
+            Action::EditorToggleCrystal,
+        ];
+        for action in editor {
+            for binding in map.bindings(action) {
+                let clash = ALL_ACTIONS
+                    .iter()
+                    .find(|other| **other != action && map.bindings(**other).contains(binding));
+                assert_eq!(clash, None, "{:?} shares {:?}", action, binding);
+            }
+        }
+    }
+
+    #[test]
+    fn toggle_binding_keeps_the_last_essential_binding() {
+        let mut map = ActionMap::default();
+        let escape = Binding::Key(KeyCode::Escape);
+        assert_eq!(map.bindings(Action::Back), &[escape]);
+        assert_eq!(
+            map.toggle_binding(Action::Back, escape),
+            BindingChange::KeptLast
+        );
+        assert_eq!(map.bindings(Action::Back), &[escape]);
+
+        let space = Binding::Key(KeyCode::Space);
+        map.toggle_binding(Action::Back, space);
+        assert_eq!(
+            map.toggle_binding(Action::Back, escape),
+            BindingChange::Removed
+        );
+        assert_eq!(map.bindings(Action::Back), &[space]);
+
+        let q = Binding::Key(KeyCode::Q);
+        map.set_bindings(Action::Quit, vec![q]);
+        assert_eq!(map.toggle_binding(Action::Quit, q), BindingChange::KeptLast);
+        let f2 = Binding::Key(KeyCode::F2);
+        map.set_bindings(Action::ToggleEditor, vec![f2]);
+        assert_eq!(
+            map.toggle_binding(Action::ToggleEditor, f2),
+            BindingChange::Removed
+        );
+    }
+
+    #[test]
+    fn toggle_binding_reports_shared_bindings() {
+        let mut map = ActionMap::default();
+        let m = Binding::Key(KeyCode::M);
+        assert_eq!(
+            map.toggle_binding(Action::Save, m),
+            BindingChange::Added {
+                shared_with: vec![Action::ToggleMinimap]
+            }
+        );
+        let f11 = Binding::Key(KeyCode::F11);
+        assert_eq!(
+            map.toggle_binding(Action::Save, f11),
+            BindingChange::Added {
+                shared_with: Vec::new()
+            }
+        );
+    }
+
+    #[test]
+    fn diagonals_press_two_directions() {
+        let input = movement_from_actions(|action| action == Action::MoveDownRight);
+        assert!(input.down && input.right);
+        assert!(!input.up && !input.left);
+    }
+}
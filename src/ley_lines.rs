@@ -1,20 +1,132 @@
+use crate::constants::{
+    HEPTAGRAM_COLOR_TEAL, HEPTAGRAM_COLOR_VIOLET, HEXAGRAM_COLOR_GOLD, HEXAGRAM_COLOR_WHITE,
+    PENTAGRAM_COLOR_ORANGE, PENTAGRAM_COLOR_RED, SQUARE_COLOR_BLUE, SQUARE_COLOR_CYAN,
+    TRIANGLE_COLOR_GREEN, TRIANGLE_COLOR_LIME,
+};
 use crate::flags::Flag;
+use crate::spatial::SpatialHash;
 use macroquad::prelude::*;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::f32::consts::{PI, TAU};
 
-const PENTAGRAM_RADIUS_TOLERANCE: f32 = 0.45;
-const PENTAGRAM_ANGLE_TOLERANCE: f32 = 0.7;
+const SIGIL_RADIUS_TOLERANCE: f32 = 0.45;
+/// Standard deviation of the corners' distances from the center, over their
+/// mean, at which a figure's score gets nothing for evenness.
+const SIGIL_SCORE_RADIUS_DEVIATION: f32 = 0.225;
+/// Largest miss, in radians, between corners' angles round the center and
+/// a regular figure's. Figures with more than five points get a share of
+/// it so their corners cannot drift into the next one's slot.
+const SIGIL_ANGLE_TOLERANCE: f32 = 0.7;
+/// Nearest neighbors of a flag tried as the next corner of a figure. This
+/// bounds the search around each flag however dense the cluster is.
+const SIGIL_CORNER_CANDIDATES: usize = 8;
+/// How far from where a regular figure would put a corner, as a fraction of
+/// the side, a flag may stand and still be taken as that corner.
+const SIGIL_CORNER_SLACK: f32 = 0.4;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum LeyLineKind {
     Normal,
+    Triangle,
+    Square,
     Pentagram,
+    Hexagram,
+    Heptagram,
+}
+
+impl LeyLineKind {
+    /// The sigil a line belongs to, if any.
+    pub fn sigil(self) -> Option<SigilKind> {
+        match self {
+            LeyLineKind::Normal => None,
+            LeyLineKind::Triangle => Some(SigilKind::Triangle),
+            LeyLineKind::Square => Some(SigilKind::Square),
+            LeyLineKind::Pentagram => Some(SigilKind::Pentagram),
+            LeyLineKind::Hexagram => Some(SigilKind::Hexagram),
+            LeyLineKind::Heptagram => Some(SigilKind::Heptagram),
+        }
+    }
+}
+
+/// A regular figure of flags whose lines are all ley lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SigilKind {
+    Triangle,
+    Square,
+    Pentagram,
+    Hexagram,
+    Heptagram,
+}
+
+impl SigilKind {
+    pub const ALL: [SigilKind; 5] = [
+        SigilKind::Triangle,
+        SigilKind::Square,
+        SigilKind::Pentagram,
+        SigilKind::Hexagram,
+        SigilKind::Heptagram,
+    ];
+
+    /// Corner flags in the figure.
+    pub fn points(self) -> usize {
+        match self {
+            SigilKind::Triangle => 3,
+            SigilKind::Square => 4,
+            SigilKind::Pentagram => 5,
+            SigilKind::Hexagram => 6,
+            SigilKind::Heptagram => 7,
+        }
+    }
+
+    pub fn line_kind(self) -> LeyLineKind {
+        match self {
+            SigilKind::Triangle => LeyLineKind::Triangle,
+            SigilKind::Square => LeyLineKind::Square,
+            SigilKind::Pentagram => LeyLineKind::Pentagram,
+            SigilKind::Hexagram => LeyLineKind::Hexagram,
+            SigilKind::Heptagram => LeyLineKind::Heptagram,
+        }
+    }
+
+    /// The two colors the figure's lines and markers cycle between.
+    pub fn colors(self) -> (Color, Color) {
+        match self {
+            SigilKind::Triangle => (TRIANGLE_COLOR_GREEN, TRIANGLE_COLOR_LIME),
+            SigilKind::Square => (SQUARE_COLOR_BLUE, SQUARE_COLOR_CYAN),
+            SigilKind::Pentagram => (PENTAGRAM_COLOR_RED, PENTAGRAM_COLOR_ORANGE),
+            SigilKind::Hexagram => (HEXAGRAM_COLOR_GOLD, HEXAGRAM_COLOR_WHITE),
+            SigilKind::Heptagram => (HEPTAGRAM_COLOR_VIOLET, HEPTAGRAM_COLOR_TEAL),
+        }
+    }
+
+    /// Longest line of the regular figure over its side.
+    fn span(self) -> f32 {
+        let n = self.points() as f32;
+        (PI * (self.points() / 2) as f32 / n).sin() / (PI / n).sin()
+    }
+
+    fn angle_tolerance(self) -> f32 {
+        SIGIL_ANGLE_TOLERANCE * (5.0 / self.points() as f32).min(1.0)
+    }
+}
+
+/// Where a sigil stands and which figure it is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SigilCenter {
+    pub kind: SigilKind,
+    pub pos: Vec2,
+    /// From 0 to 1: how even the corners' distances from the center are,
+    /// how evenly they go round it and how big the figure is next to the
+    /// biggest the ley line length allows.
+    pub score: f32,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct LeyState {
     pub lines: Vec<LeyLine>,
     pub pentagram_centers: Vec<Vec2>,
+    /// Every sigil, pentagrams included, in flag order.
+    pub sigil_centers: Vec<SigilCenter>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -25,49 +137,9 @@ pub struct LeyLine {
     pub kind: LeyLineKind,
 }
 
-#[derive(Clone, Copy, Debug)]
-struct LineCandidate {
-    indices: (usize, usize),
-    a: Vec2,
-    b: Vec2,
-    intensity: f32,
-}
-
 pub fn compute_ley_state(flags: &[Flag], max_distance: f32) -> LeyState {
-    if flags.len() < 2 || max_distance <= 0.0 {
-        return LeyState {
-            lines: Vec::new(),
-            pentagram_centers: Vec::new(),
-        };
-    }
-
-    let (line_candidates, neighbors) = build_proximity_graph(flags, max_distance);
-    let pentagrams = if flags.len() >= 5 {
-        find_pentagrams(flags, max_distance, &neighbors)
-    } else {
-        Vec::new()
-    };
-    let pentagram_pairs = pentagram_pairs(&pentagrams);
-    let lines = line_candidates
-        .into_iter()
-        .map(|candidate| LeyLine {
-            a: candidate.a,
-            b: candidate.b,
-            intensity: candidate.intensity,
-            kind: if pentagram_pairs.contains(&candidate.indices) {
-                LeyLineKind::Pentagram
-            } else {
-                LeyLineKind::Normal
-            },
-        })
-        .collect();
-
-    let pentagram_centers = pentagrams.iter().map(|p| p.center).collect();
-
-    LeyState {
-        lines,
-        pentagram_centers,
-    }
+    let spatial = SpatialHash::from_positions(max_distance, flags.iter().map(|flag| flag.pos));
+    LeyGraph::build(flags, &spatial, max_distance).state()
 }
 
 #[cfg(test)]
@@ -80,185 +152,431 @@ pub fn pentagram_centers(flags: &[Flag], max_distance: f32) -> Vec<Vec2> {
     compute_ley_state(flags, max_distance).pentagram_centers
 }
 
-struct Pentagram {
-    indices: [usize; 5],
+/// A figure found around one of its corners.
+#[derive(Clone, Debug, PartialEq)]
+struct Figure {
+    kind: SigilKind,
+    /// Flag indices, sorted.
+    indices: Vec<usize>,
+    center: Vec2,
+    /// How far the figure is from regular; lower is better.
+    error: f32,
+    score: f32,
+}
+
+/// What `polygram_fit` makes of a set of corners.
+struct Fit {
     center: Vec2,
+    error: f32,
+    score: f32,
 }
 
-fn build_proximity_graph(
-    flags: &[Flag],
+/// The proximity graph and sigils over a list of flags, kept up to date one
+/// flag at a time instead of rebuilt on every change. Indexed like the flag
+/// list it follows: call `insert` after pushing a flag and `swap_remove`
+/// after `Vec::swap_remove`, each once the `SpatialHash` over the flags has
+/// had the same edit. `state` gives the same result as `compute_ley_state`
+/// on the current flags.
+///
+/// Figures are looked for around each flag separately, from a bounded
+/// number of its nearest neighbors, so an edit only re-checks the flags
+/// whose search it reaches. Where figures overlap, sharing flags, the one
+/// with the most points counts, then the most regular (ties go to the lower
+/// indices). Figures nested inside one another with flags of their own all
+/// count.
+#[derive(Clone, Debug)]
+pub struct LeyGraph {
     max_distance: f32,
-) -> (Vec<LineCandidate>, Vec<Vec<usize>>) {
-    let mut lines = Vec::new();
-    let mut neighbors = vec![Vec::new(); flags.len()];
-    let max_d2 = max_distance * max_distance;
-
-    for i in 0..flags.len() {
-        for j in (i + 1)..flags.len() {
-            let a = flags[i].pos;
-            let b = flags[j].pos;
-            let d2 = a.distance_squared(b);
-            if d2 <= max_d2 {
-                neighbors[i].push(j);
-                neighbors[j].push(i);
-                let d = d2.sqrt();
-                let t = 1.0 - (d / max_distance);
-                lines.push(LineCandidate {
-                    indices: (i, j),
+    positions: Vec<Vec2>,
+    /// Sorted neighbor indices per flag.
+    neighbors: Vec<Vec<usize>>,
+    /// The figure search around each flag.
+    searches: Vec<SigilSearch>,
+}
+
+/// Figures found around one flag, which is always one of their corners.
+#[derive(Clone, Debug, Default)]
+struct SigilSearch {
+    figures: Vec<Figure>,
+    /// How far from the flag the search looked. Flags added or removed
+    /// further out cannot change what it finds.
+    reach: f32,
+}
+
+impl LeyGraph {
+    pub fn new(max_distance: f32) -> Self {
+        Self {
+            max_distance,
+            positions: Vec::new(),
+            neighbors: Vec::new(),
+            searches: Vec::new(),
+        }
+    }
+
+    /// A graph over `flags`, with `spatial` indexing their positions.
+    pub fn build(flags: &[Flag], spatial: &SpatialHash, max_distance: f32) -> Self {
+        let mut graph = Self::new(max_distance);
+        graph.positions = flags.iter().map(|flag| flag.pos).collect();
+        graph.neighbors = (0..flags.len())
+            .map(|index| graph.nearby(spatial, index))
+            .collect();
+        graph.searches = vec![SigilSearch::default(); flags.len()];
+        graph.refresh(spatial, 0..flags.len());
+        graph
+    }
+
+    pub fn max_distance(&self) -> f32 {
+        self.max_distance
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Adds a flag at `pos` as the last index.
+    pub fn insert(&mut self, pos: Vec2, spatial: &SpatialHash) {
+        let index = self.positions.len();
+        debug_assert_eq!(index + 1, spatial.len());
+        self.positions.push(pos);
+        let neighbors = self.nearby(spatial, index);
+        for other in &neighbors {
+            self.neighbors[*other].push(index);
+        }
+        let mut anchors = self.reached_by(pos, &neighbors);
+        anchors.push(index);
+        self.neighbors.push(neighbors);
+        self.searches.push(SigilSearch::default());
+        self.refresh(spatial, anchors);
+    }
+
+    /// Removes flag `index`, moving the last flag into its place like
+    /// `Vec::swap_remove`.
+    pub fn swap_remove(&mut self, index: usize, spatial: &SpatialHash) {
+        let last = self.positions.len() - 1;
+        let removed = std::mem::take(&mut self.neighbors[index]);
+        for other in &removed {
+            remove_sorted(&mut self.neighbors[*other], index);
+        }
+        let mut anchors = self.reached_by(self.positions[index], &removed);
+
+        if index != last {
+            // Renumbering can change how ties between equally distant flags
+            // break, so searches that reach the moved flag run again too.
+            let moved = std::mem::take(&mut self.neighbors[last]);
+            anchors.extend(self.reached_by(self.positions[last], &moved));
+            anchors.push(last);
+            for other in &moved {
+                let list = &mut self.neighbors[*other];
+                remove_sorted(list, last);
+                let slot = list.partition_point(|item| *item < index);
+                list.insert(slot, index);
+            }
+            self.neighbors[index] = moved;
+        }
+        self.neighbors.pop();
+        self.positions.swap_remove(index);
+        self.searches.swap_remove(index);
+
+        for anchor in &mut anchors {
+            if *anchor == last {
+                *anchor = index;
+            }
+        }
+        anchors.sort_unstable();
+        anchors.dedup();
+        self.refresh(spatial, anchors);
+    }
+
+    /// Lines and sigil centers over the current flags.
+    pub fn state(&self) -> LeyState {
+        let sigils = self.sigils();
+        let mut sigil_pairs = HashMap::new();
+        for sigil in &sigils {
+            let indices = &sigil.indices;
+            for i in 0..indices.len() {
+                for j in (i + 1)..indices.len() {
+                    sigil_pairs.insert((indices[i], indices[j]), sigil.kind.line_kind());
+                }
+            }
+        }
+
+        let mut lines = Vec::new();
+        for (i, neighbors) in self.neighbors.iter().enumerate() {
+            let a = self.positions[i];
+            for &j in neighbors_after(neighbors, i) {
+                let b = self.positions[j];
+                let t = 1.0 - (a.distance(b) / self.max_distance);
+                lines.push(LeyLine {
                     a,
                     b,
                     intensity: (t * t).clamp(0.0, 1.0),
+                    kind: sigil_pairs
+                        .get(&(i, j))
+                        .copied()
+                        .unwrap_or(LeyLineKind::Normal),
                 });
             }
         }
-    }
 
-    (lines, neighbors)
-}
-
-fn pentagram_pairs(pentagrams: &[Pentagram]) -> HashSet<(usize, usize)> {
-    let mut pairs = HashSet::new();
-    for pentagram in pentagrams {
-        for i in 0..5 {
-            for j in (i + 1)..5 {
-                let mut u = pentagram.indices[i];
-                let mut v = pentagram.indices[j];
-                if u > v {
-                    std::mem::swap(&mut u, &mut v);
-                }
-                pairs.insert((u, v));
-            }
+        LeyState {
+            lines,
+            pentagram_centers: sigils
+                .iter()
+                .filter(|sigil| sigil.kind == SigilKind::Pentagram)
+                .map(|sigil| sigil.center)
+                .collect(),
+            sigil_centers: sigils
+                .iter()
+                .map(|sigil| SigilCenter {
+                    kind: sigil.kind,
+                    pos: sigil.center,
+                    score: sigil.score,
+                })
+                .collect(),
         }
     }
 
-    pairs
-}
+    /// The figures that count, in index order: bigger figures first claim
+    /// their flags, the most regular first among equals, and any figure
+    /// sharing one of them is dropped.
+    fn sigils(&self) -> Vec<Figure> {
+        let unique: BTreeMap<Vec<usize>, &Figure> = self
+            .searches
+            .iter()
+            .flat_map(|search| &search.figures)
+            .map(|figure| (figure.indices.clone(), figure))
+            .collect();
+        let mut candidates: Vec<&Figure> = unique.into_values().collect();
+        candidates.sort_by(|a, b| {
+            b.kind
+                .points()
+                .cmp(&a.kind.points())
+                .then(a.error.total_cmp(&b.error))
+                .then_with(|| a.indices.cmp(&b.indices))
+        });
 
-fn neighbors_after(sorted_neighbors: &[usize], min_index: usize) -> &[usize] {
-    let mut start = 0;
-    while start < sorted_neighbors.len() && sorted_neighbors[start] <= min_index {
-        start += 1;
+        let mut claimed = HashSet::new();
+        let mut chosen = Vec::new();
+        for candidate in candidates {
+            if candidate
+                .indices
+                .iter()
+                .any(|index| claimed.contains(index))
+            {
+                continue;
+            }
+            claimed.extend(candidate.indices.iter().copied());
+            chosen.push(candidate.clone());
+        }
+        chosen.sort_by(|a, b| a.indices.cmp(&b.indices));
+        chosen
     }
-    &sorted_neighbors[start..]
-}
 
-fn intersect_sorted(left: &[usize], right: &[usize]) -> Vec<usize> {
-    let mut i = 0;
-    let mut j = 0;
-    let mut out = Vec::with_capacity(left.len().min(right.len()));
-
-    while i < left.len() && j < right.len() {
-        match left[i].cmp(&right[j]) {
-            std::cmp::Ordering::Less => i += 1,
-            std::cmp::Ordering::Greater => j += 1,
-            std::cmp::Ordering::Equal => {
-                out.push(left[i]);
-                i += 1;
-                j += 1;
-            }
+    /// Flags within `max_distance` of flag `index`, not counting itself.
+    fn nearby(&self, spatial: &SpatialHash, index: usize) -> Vec<usize> {
+        if self.max_distance <= 0.0 {
+            return Vec::new();
         }
+        let mut nearby = spatial.within(self.positions[index], self.max_distance);
+        nearby.retain(|other| *other != index);
+        nearby
     }
 
-    out
-}
+    /// Those of `candidates` whose figure search reaches `pos`.
+    fn reached_by(&self, pos: Vec2, candidates: &[usize]) -> Vec<usize> {
+        candidates
+            .iter()
+            .copied()
+            .filter(|anchor| self.positions[*anchor].distance(pos) <= self.searches[*anchor].reach)
+            .collect()
+    }
 
-fn find_pentagrams(flags: &[Flag], max_distance: f32, neighbors: &[Vec<usize>]) -> Vec<Pentagram> {
-    let mut pentagrams = Vec::new();
-    if flags.len() < 5 {
-        return pentagrams;
+    fn refresh(&mut self, spatial: &SpatialHash, anchors: impl IntoIterator<Item = usize>) {
+        for anchor in anchors {
+            self.searches[anchor] = self.search_around(spatial, anchor);
+        }
     }
 
-    for a in 0..flags.len() - 4 {
-        let b_candidates = neighbors_after(&neighbors[a], a);
-        if b_candidates.len() < 4 {
-            continue;
+    /// Figures with flag `anchor` as a corner. For each kind of figure, each
+    /// of its nearest neighbors is tried as the next corner, going either
+    /// way round, and the other corners are looked up where a regular figure
+    /// would put them.
+    fn search_around(&self, spatial: &SpatialHash, anchor: usize) -> SigilSearch {
+        let neighbors = &self.neighbors[anchor];
+        let origin = self.positions[anchor];
+        let closer = |a: &usize, b: &usize| {
+            origin
+                .distance_squared(self.positions[*a])
+                .total_cmp(&origin.distance_squared(self.positions[*b]))
+                .then(a.cmp(b))
+        };
+        let mut nearest = neighbors.clone();
+        // While every neighbor is tried, any new one changes the search.
+        let mut reach = f32::INFINITY;
+        if nearest.len() > SIGIL_CORNER_CANDIDATES {
+            nearest.select_nth_unstable_by(SIGIL_CORNER_CANDIDATES - 1, closer);
+            nearest.truncate(SIGIL_CORNER_CANDIDATES);
+            let farthest = nearest
+                .iter()
+                .map(|index| origin.distance(self.positions[*index]))
+                .fold(0.0, f32::max);
+            let span = SigilKind::ALL
+                .iter()
+                .map(|kind| kind.span())
+                .fold(0.0, f32::max);
+            // Corners are looked for at most the longest line of a figure
+            // away, give or take the slack; a little more covers rounding.
+            reach = farthest * (span + SIGIL_CORNER_SLACK) * 1.01;
         }
 
-        for (b_pos, &b) in b_candidates.iter().enumerate() {
-            let c_candidates = intersect_sorted(&b_candidates[(b_pos + 1)..], &neighbors[b]);
-            if c_candidates.len() < 3 {
+        let mut figures = Vec::new();
+        for kind in SigilKind::ALL {
+            let points = kind.points();
+            if neighbors.len() + 1 < points {
                 continue;
             }
-
-            for (c_pos, &c) in c_candidates.iter().enumerate() {
-                let d_candidates = intersect_sorted(&c_candidates[(c_pos + 1)..], &neighbors[c]);
-                if d_candidates.len() < 2 {
+            let step = TAU / points as f32;
+            for &next in &nearest {
+                let side = self.positions[next] - origin;
+                let length = side.length();
+                if length <= f32::EPSILON {
                     continue;
                 }
-
-                for (d_pos, &d) in d_candidates.iter().enumerate() {
-                    let e_candidates =
-                        intersect_sorted(&d_candidates[(d_pos + 1)..], &neighbors[d]);
-                    for &e in &e_candidates {
-                        let indices = [a, b, c, d, e];
-                        if let Some(center) = pentagram_center(indices, flags, max_distance) {
-                            pentagrams.push(Pentagram { indices, center });
+                let apothem = length * 0.5 / (PI / points as f32).tan();
+                'turns: for turn in [1.0, -1.0] {
+                    let center = origin + side * 0.5 + side.perp() / length * apothem * turn;
+                    let spoke = origin - center;
+                    let mut corners = vec![anchor, next];
+                    for k in 2..points {
+                        let predicted =
+                            center + Vec2::from_angle(turn * step * k as f32).rotate(spoke);
+                        let distance =
+                            |index: &usize| predicted.distance_squared(self.positions[*index]);
+                        let corner = spatial
+                            .within(predicted, length * SIGIL_CORNER_SLACK)
+                            .into_iter()
+                            .filter(|index| {
+                                !corners.contains(index) && neighbors.binary_search(index).is_ok()
+                            })
+                            .min_by(|a, b| distance(a).total_cmp(&distance(b)).then(a.cmp(b)));
+                        match corner {
+                            Some(corner) => corners.push(corner),
+                            None => continue 'turns,
                         }
                     }
+
+                    // Every line of the figure has to be a ley line too.
+                    let linked = |a: usize, b: usize| self.neighbors[a].binary_search(&b).is_ok();
+                    let all_linked = (1..points)
+                        .all(|i| (i + 1..points).all(|j| linked(corners[i], corners[j])));
+                    if !all_linked {
+                        continue;
+                    }
+                    corners.sort_unstable();
+                    let mut positions: Vec<Vec2> =
+                        corners.iter().map(|index| self.positions[*index]).collect();
+                    if let Some(fit) = polygram_fit(kind, &mut positions, self.max_distance) {
+                        figures.push(Figure {
+                            kind,
+                            indices: corners,
+                            center: fit.center,
+                            error: fit.error,
+                            score: fit.score,
+                        });
+                    }
                 }
             }
         }
+        SigilSearch { figures, reach }
     }
+}
 
-    pentagrams
+fn remove_sorted(list: &mut Vec<usize>, item: usize) {
+    if let Ok(slot) = list.binary_search(&item) {
+        list.remove(slot);
+    }
 }
 
-fn pentagram_center(indices: [usize; 5], flags: &[Flag], max_distance: f32) -> Option<Vec2> {
+fn neighbors_after(sorted_neighbors: &[usize], min_index: usize) -> &[usize] {
+    let start = sorted_neighbors.partition_point(|item| *item <= min_index);
+    &sorted_neighbors[start..]
+}
+
+/// The center of the `kind` figure with corners at `positions`, how far it
+/// is from regular and its score, or `None` when they are too uneven to
+/// count as one.
+fn polygram_fit(kind: SigilKind, positions: &mut [Vec2], max_distance: f32) -> Option<Fit> {
+    // Work through the points in position order so the result depends only
+    // on where the flags are, not on their indices.
+    positions.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    let count = positions.len();
+
     let mut centroid = Vec2::ZERO;
-    for idx in indices {
-        centroid += flags[idx].pos;
+    for pos in positions.iter() {
+        centroid += *pos;
     }
-    centroid /= 5.0;
+    centroid /= count as f32;
 
-    let mut points: Vec<(f32, usize, Vec2, f32)> = Vec::with_capacity(5);
+    let mut angles: Vec<f32> = Vec::with_capacity(count);
+    let mut radii: Vec<f32> = Vec::with_capacity(count);
     let mut min_r = f32::MAX;
     let mut max_r: f32 = 0.0;
     let mut sum_r = 0.0;
 
-    for idx in indices {
-        let pos = flags[idx].pos;
-        let offset = pos - centroid;
-        let angle = offset.y.atan2(offset.x);
+    for pos in positions.iter() {
+        let offset = *pos - centroid;
         let r = offset.length();
         min_r = min_r.min(r);
         max_r = max_r.max(r);
         sum_r += r;
-        points.push((angle, idx, pos, r));
+        radii.push(r);
+        angles.push(offset.y.atan2(offset.x));
     }
 
-    let mean_r = sum_r / 5.0;
+    let mean_r = sum_r / count as f32;
     if mean_r <= f32::EPSILON {
         return None;
     }
 
-    if (max_r - min_r) / mean_r > PENTAGRAM_RADIUS_TOLERANCE {
+    let radius_error = (max_r - min_r) / mean_r;
+    if radius_error > SIGIL_RADIUS_TOLERANCE {
         return None;
     }
 
-    points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-    let expected = std::f32::consts::TAU / 5.0;
-    for i in 0..5 {
-        let angle = points[i].0;
-        let next_angle = if i == 4 {
-            points[0].0 + std::f32::consts::TAU
+    angles.sort_by(|a, b| a.total_cmp(b));
+    let expected = TAU / count as f32;
+    let tolerance = kind.angle_tolerance();
+    let mut angle_error: f32 = 0.0;
+    for i in 0..count {
+        let next_angle = if i == count - 1 {
+            angles[0] + TAU
         } else {
-            points[i + 1].0
+            angles[i + 1]
         };
-        let diff = next_angle - angle;
-        if (diff - expected).abs() > PENTAGRAM_ANGLE_TOLERANCE {
+        let diff = (next_angle - angles[i] - expected).abs();
+        if diff > tolerance {
             return None;
         }
+        angle_error = angle_error.max(diff);
     }
 
-    for i in 0..5 {
-        let a = points[i].2;
-        let b = points[(i + 2) % 5].2;
-        if a.distance(b) > max_distance {
-            return None;
-        }
-    }
+    let variance = radii.iter().map(|r| (r - mean_r).powi(2)).sum::<f32>() / count as f32;
+    let radius_score = 1.0 - variance.sqrt() / mean_r / SIGIL_SCORE_RADIUS_DEVIATION;
+    let angle_score = 1.0 - angle_error / tolerance;
+    // The longest line of the figure is at most a ley line long.
+    let largest_radius = max_distance / (2.0 * (PI / count as f32).sin() * kind.span());
+    let size_score = mean_r / largest_radius;
+    let score = (radius_score.clamp(0.0, 1.0) + angle_score + size_score.clamp(0.0, 1.0)) / 3.0;
 
-    Some(centroid)
+    Some(Fit {
+        center: centroid,
+        error: radius_error / SIGIL_RADIUS_TOLERANCE + angle_error / tolerance,
+        score,
+    })
 }
 
 #[cfg(test)]
@@ -483,4 +801,225 @@ mod tests {
             10
         );
     }
+
+    /// Applies random placements and removals to a flag list, a spatial
+    /// index and a ley graph together, checking the graph against a full
+    /// recompute after every edit. Flags land on jittered pentagon corners
+    /// around a few spots so pentagrams keep forming and breaking.
+    /// Returns how many pentagrams were seen along the way.
+    fn check_random_edits(seed: u32, steps: usize, max_distance: f32) -> usize {
+        let mut rng = crate::rng::Rng::new(seed);
+        let spots = [
+            vec2(0.0, 0.0),
+            vec2(60.0, 30.0),
+            vec2(400.0, 300.0),
+            vec2(900.0, 0.0),
+            vec2(-500.0, 200.0),
+            vec2(300.0, -600.0),
+        ];
+        let mut flags: Vec<Flag> = Vec::new();
+        let mut spatial = SpatialHash::new(max_distance);
+        let mut graph = LeyGraph::new(max_distance);
+        let mut pentagrams = 0;
+
+        for _ in 0..steps {
+            let remove = !flags.is_empty() && rng.next_f32() < 0.4;
+            if remove {
+                let index = rng.next_u32() as usize % flags.len();
+                flags.swap_remove(index);
+                spatial.swap_remove(index);
+                graph.swap_remove(index, &spatial);
+            } else {
+                let spot = spots[rng.next_u32() as usize % spots.len()];
+                let corner = (rng.next_u32() % 5) as f32 * std::f32::consts::TAU / 5.0;
+                let radius = rng.range(35.0, 50.0);
+                let pos = spot
+                    + vec2(corner.cos(), corner.sin()) * radius
+                    + vec2(rng.range(-4.0, 4.0), rng.range(-4.0, 4.0));
+                flags.push(crate::flags::make_flag(pos));
+                spatial.push(pos);
+                graph.insert(pos, &spatial);
+            }
+            assert_eq!(graph.len(), flags.len());
+            let state = graph.state();
+            assert_eq!(state, compute_ley_state(&flags, max_distance));
+            pentagrams += state.pentagram_centers.len();
+        }
+        pentagrams
+    }
+
+    #[test]
+    fn ley_graph_edits_match_full_recompute() {
+        let mut pentagrams = 0;
+        for seed in 1..=6 {
+            pentagrams += check_random_edits(seed, 100, 90.0);
+        }
+        // The layouts do make pentagrams, so the comparison covers them.
+        assert!(pentagrams > 0);
+    }
+
+    #[test]
+    fn ley_graph_removal_breaks_pentagrams_and_renumbers() {
+        let mut flags = vec![crate::flags::make_flag(vec2(500.0, 500.0))];
+        for i in 0..5 {
+            let angle = i as f32 * std::f32::consts::TAU / 5.0;
+            flags.push(crate::flags::make_flag(
+                vec2(angle.cos(), angle.sin()) * 45.0,
+            ));
+        }
+        let mut spatial = SpatialHash::from_positions(90.0, flags.iter().map(|flag| flag.pos));
+        let mut graph = LeyGraph::build(&flags, &spatial, 90.0);
+        assert_eq!(graph.state().pentagram_centers.len(), 1);
+
+        // Removing the lone flag moves a pentagram corner to index 0.
+        flags.swap_remove(0);
+        spatial.swap_remove(0);
+        graph.swap_remove(0, &spatial);
+        assert_eq!(graph.state(), compute_ley_state(&flags, 90.0));
+        assert_eq!(graph.state().pentagram_centers.len(), 1);
+
+        flags.swap_remove(2);
+        spatial.swap_remove(2);
+        graph.swap_remove(2, &spatial);
+        let state = graph.state();
+        assert!(state.pentagram_centers.is_empty());
+        assert_eq!(state.lines.len(), 6);
+        // Three of the four corners left can still make a triangle.
+        assert!(state
+            .lines
+            .iter()
+            .all(|line| line.kind != LeyLineKind::Pentagram));
+        assert_eq!(state, compute_ley_state(&flags, 90.0));
+    }
+
+    fn star(center: Vec2, radius: f32, turn: f32) -> Vec<Flag> {
+        figure(5, center, radius, turn)
+    }
+
+    fn figure(points: usize, center: Vec2, radius: f32, turn: f32) -> Vec<Flag> {
+        (0..points)
+            .map(|i| {
+                let angle = turn + i as f32 * std::f32::consts::TAU / points as f32;
+                crate::flags::make_flag(center + vec2(angle.cos(), angle.sin()) * radius)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn overlapping_stars_keep_the_most_regular() {
+        // A second, lopsided star shares the first one's corner at (45, 0).
+        let mut flags = star(Vec2::ZERO, 45.0, 0.0);
+        for (i, flag) in star(vec2(90.0, 0.0), 45.0, std::f32::consts::PI)
+            .into_iter()
+            .enumerate()
+            .skip(1)
+        {
+            let squash = if i % 2 == 0 { 1.15 } else { 0.9 };
+            flags.push(crate::flags::make_flag(
+                vec2(90.0, 0.0) + (flag.pos - vec2(90.0, 0.0)) * squash,
+            ));
+        }
+
+        let centers = pentagram_centers(&flags, 100.0);
+        assert_eq!(centers.len(), 1);
+        assert!(centers[0].length() < 1e-3);
+    }
+
+    #[test]
+    fn nested_stars_with_their_own_flags_both_count() {
+        let mut flags = star(Vec2::ZERO, 45.0, 0.0);
+        flags.extend(star(Vec2::ZERO, 18.0, std::f32::consts::PI / 5.0));
+        let centers = pentagram_centers(&flags, 100.0);
+        assert_eq!(centers.len(), 2);
+        assert!(centers.iter().all(|center| center.length() < 1e-3));
+    }
+
+    #[test]
+    fn dense_clusters_give_stars_without_shared_flags() {
+        let mut rng = crate::rng::Rng::new(9);
+        let flags: Vec<Flag> = (0..300)
+            .map(|_| crate::flags::make_flag(vec2(rng.range(0.0, 60.0), rng.range(0.0, 60.0))))
+            .collect();
+        let spatial = SpatialHash::from_positions(90.0, flags.iter().map(|flag| flag.pos));
+        let graph = LeyGraph::build(&flags, &spatial, 90.0);
+        let sigils = graph.sigils();
+        assert!(!sigils.is_empty());
+
+        let mut claimed = HashSet::new();
+        for sigil in &sigils {
+            assert!(sigil.indices.iter().all(|index| claimed.insert(*index)));
+        }
+        // Each flag searches a bounded set of its neighbors per figure.
+        assert!(graph.searches.iter().all(
+            |search| search.figures.len() <= 2 * SIGIL_CORNER_CANDIDATES * SigilKind::ALL.len()
+        ));
+    }
+
+    #[test]
+    fn each_regular_figure_gets_its_own_kind() {
+        for kind in SigilKind::ALL {
+            let flags = figure(kind.points(), vec2(200.0, 100.0), 30.0, 0.3);
+            let state = compute_ley_state(&flags, 90.0);
+            assert_eq!(state.sigil_centers.len(), 1, "{:?}", kind);
+            assert_eq!(state.sigil_centers[0].kind, kind);
+            assert!((state.sigil_centers[0].pos - vec2(200.0, 100.0)).length() < 1e-3);
+            let pairs = kind.points() * (kind.points() - 1) / 2;
+            assert_eq!(state.lines.len(), pairs);
+            assert!(state
+                .lines
+                .iter()
+                .all(|line| line.kind.sigil() == Some(kind)));
+            assert_eq!(
+                state.pentagram_centers.len(),
+                usize::from(kind == SigilKind::Pentagram)
+            );
+        }
+    }
+
+    #[test]
+    fn figures_side_by_side_keep_their_kinds() {
+        let mut flags = figure(3, vec2(0.0, 0.0), 30.0, 0.0);
+        flags.extend(figure(4, vec2(400.0, 0.0), 30.0, 0.2));
+        flags.extend(figure(6, vec2(0.0, 400.0), 35.0, 0.1));
+        let state = compute_ley_state(&flags, 90.0);
+        let mut kinds: Vec<SigilKind> =
+            state.sigil_centers.iter().map(|sigil| sigil.kind).collect();
+        kinds.sort();
+        assert_eq!(
+            kinds,
+            vec![SigilKind::Triangle, SigilKind::Square, SigilKind::Hexagram]
+        );
+        assert!(state.pentagram_centers.is_empty());
+    }
+
+    #[test]
+    fn bigger_figures_claim_their_flags_first() {
+        // A regular hexagon also holds two equilateral triangles.
+        let flags = figure(6, Vec2::ZERO, 40.0, 0.0);
+        let state = compute_ley_state(&flags, 90.0);
+        assert_eq!(state.sigil_centers.len(), 1);
+        assert_eq!(state.sigil_centers[0].kind, SigilKind::Hexagram);
+    }
+
+    #[test]
+    fn scores_favor_even_and_large_stars() {
+        let score = |flags: &[Flag]| {
+            let state = compute_ley_state(flags, 90.0);
+            assert_eq!(state.sigil_centers.len(), 1);
+            state.sigil_centers[0].score
+        };
+        // A pentagram's star lines reach a ley line's length at this radius.
+        let largest = score(&star(Vec2::ZERO, 47.0, 0.0));
+        assert!(largest > 0.95, "{}", largest);
+
+        let small = score(&star(Vec2::ZERO, 20.0, 0.0));
+        assert!(small < largest - 0.1);
+
+        let mut uneven = star(Vec2::ZERO, 47.0, 0.0);
+        uneven[1].pos *= 0.85;
+        uneven[3].pos *= 0.85;
+        let uneven = score(&uneven);
+        assert!(uneven < largest - 0.1);
+        assert!(uneven >= 0.0);
+    }
 }
@@ -0,0 +1,47 @@
+// Lints the original code predates; keep them quiet rather than churn it.
+#![allow(
+    clippy::assertions_on_constants,
+    clippy::collapsible_if,
+    clippy::if_same_then_else,
+    clippy::manual_div_ceil,
+    clippy::manual_is_multiple_of,
+    clippy::needless_lifetimes,
+    clippy::unnecessary_sort_by
+)]
+
+pub mod args;
+pub mod assets;
+pub mod camera;
+pub mod camp_editor;
+pub mod camps;
+pub mod collision;
+pub mod constants;
+pub mod fire;
+pub mod flag_state;
+pub mod flags;
+pub mod geom;
+pub mod hud;
+pub mod input;
+pub mod ley_lines;
+pub mod map;
+pub mod minimap;
+pub mod movement;
+pub mod nav;
+pub mod npc;
+pub mod player;
+pub mod replay;
+pub mod rng;
+pub mod save;
+pub mod scale;
+pub mod scene;
+pub mod scenery;
+pub mod scenery_editor;
+pub mod settings;
+pub mod spatial;
+pub mod spells;
+pub mod tile_cache;
+pub mod tile_slicer;
+pub mod timestep;
+pub mod tuning;
+pub mod world;
+pub mod world_map;
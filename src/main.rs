@@ -1,225 +1,291 @@
 use macroquad::prelude::*;
-
-mod assets;
-mod camera;
-mod camps;
-mod constants;
-mod fire;
-mod flag_state;
-mod flags;
-mod geom;
-mod hud;
-mod ley_lines;
-mod map;
-mod movement;
-mod npc;
-mod player;
-mod scale;
-mod scenery;
-
-use constants::*;
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Scene {
-    Title,
-    ClassSelect,
-    Dungeon,
-}
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum ClassChoice {
-    Vexillomancer,
-    StressTest,
+use std::path::{Path, PathBuf};
+
+use flaghack2::args::{arg_value, parse_seed_arg};
+use flaghack2::assets::AssetError;
+use flaghack2::camp_editor::{CampEditor, EditorField};
+use flaghack2::constants::*;
+use flaghack2::input::{self, Action, ActionMap, Binding, BindingChange};
+use flaghack2::minimap::{self, Minimap};
+use flaghack2::replay::{Replay, ReplayFrame, ReplayPlayer, ReplayRecorder};
+use flaghack2::save;
+use flaghack2::scene::{Scene, SceneStack, Transition};
+use flaghack2::scenery::{DomeDecoration, SceneryLayout};
+use flaghack2::scenery_editor::SceneryEditor;
+use flaghack2::settings::{self, Settings};
+use flaghack2::timestep::FixedTimestep;
+use flaghack2::tuning::{self, Tuning, TuningWatcher};
+use flaghack2::world::{self, ClassChoice, PositionSnapshot, World, WorldConfig, CLASS_OPTIONS};
+use flaghack2::world_map::{self, WorldMapView};
+use flaghack2::{
+    assets, camera, camps, flags, hud, ley_lines, map, npc, player, scale, scenery, spells,
+};
+
+/// State shared by every scene for the lifetime of the window.
+struct App {
+    assets: Assets,
+    /// Directory holding `assets/`; asset paths are relative to it.
+    asset_root: PathBuf,
+    actions: ActionMap,
+    settings: Settings,
+    tuning: Tuning,
+    tuning_watcher: TuningWatcher,
+    camps: Vec<camps::CampConfig>,
+    scenery: SceneryLayout,
+    map: map::TileMap,
+    seed: u32,
+    record_path: Option<String>,
+    title_message: Option<String>,
+    /// Set on the world map; the dungeon HUD points towards it.
+    waypoint: Option<Vec2>,
 }
 
-impl ClassChoice {
-    fn name(self) -> &'static str {
-        match self {
-            ClassChoice::Vexillomancer => "Vexillomancer",
-            ClassChoice::StressTest => "Stress Test",
+impl App {
+    fn world_config(&self) -> WorldConfig {
+        WorldConfig {
+            tuning: self.tuning.clone(),
+            camps: self.camps.clone(),
+            scenery: self.scenery.clone(),
         }
     }
 }
 
-const CLASS_OPTIONS: [ClassChoice; 2] = [ClassChoice::Vexillomancer, ClassChoice::StressTest];
+struct Assets {
+    signifier_mark: Texture2D,
+    signifier_size: Vec2,
+}
 
-#[derive(Clone, Copy, Debug)]
-struct Player {
-    pos: Vec2,
-    facing: player::Facing,
+struct TitleScene;
+
+/// Shown instead of the title screen when required assets fail to load.
+struct AssetErrorScene {
+    lines: Vec<String>,
 }
 
-struct Game {
-    scene: Scene,
-    player: Player,
+struct ClassSelectScene {
     class_index: usize,
-    flag_state: flag_state::FlagState,
-    wind: flags::Wind,
-    scenery: Vec<scenery::SceneryItem>,
-    ley_lines: Vec<ley_lines::LeyLine>,
-    pentagram_centers: Vec<Vec2>,
+}
+
+struct DungeonScene {
+    world: World,
     pentagram_sparkles: Vec<PentagramSparkle>,
     sparkle_spawn_accum: f32,
     sparkle_spawn_counter: u32,
-    flagic: u8,
-    flagic_accum: f32,
-    camp_notices: Vec<CampNotice>,
-    camp_vertices: Vec<Vec<Vec2>>,
-    hippies: Vec<npc::Hippie>,
-    map: map::TileMap,
-    camp_regions: Vec<map::MapRegion>,
     camera: camera::CameraState,
-    player_speed: f32,
+    recorder: Option<ReplayRecorder>,
+    playback: Option<ReplayPlayer>,
+    autosave_timer: f32,
+    clock: FixedTimestep,
+    previous: PositionSnapshot,
+    pending_input: world::InputFrame,
+    frame_steps: u32,
+    editor: Option<EditorMode>,
+    editor_cursor: Vec2,
+    show_minimap: bool,
+    /// While on, the place-flag click walks the player to the cursor.
+    click_to_move: bool,
 }
 
-struct CampNotice {
-    camp_name: &'static str,
-    text: &'static str,
-    inside: bool,
-    timer: f32,
+/// Map editing inside the dungeon. The simulation is frozen while an editor
+/// is open.
+enum EditorMode {
+    Camp(CampEditorMode),
+    Scenery(SceneryEditorMode),
 }
 
-impl CampNotice {
-    fn new(camp_name: &'static str, text: &'static str) -> Self {
-        Self {
-            camp_name,
-            text,
-            inside: false,
-            timer: -1.0,
-        }
-    }
+struct CampEditorMode {
+    editor: CampEditor,
+    /// 0 edits a new camp, `i + 1` edits `App::camps[i]`.
+    source: usize,
+    message: Option<String>,
 }
 
-struct Assets {
-    signifier_mark: Texture2D,
-    signifier_size: Vec2,
+struct SceneryEditorMode {
+    editor: SceneryEditor,
+    message: Option<String>,
 }
 
-impl Game {
-    fn new() -> Self {
-        Self::new_with_class(ClassChoice::Vexillomancer)
-    }
+const PAUSE_OPTIONS: [&str; 3] = ["Resume", "Settings", "Quit to title"];
 
-    fn new_with_class(class_choice: ClassChoice) -> Self {
-        let map = map::TileMap::load_from_dir(MAP_TILE_DIR);
-        let field_rect = map.field_rect();
-        let camp_configs = camps::camp_configs();
-        let camp_regions = camp_configs
-            .iter()
-            .map(|camp| map::MapRegion::new(camp.name, camp.vertices.clone(), camp.color))
-            .collect::<Vec<_>>();
-        let camp_notices = camp_configs
-            .iter()
-            .map(|camp| CampNotice::new(camp.name, camp.notice_text))
-            .collect::<Vec<_>>();
-        let camp_vertices = camps::collect_camp_vertices(&camp_configs);
-        let hippies = match class_choice {
-            ClassChoice::Vexillomancer => {
-                let mut hippies = Vec::new();
-                for (camp_index, camp) in camp_configs.iter().enumerate() {
-                    if !camp.spawns.hippies.is_empty() {
-                        hippies.extend(npc::spawn_hippies(
-                            &camp.spawns.hippies,
-                            camp_index,
-                            &camp.vertices,
-                        ));
-                    }
-                }
-                hippies
-            }
-            ClassChoice::StressTest => spawn_stress_test_hippies(&camp_configs),
-        };
-        let camp_spawns = camps::collect_scenery_spawns(&camp_configs);
-        let ground_flags =
-            flags::spawn_random_flags(FLAG_COUNT_START, field_rect, 40.0 * scale::MODEL_SCALE);
-        let mut ground_flags = ground_flags;
-        for pos in camps::collect_flag_spawns(&camp_configs) {
-            ground_flags.push(flags::make_flag(pos));
-        }
-        let total_flags =
-            ground_flags.len() as u32 + STARTING_FLAG_INVENTORY + total_hippie_flags(&hippies);
-        let flag_state =
-            flag_state::FlagState::new(ground_flags, STARTING_FLAG_INVENTORY, total_flags);
-        let ley_state = ley_lines::compute_ley_state(flag_state.ground_flags(), LEY_MAX_DISTANCE);
-        let scenery = scenery::spawn_scenery(field_rect, &camp_spawns);
-        let player_speed =
-            map::adjusted_travel_speed(map.width, map.height, MAP_TRAVEL_MINUTES, SPEED_MULTIPLIER);
+struct PauseScene {
+    selected: usize,
+}
+
+/// Full-screen map with camp names. Clicking sets the waypoint the dungeon
+/// HUD points to.
+struct WorldMapScene {
+    view: WorldMapView,
+    player: Vec2,
+    drag_last: Option<Vec2>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SettingsRow {
+    ZoomMin,
+    ZoomMax,
+    Volume,
+    Keybind(Action),
+    ResetKeybinds,
+}
+
+struct SettingsScene {
+    selected: usize,
+    capturing: bool,
+    /// Outcome of the last rebind, shown above the hint.
+    message: Option<String>,
+}
+
+impl DungeonScene {
+    fn new(world: World) -> Self {
+        let previous = world.position_snapshot();
         Self {
-            scene: Scene::Title,
-            player: Player {
-                pos: PLAYER_SPAWN_POS,
-                facing: player::Facing::Down,
-            },
-            class_index: class_choice_index(class_choice),
-            flag_state,
-            wind: flags::Wind::new(vec2(1.0, 0.0), 0.6),
-            scenery,
-            ley_lines: ley_state.lines,
-            pentagram_centers: ley_state.pentagram_centers,
+            world,
             pentagram_sparkles: Vec::new(),
             sparkle_spawn_accum: 0.0,
             sparkle_spawn_counter: 0,
-            flagic: 0,
-            flagic_accum: 0.0,
-            camp_notices,
-            camp_vertices,
-            hippies,
-            map,
-            camp_regions,
             camera: camera::CameraState::new(),
-            player_speed,
+            recorder: None,
+            playback: None,
+            autosave_timer: 0.0,
+            clock: FixedTimestep::new(SIM_STEP, SIM_MAX_STEPS_PER_FRAME),
+            previous,
+            pending_input: world::InputFrame::default(),
+            frame_steps: 0,
+            editor: None,
+            editor_cursor: Vec2::ZERO,
+            show_minimap: true,
+            click_to_move: false,
         }
     }
-}
 
-fn class_choice_index(choice: ClassChoice) -> usize {
-    match choice {
-        ClassChoice::Vexillomancer => 0,
-        ClassChoice::StressTest => 1,
+    fn from_replay(replay: Replay) -> Self {
+        let mut scene = Self::new(replay.new_world());
+        scene.playback = Some(ReplayPlayer::new(replay));
+        scene
     }
-}
 
-fn class_choice_from_index(index: usize) -> ClassChoice {
-    CLASS_OPTIONS[index.min(CLASS_OPTIONS.len() - 1)]
-}
-
-fn spawn_stress_test_hippies(camp_configs: &[camps::CampConfig]) -> Vec<npc::Hippie> {
-    if camp_configs.is_empty() || STRESS_TEST_HIPPIE_COUNT == 0 {
-        return Vec::new();
+    fn autosave(&mut self) {
+        if self.playback.is_some() {
+            return;
+        }
+        self.autosave_timer = 0.0;
+        if let Err(err) = save::save_world(&self.world, save::AUTOSAVE_PATH) {
+            eprintln!("autosave failed: {}", err);
+        }
     }
 
-    let camp_count = camp_configs.len();
-    let per_camp = STRESS_TEST_HIPPIE_COUNT / camp_count;
-    let remainder = STRESS_TEST_HIPPIE_COUNT % camp_count;
-    let mut hippies = Vec::with_capacity(STRESS_TEST_HIPPIE_COUNT);
-    let mut rng_state = 1u32;
+    fn start_recording(&mut self, path: &str, config: &WorldConfig) {
+        match ReplayRecorder::create(
+            path,
+            self.world.seed,
+            self.world.class_choice,
+            self.world.field_rect(),
+            config,
+        ) {
+            Ok(recorder) => self.recorder = Some(recorder),
+            Err(err) => eprintln!("could not start recording to {}: {}", path, err),
+        }
+    }
 
-    for (camp_index, camp) in camp_configs.iter().enumerate() {
-        let count = per_camp + usize::from(camp_index < remainder);
-        if count == 0 {
-            continue;
+    /// Pulls the next step from an active replay, or consumes the latched
+    /// live input once there is nothing left to play back.
+    fn next_sim_frame(&mut self) -> ReplayFrame {
+        if let Some(frame) = self.playback.as_mut().and_then(ReplayPlayer::next_frame) {
+            self.camera.zoom = frame.zoom;
+            self.camera.pan = frame.pan;
+            return frame;
         }
 
-        let mut spawns = Vec::with_capacity(count);
-        for _ in 0..count {
-            rng_state = rng_state.wrapping_mul(1664525).wrapping_add(1013904223);
-            let pos = npc::random_point_in_polygon(&camp.vertices, &mut rng_state);
-            spawns.push((pos, STRESS_TEST_FLAGS_PER_HIPPIE));
+        let input = self.pending_input;
+        self.pending_input.place_flag = false;
+        self.pending_input.pickup_flag = false;
+        self.pending_input.move_to = None;
+        self.pending_input.cast = None;
+        ReplayFrame {
+            dt: self.clock.step(),
+            input,
+            zoom: self.camera.zoom,
+            pan: self.camera.pan,
         }
+    }
+
+    /// Player position and center blended between the last two steps.
+    fn interpolated_player(&self) -> (Vec2, Vec2) {
+        let pos = self.previous.player_pos(&self.world, self.clock.alpha());
+        (
+            pos,
+            pos + (self.world.player.center() - self.world.player.pos),
+        )
+    }
+
+    /// The camp editor always has its name or notice field focused.
+    fn text_field_focused(&self) -> bool {
+        matches!(self.editor, Some(EditorMode::Camp(_)))
+    }
+
+    /// Cycles from play to the camp editor, the scenery editor and back.
+    fn cycle_editor(&mut self, app: &App) {
+        self.editor = match self.editor.take() {
+            None => {
+                // Drop keys typed before the editor opened.
+                while get_char_pressed().is_some() {}
+                Some(EditorMode::Camp(CampEditorMode {
+                    editor: CampEditor::new(),
+                    source: 0,
+                    message: None,
+                }))
+            }
+            Some(EditorMode::Camp(_)) => Some(EditorMode::Scenery(SceneryEditorMode {
+                editor: SceneryEditor::new(&app.scenery),
+                message: None,
+            })),
+            Some(EditorMode::Scenery(_)) => None,
+        };
+    }
 
-        hippies.extend(npc::spawn_hippies_with_flags(
-            &spawns,
-            camp_index,
-            &camp.vertices,
-        ));
+    fn update_editor(&mut self, app: &mut App) -> Transition<App> {
+        let actions = &app.actions;
+        if actions.is_pressed(Action::Back) {
+            self.editor = None;
+            return Transition::None;
+        }
+        handle_camera(&mut self.camera, actions, &app.settings, &app.tuning);
+
+        let map_size = vec2(app.map.width, app.map.height);
+        let (_, player_center) = self.interpolated_player();
+        let camera = build_camera(&self.camera, map_size, player_center);
+        self.editor_cursor = camera.screen_to_world(mouse_position().into());
+        match self.editor.as_mut() {
+            Some(EditorMode::Camp(mode)) => {
+                let radius = CAMP_EDITOR_HANDLE_RADIUS / self.camera.zoom;
+                update_camp_editor(mode, self.editor_cursor, radius, app);
+            }
+            Some(EditorMode::Scenery(mode)) => {
+                let radius = SCENERY_EDITOR_PICK_RADIUS / self.camera.zoom;
+                let changed = update_scenery_editor(mode, self.editor_cursor, radius, app);
+                if changed {
+                    self.world.set_layout_scenery(&mode.editor.items);
+                }
+            }
+            None => {}
+        }
+        Transition::None
     }
 
-    hippies
+    /// Blended positions indexed like `world.hippies`.
+    fn interpolated_hippies(&self, alpha: f32) -> Vec<Vec2> {
+        self.world
+            .hippies
+            .iter()
+            .enumerate()
+            .map(|(index, hippie)| self.previous.hippie_pos(index, hippie.pos, alpha))
+            .collect()
+    }
 }
 
 impl Assets {
-    fn load() -> Self {
-        let raster = assets::load_png_rgba("assets/png/signifiersmark.png");
+    fn load(root: &Path) -> Result<Self, AssetError> {
+        let raster = assets::load_png_rgba(root.join(SIGNIFIER_MARK_PATH))?;
 
         let signifier_mark = Texture2D::from_rgba8(raster.width, raster.height, &raster.pixels);
         signifier_mark.set_filter(FilterMode::Linear);
@@ -227,10 +293,23 @@ impl Assets {
         let (scaled_w, scaled_h) =
             assets::scale_to_fit(raster.width as f32, raster.height as f32, 260.0);
 
-        Self {
+        Ok(Self {
             signifier_mark,
             signifier_size: vec2(scaled_w, scaled_h),
-        }
+        })
+    }
+}
+
+/// Everything the game cannot start without. Collects every failure so the
+/// error screen can list them all at once.
+fn load_required_assets() -> Result<(PathBuf, Assets, map::TileMap), Vec<AssetError>> {
+    let root = assets::asset_root().map_err(|err| vec![err])?;
+    match (
+        Assets::load(&root),
+        map::TileMap::load_from_dir(root.join(MAP_TILE_DIR)),
+    ) {
+        (Ok(assets), Ok(map)) => Ok((root, assets, map)),
+        (assets, map) => Err([assets.err(), map.err()].into_iter().flatten().collect()),
     }
 }
 
@@ -245,224 +324,869 @@ fn window_conf() -> Conf {
 
 #[macroquad::main(window_conf)]
 async fn main() {
-    let mut game = Game::new();
-    let assets = Assets::load();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (actions, keybind_error) = ActionMap::load_or_create(input::KEYBINDS_PATH);
+    if let Some(err) = keybind_error {
+        eprintln!("using default keybinds: {}", err);
+    }
+    let (settings, settings_error) = Settings::load_or_default(settings::SETTINGS_PATH);
+    if let Some(err) = settings_error {
+        eprintln!("using default settings: {}", err);
+    }
+    let (tuning, tuning_error) = Tuning::load_or_default(tuning::TUNING_PATH);
+    if let Some(err) = tuning_error {
+        eprintln!("using default tuning: {}", err);
+    }
+    let (asset_root, assets, map) = match load_required_assets() {
+        Ok(loaded) => loaded,
+        Err(errors) => {
+            for err in &errors {
+                eprintln!("{}", err);
+            }
+            run_error_scene(AssetErrorScene::new(&errors), actions).await;
+            return;
+        }
+    };
+    let (camps, camp_message) = load_camps(&asset_root, map.field_rect());
+    let scenery =
+        match SceneryLayout::load(asset_root.join(scenery::SCENERY_PATH), map.field_rect()) {
+            Ok(layout) => layout,
+            Err(err) => {
+                eprintln!("using built-in scenery: {}", err);
+                SceneryLayout::builtin()
+            }
+        };
+    let mut app = App {
+        assets,
+        asset_root,
+        actions,
+        settings,
+        tuning,
+        tuning_watcher: TuningWatcher::new(tuning::TUNING_PATH),
+        camps,
+        scenery,
+        map,
+        seed: parse_seed_arg(&args).unwrap_or_else(time_seed),
+        record_path: arg_value(&args, "--record").map(str::to_string),
+        title_message: camp_message,
+        waypoint: None,
+    };
+
+    let root: Box<dyn Scene<App>> = match arg_value(&args, "--replay") {
+        Some(path) => match Replay::load(path) {
+            Ok(replay) => {
+                app.seed = replay.seed;
+                Box::new(DungeonScene::from_replay(replay))
+            }
+            Err(err) => {
+                eprintln!("failed to load replay {}: {}", path, err);
+                app.title_message = Some(format!("Could not load replay: {}", err));
+                Box::new(TitleScene)
+            }
+        },
+        None => Box::new(TitleScene),
+    };
+    let mut scenes = SceneStack::new(root);
 
     loop {
-        if is_key_pressed(KeyCode::Q) {
+        if !scenes.update(&mut app) {
             break;
         }
+        clear_background(BLACK);
+        scenes.draw(&mut app);
+
+        next_frame().await;
+    }
+}
+
+/// Loads the camp files, keeping the valid ones. Skipped files are logged and
+/// summed up in the returned title-screen message.
+fn load_camps(asset_root: &Path, field: Rect) -> (Vec<camps::CampConfig>, Option<String>) {
+    let loaded = match camps::load_camp_dir(asset_root.join(camps::CAMP_DIR), field) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            eprintln!("using built-in camps: {}", err);
+            let message = format!("Using built-in camps: {}", err);
+            return (camps::camp_configs(), Some(message));
+        }
+    };
+    for (path, err) in &loaded.skipped {
+        eprintln!("skipped camp file {}: {}", path.display(), err);
+    }
+    let Some((path, err)) = loaded.skipped.first() else {
+        return (loaded.camps, None);
+    };
+    let first = format!("{}: {}", path.display(), err);
+    if loaded.camps.is_empty() {
+        eprintln!("no valid camp files, using built-in camps");
+        return (
+            camps::camp_configs(),
+            Some(format!(
+                "No valid camp files, using built-in camps. {}",
+                first
+            )),
+        );
+    }
+    let message = format!("Skipped {} camp file(s). {}", loaded.skipped.len(), first);
+    (loaded.camps, Some(message))
+}
 
-        match game.scene {
-            Scene::Title => render_title(&mut game, &assets),
-            Scene::ClassSelect => render_class_select(&mut game),
-            Scene::Dungeon => render_dungeon(&mut game),
+/// Runs a scene that only needs the key bindings, for when the game itself
+/// could not start.
+async fn run_error_scene(scene: AssetErrorScene, mut actions: ActionMap) {
+    let mut scenes: SceneStack<ActionMap> = SceneStack::new(Box::new(scene));
+    loop {
+        if !scenes.update(&mut actions) {
+            break;
         }
+        clear_background(BLACK);
+        scenes.draw(&mut actions);
 
         next_frame().await;
     }
 }
 
-fn render_title(game: &mut Game, assets: &Assets) {
-    clear_background(BLACK);
+fn time_seed() -> u32 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() ^ d.as_secs() as u32)
+        .unwrap_or(1)
+}
 
-    let title_size = 64.0;
-    let subtitle_size = 28.0;
+fn has_autosave() -> bool {
+    std::path::Path::new(save::AUTOSAVE_PATH).exists()
+}
 
-    draw_centered("FLAGHACK2", 90.0, title_size, ACCENT);
+impl AssetErrorScene {
+    fn new(errors: &[AssetError]) -> Self {
+        Self {
+            lines: errors.iter().map(ToString::to_string).collect(),
+        }
+    }
+}
 
-    let rotation = (get_time() as f32) * 0.25;
-    let x = (screen_width() - assets.signifier_size.x) * 0.5;
-    let y = 150.0;
+impl Scene<ActionMap> for AssetErrorScene {
+    fn update(&mut self, actions: &mut ActionMap) -> Transition<ActionMap> {
+        if actions.is_pressed(Action::Quit)
+            || actions.is_pressed(Action::Back)
+            || actions.is_pressed(Action::Confirm)
+        {
+            return Transition::Quit;
+        }
+        Transition::None
+    }
 
-    draw_texture_ex(
-        &assets.signifier_mark,
-        x,
-        y,
-        WHITE,
-        DrawTextureParams {
-            dest_size: Some(assets.signifier_size),
-            rotation,
-            ..Default::default()
-        },
-    );
+    fn draw(&mut self, actions: &mut ActionMap) {
+        draw_centered("Could not load the game", 80.0, 40.0, RED);
+        let mut y = 130.0;
+        for line in &self.lines {
+            for wrapped in wrap_text(line, 20.0, screen_width() - 64.0) {
+                draw_text(&wrapped, 32.0, y, 20.0, WHITE);
+                y += 24.0;
+            }
+            y += 8.0;
+        }
+        let footer = format!(
+            "Run from the game directory or set {} to it. {} to quit",
+            assets::ASSET_ROOT_ENV,
+            actions.primary(Action::Quit)
+        );
+        draw_centered(&footer, screen_height() - 30.0, 20.0, ACCENT);
+    }
+}
+
+impl Scene<App> for TitleScene {
+    fn update(&mut self, app: &mut App) -> Transition<App> {
+        if app.actions.is_pressed(Action::Quit) {
+            return Transition::Quit;
+        }
+        if has_autosave() && app.actions.is_pressed(Action::Load) {
+            return match save::load_world(save::AUTOSAVE_PATH, app.world_config()) {
+                Ok(world) => {
+                    app.seed = world.seed;
+                    app.waypoint = None;
+                    app.title_message = None;
+                    Transition::Reset(Box::new(DungeonScene::new(world)))
+                }
+                Err(err) => {
+                    app.title_message = Some(format!("Could not load: {}", err));
+                    Transition::None
+                }
+            };
+        }
+        if app.actions.is_pressed(Action::Settings) {
+            return Transition::Push(Box::new(SettingsScene::new()));
+        }
+        if get_last_key_pressed().is_some() {
+            return Transition::Push(Box::new(ClassSelectScene { class_index: 0 }));
+        }
+        Transition::None
+    }
 
-    draw_centered("Press any key", 470.0, subtitle_size, ACCENT);
-    draw_centered("Q to quit", 505.0, 20.0, ACCENT);
+    fn draw(&mut self, app: &mut App) {
+        let title_size = 64.0;
+        let subtitle_size = 28.0;
+
+        draw_centered("FLAGHACK2", 90.0, title_size, ACCENT);
+
+        let rotation = (get_time() as f32) * 0.25;
+        let x = (screen_width() - app.assets.signifier_size.x) * 0.5;
+        let y = 150.0;
+
+        draw_texture_ex(
+            &app.assets.signifier_mark,
+            x,
+            y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(app.assets.signifier_size),
+                rotation,
+                ..Default::default()
+            },
+        );
 
-    if get_last_key_pressed().is_some() {
-        game.scene = Scene::ClassSelect;
+        if has_autosave() {
+            let load = format!("{} to load autosave", app.actions.primary(Action::Load));
+            draw_centered(&load, 440.0, 20.0, ACCENT);
+        }
+        draw_centered("Press any key", 470.0, subtitle_size, ACCENT);
+        let footer = format!(
+            "{} for settings, {} to quit",
+            app.actions.primary(Action::Settings),
+            app.actions.primary(Action::Quit)
+        );
+        draw_centered(&footer, 505.0, 20.0, ACCENT);
+        if let Some(message) = &app.title_message {
+            draw_centered(message, 530.0, 16.0, ACCENT);
+        }
     }
 }
 
-fn render_class_select(game: &mut Game) {
-    clear_background(BLACK);
+impl Scene<App> for ClassSelectScene {
+    fn update(&mut self, app: &mut App) -> Transition<App> {
+        let actions = &app.actions;
+        if actions.is_pressed(Action::Quit) {
+            return Transition::Quit;
+        }
+        if actions.is_pressed(Action::MoveDown) {
+            self.class_index = (self.class_index + 1) % CLASS_OPTIONS.len();
+        }
+        if actions.is_pressed(Action::MoveUp) {
+            self.class_index = (self.class_index + CLASS_OPTIONS.len() - 1) % CLASS_OPTIONS.len();
+        }
+        if actions.is_pressed(Action::Back) {
+            return Transition::Pop;
+        }
+        if actions.is_pressed(Action::Confirm) {
+            let choice: ClassChoice = world::class_choice_from_index(self.class_index);
+            let config = app.world_config();
+            let world =
+                World::new_with_config(choice, app.map.field_rect(), app.seed, config.clone());
+            let mut dungeon = DungeonScene::new(world);
+            app.waypoint = None;
+            if let Some(path) = &app.record_path {
+                dungeon.start_recording(path, &config);
+            }
+            return Transition::Reset(Box::new(dungeon));
+        }
+        Transition::None
+    }
 
-    draw_centered("Choose Your Class", 120.0, 44.0, ACCENT);
+    fn draw(&mut self, app: &mut App) {
+        let actions = &app.actions;
+        draw_centered("Choose Your Class", 120.0, 44.0, ACCENT);
 
-    if is_key_pressed(KeyCode::Down) || is_key_pressed(KeyCode::S) {
-        game.class_index = (game.class_index + 1) % CLASS_OPTIONS.len();
-    }
-    if is_key_pressed(KeyCode::Up) || is_key_pressed(KeyCode::W) {
-        game.class_index = (game.class_index + CLASS_OPTIONS.len() - 1) % CLASS_OPTIONS.len();
+        for (index, choice) in CLASS_OPTIONS.iter().enumerate() {
+            let label = choice.name();
+            let line = if index == self.class_index {
+                format!("> {} <", label)
+            } else {
+                label.to_string()
+            };
+            draw_centered(&line, 220.0 + index as f32 * 36.0, 32.0, ACCENT);
+        }
+
+        let choose = format!(
+            "{}/{} to choose",
+            actions.primary(Action::MoveUp),
+            actions.primary(Action::MoveDown)
+        );
+        draw_centered(&choose, 300.0, 18.0, ACCENT);
+        let begin = format!("{} to begin", actions.primary(Action::Confirm));
+        draw_centered(&begin, 325.0, 24.0, ACCENT);
+        let back = format!("{} to go back", actions.primary(Action::Back));
+        draw_centered(&back, 357.0, 20.0, ACCENT);
+        let quit = format!("{} to quit", actions.primary(Action::Quit));
+        draw_centered(&quit, 385.0, 20.0, ACCENT);
     }
+}
 
-    for (index, choice) in CLASS_OPTIONS.iter().enumerate() {
-        let label = choice.name();
-        let line = if index == game.class_index {
-            format!("> {} <", label)
-        } else {
-            label.to_string()
-        };
-        draw_centered(&line, 220.0 + index as f32 * 36.0, 32.0, ACCENT);
+impl Scene<App> for DungeonScene {
+    fn update(&mut self, app: &mut App) -> Transition<App> {
+        let actions = &app.actions;
+        // Keys typed into a camp editor field are text, not hotkeys.
+        if !self.text_field_focused() {
+            if actions.is_pressed(Action::ToggleMinimap) {
+                self.show_minimap = !self.show_minimap;
+            }
+            if actions.is_pressed(Action::ClickToMove) {
+                self.click_to_move = !self.click_to_move;
+            }
+        }
+        if actions.is_pressed(Action::ToggleEditor) && self.playback.is_none() {
+            self.cycle_editor(app);
+            return Transition::None;
+        }
+        if self.editor.is_some() {
+            return self.update_editor(app);
+        }
+
+        if actions.is_pressed(Action::Quit) {
+            return Transition::Quit;
+        }
+        if actions.is_pressed(Action::Back) {
+            self.autosave();
+            return Transition::Push(Box::new(PauseScene { selected: 0 }));
+        }
+        if actions.is_pressed(Action::WorldMap) {
+            return Transition::Push(Box::new(WorldMapScene {
+                view: WorldMapView::new(app.map.field_rect()),
+                player: self.world.player.center(),
+                drag_last: None,
+            }));
+        }
+
+        // Replays carry the tuning they were recorded with, so neither
+        // playback nor a recording in progress picks up edits to the file.
+        if self.playback.is_none() {
+            match app.tuning_watcher.poll(get_frame_time()) {
+                Some(Ok(_)) if self.recorder.is_some() => {
+                    eprintln!("ignoring tuning change while recording a replay");
+                }
+                Some(Ok(tuning)) => {
+                    self.world.set_tuning(tuning.clone());
+                    app.tuning = tuning;
+                }
+                Some(Err(err)) => eprintln!("keeping current tuning: {}", err),
+                None => {}
+            }
+        }
+
+        let actions = &app.actions;
+        if self.playback.is_none() {
+            handle_camera(&mut self.camera, actions, &app.settings, &app.tuning);
+            let mut polled = poll_input_frame(actions);
+            if self.click_to_move && polled.place_flag {
+                let map_size = vec2(app.map.width, app.map.height);
+                let (_, player_center) = self.interpolated_player();
+                let camera = build_camera(&self.camera, map_size, player_center);
+                polled.place_flag = false;
+                polled.move_to = Some(camera.screen_to_world(mouse_position().into()));
+            }
+            latch_input_frame(&mut self.pending_input, polled);
+        }
+
+        self.frame_steps = self.clock.advance(get_frame_time());
+        for _ in 0..self.frame_steps {
+            let frame = self.next_sim_frame();
+            if let Some(recorder) = self.recorder.as_mut() {
+                if let Err(err) = recorder.record(&frame) {
+                    eprintln!("stopping replay recording: {}", err);
+                    self.recorder = None;
+                }
+            }
+            self.previous = self.world.position_snapshot();
+            self.world.step(frame.dt, frame.input);
+            self.autosave_timer += frame.dt;
+        }
+
+        if self.autosave_timer >= AUTOSAVE_INTERVAL || actions.is_pressed(Action::Save) {
+            self.autosave();
+        }
+        Transition::None
     }
 
-    draw_centered("Up/Down to choose", 300.0, 18.0, ACCENT);
-    draw_centered("Enter to begin", 325.0, 24.0, ACCENT);
-    draw_centered("Esc to go back", 357.0, 20.0, ACCENT);
-    draw_centered("Q to quit", 385.0, 20.0, ACCENT);
+    fn draw(&mut self, app: &mut App) {
+        let actions = &app.actions;
+        let time = get_time() as f32;
+        // Steps only advance while this scene is on top, so a paused run
+        // spawns no sparkles.
+        let sim_dt = std::mem::take(&mut self.frame_steps) as f32 * self.clock.step();
+        let alpha = self.clock.alpha();
+        let (player_pos, player_center) = self.interpolated_player();
+
+        let map_size = vec2(app.map.width, app.map.height);
+        let camera = build_camera(&self.camera, map_size, player_center);
+        let view_rect = camera_view_rect(&self.camera, camera.target);
+        set_camera(&camera);
+
+        app.map.draw(view_rect);
+        for camp in &self.world.camp_regions {
+            camp.draw();
+        }
+        scenery::draw_scenery(&self.world.scenery, time);
+        npc::draw_hippies(&self.world.hippies, &self.interpolated_hippies(alpha));
+        draw_ley_lines(&self.world.ley_lines, time);
+        for flag in self.world.flag_state.ground_flags() {
+            draw_flag(flag, time, self.world.wind);
+        }
 
-    if is_key_pressed(KeyCode::Escape) {
-        game.scene = Scene::Title;
-        return;
+        if !self.world.player_path.is_empty() {
+            let feet = player_pos + (self.world.player.feet() - self.world.player.pos);
+            draw_move_path(feet, &self.world.player_path, self.camera.zoom);
+        }
+        player::draw_player(player_pos, ACCENT, self.world.player.facing);
+        let in_pentagram = self.world.in_pentagram();
+        update_pentagram_sparkles(
+            &mut self.pentagram_sparkles,
+            &mut self.sparkle_spawn_accum,
+            &mut self.sparkle_spawn_counter,
+            player_center,
+            in_pentagram,
+            time,
+            sim_dt,
+            view_rect,
+        );
+        if let Some(waypoint) = app.waypoint {
+            draw_waypoint_marker(waypoint, 12.0 / self.camera.zoom);
+        }
+        match &self.editor {
+            Some(EditorMode::Camp(mode)) => draw_camp_editor(&mode.editor, self.camera.zoom),
+            Some(EditorMode::Scenery(mode)) => draw_scenery_editor(&mode.editor, self.camera.zoom),
+            None => {}
+        }
+
+        set_default_camera();
+        draw_camp_notices(&self.world.camp_notices);
+        draw_centered("FLAGHACK2", 60.0, 64.0, ACCENT);
+        let click_state = if self.click_to_move { "on" } else { "off" };
+        let move_help = format!(
+            "{}{}{}{} to move, {} for click to move ({})",
+            actions.primary(Action::MoveUp),
+            actions.primary(Action::MoveLeft),
+            actions.primary(Action::MoveDown),
+            actions.primary(Action::MoveRight),
+            actions.primary(Action::ClickToMove),
+            click_state
+        );
+        draw_centered(&move_help, 110.0, 20.0, ACCENT);
+        let back = format!(
+            "{} to pause, {} for the map",
+            actions.primary(Action::Back),
+            actions.primary(Action::WorldMap)
+        );
+        draw_centered(&back, 135.0, 20.0, ACCENT);
+        let save_quit = format!(
+            "{} to save, {} to quit",
+            actions.primary(Action::Save),
+            actions.primary(Action::Quit)
+        );
+        draw_centered(&save_quit, 160.0, 20.0, ACCENT);
+        draw_centered(&format!("Seed {}", self.world.seed), 185.0, 16.0, ACCENT);
+        if let Some(playback) = &self.playback {
+            let (current, total) = playback.progress();
+            let status = if playback.is_finished() {
+                "Replay finished".to_string()
+            } else {
+                format!("Replay {}/{}", current, total)
+            };
+            draw_centered(&status, 205.0, 16.0, ACCENT);
+        } else if self.recorder.is_some() {
+            draw_centered("Recording", 205.0, 16.0, ACCENT);
+        }
+        hud::draw_hud(
+            self.world.flag_state.player_inventory(),
+            self.world.player_speed,
+            self.world.player.pos,
+            self.world.current_total_flags(),
+            self.world.flagic,
+            &self.world.pentagram_scores_here(),
+        );
+        if self.world.class_choice == ClassChoice::Vexillomancer {
+            let keys: Vec<String> = spells::ALL_SPELLS
+                .iter()
+                .map(|spell| actions.primary(Action::cast(*spell)))
+                .collect();
+            hud::draw_spell_bar(
+                &self.world.spellbook,
+                self.world.flagic,
+                &self.world.tuning.spells,
+                &keys,
+            );
+        }
+        if let Some(waypoint) = app.waypoint {
+            draw_waypoint_arrow(waypoint, player_center, view_rect);
+        }
+        if self.show_minimap {
+            let minimap = Minimap::new(app.map.field_rect(), vec2(screen_width(), screen_height()));
+            let background = app.map.overview();
+            minimap::draw_minimap(&minimap, background, &self.world, player_center, view_rect);
+        }
+        match &self.editor {
+            Some(EditorMode::Camp(mode)) => {
+                draw_camp_editor_panel(mode, self.editor_cursor, actions)
+            }
+            Some(EditorMode::Scenery(mode)) => {
+                draw_scenery_editor_panel(mode, self.editor_cursor, actions)
+            }
+            None => {}
+        }
     }
 
-    if is_key_pressed(KeyCode::Enter) {
-        let choice = class_choice_from_index(game.class_index);
-        let mut new_game = Game::new_with_class(choice);
-        new_game.scene = Scene::Dungeon;
-        new_game.class_index = game.class_index;
-        *game = new_game;
+    fn on_exit(&mut self, _app: &mut App) {
+        self.autosave();
     }
 }
 
-fn render_dungeon(game: &mut Game) {
-    clear_background(BLACK);
+impl Scene<App> for WorldMapScene {
+    fn update(&mut self, app: &mut App) -> Transition<App> {
+        let actions = &app.actions;
+        if actions.is_pressed(Action::Quit) {
+            return Transition::Quit;
+        }
+        if actions.is_pressed(Action::Back) || actions.is_pressed(Action::WorldMap) {
+            return Transition::Pop;
+        }
 
-    handle_camera(game);
-    handle_movement(game);
+        let screen = vec2(screen_width(), screen_height());
+        let mouse: Vec2 = mouse_position().into();
+        let (_, wheel_y) = mouse_wheel();
+        if wheel_y != 0.0 {
+            let factor = (1.0 + wheel_y * app.tuning.camera.zoom_step).max(0.1);
+            self.view.zoom_at(factor, mouse, screen);
+        }
+        if actions.is_down(Action::PanCamera) {
+            if let Some(last) = self.drag_last {
+                self.view.pan(mouse - last, screen);
+            }
+            self.drag_last = Some(mouse);
+        } else {
+            self.drag_last = None;
+        }
+        let keys = [
+            (Action::MoveUp, vec2(0.0, 1.0)),
+            (Action::MoveDown, vec2(0.0, -1.0)),
+            (Action::MoveLeft, vec2(1.0, 0.0)),
+            (Action::MoveRight, vec2(-1.0, 0.0)),
+        ];
+        let pan: Vec2 = keys
+            .iter()
+            .filter(|(action, _)| actions.is_down(*action))
+            .map(|(_, direction)| *direction)
+            .sum();
+        self.view
+            .pan(pan * WORLD_MAP_PAN_SPEED * get_frame_time(), screen);
+
+        if actions.is_pressed(Action::PlaceFlag) {
+            let field = app.map.field_rect();
+            let pos = self.view.to_world(mouse, screen);
+            app.waypoint = Some(pos.clamp(field.point(), field.point() + field.size()));
+        }
+        if actions.is_pressed(Action::PickupFlag) {
+            app.waypoint = None;
+        }
+        Transition::None
+    }
 
-    handle_flag_interactions(game);
+    fn draw(&mut self, app: &mut App) {
+        let screen = vec2(screen_width(), screen_height());
+        let scale = self.view.scale(screen);
+        let mut camera = Camera2D::from_display_rect(Rect::new(0.0, 0.0, screen.x, screen.y));
+        camera.target = self.view.center;
+        camera.zoom = camera::flip_zoom_y(camera.zoom * scale);
+        set_camera(&camera);
+        app.map.draw(self.view.view_rect(screen));
+        for camp in &app.camps {
+            let mut fill = camp.color;
+            fill.a = 0.35;
+            for tri in flaghack2::geom::triangulate_polygon(&camp.vertices) {
+                draw_triangle(tri[0], tri[1], tri[2], fill);
+            }
+        }
+        if let Some(waypoint) = app.waypoint {
+            draw_waypoint_marker(waypoint, 10.0 / scale);
+        }
+        set_default_camera();
+
+        for camp in &app.camps {
+            if let Some(label) = world_map::label_position(&camp.vertices) {
+                let pos = self.view.to_screen(label, screen);
+                let metrics = measure_text(&camp.name, None, 20, 1.0);
+                let x = pos.x - metrics.width * 0.5;
+                draw_text(&camp.name, x + 1.0, pos.y + 1.0, 20.0, BLACK);
+                draw_text(&camp.name, x, pos.y, 20.0, WHITE);
+            }
+        }
+        let player = self.view.to_screen(self.player, screen);
+        draw_circle(player.x, player.y, 6.0, BLACK);
+        draw_circle(player.x, player.y, 4.0, ACCENT);
+
+        let actions = &app.actions;
+        draw_centered("MAP", 40.0, 36.0, ACCENT);
+        let help = format!(
+            "{} set waypoint, {} clear, wheel zoom, {} or {} close",
+            actions.primary(Action::PlaceFlag),
+            actions.primary(Action::PickupFlag),
+            actions.primary(Action::WorldMap),
+            actions.primary(Action::Back)
+        );
+        draw_rectangle(
+            0.0,
+            screen.y - 36.0,
+            screen.x,
+            36.0,
+            Color::new(0.0, 0.0, 0.0, 0.7),
+        );
+        draw_centered(&help, screen.y - 12.0, 20.0, ACCENT);
+    }
+}
 
-    let time = get_time() as f32;
-    let dt = get_frame_time();
-    let player_center =
-        game.player.pos + vec2(player::PLAYER_WIDTH * 0.5, player::PLAYER_HEIGHT * 0.5);
-    update_camp_notices(game, player_center, dt);
-    let hippies_picked = npc::update_hippies(
-        &mut game.hippies,
-        dt,
-        &game.camp_vertices,
-        &mut game.flag_state,
-        player_center,
-        game.player_speed,
-    );
-    if hippies_picked {
-        recompute_ley_state(game);
-    }
-
-    let camera = build_camera(game);
-    let view_rect = camera_view_rect(game, camera.target);
-    set_camera(&camera);
-
-    game.map.draw(view_rect);
-    for camp in &game.camp_regions {
-        camp.draw();
-    }
-    scenery::draw_scenery(&game.scenery, time);
-    npc::draw_hippies(&game.hippies);
-    draw_ley_lines(&game.ley_lines, time);
-    for flag in game.flag_state.ground_flags() {
-        draw_flag(flag, time, game.wind);
-    }
-
-    player::draw_player(game.player.pos, ACCENT, game.player.facing);
-    let in_pentagram = player_in_pentagram(player_center, &game.pentagram_centers);
-    update_flagic(&mut game.flagic, &mut game.flagic_accum, in_pentagram, dt);
-    update_pentagram_sparkles(
-        &mut game.pentagram_sparkles,
-        &mut game.sparkle_spawn_accum,
-        &mut game.sparkle_spawn_counter,
-        player_center,
-        in_pentagram,
-        time,
-        dt,
-        view_rect,
-    );
+impl Scene<App> for PauseScene {
+    fn update(&mut self, app: &mut App) -> Transition<App> {
+        let actions = &app.actions;
+        if actions.is_pressed(Action::Quit) {
+            return Transition::Quit;
+        }
+        if actions.is_pressed(Action::Back) {
+            return Transition::Pop;
+        }
+        self.selected = menu_step(actions, self.selected, PAUSE_OPTIONS.len());
+        if actions.is_pressed(Action::Confirm) {
+            return match self.selected {
+                0 => Transition::Pop,
+                1 => Transition::Push(Box::new(SettingsScene::new())),
+                _ => Transition::Reset(Box::new(TitleScene)),
+            };
+        }
+        Transition::None
+    }
 
-    set_default_camera();
-    draw_camp_notices(game);
-    draw_centered("FLAGHACK2", 60.0, 64.0, ACCENT);
-    draw_centered("WASD to move", 110.0, 20.0, ACCENT);
-    draw_centered("Esc to class select", 135.0, 20.0, ACCENT);
-    draw_centered("Q to quit", 160.0, 20.0, ACCENT);
-    hud::draw_hud(
-        game.flag_state.player_inventory(),
-        game.player_speed,
-        game.player.pos,
-        current_total_flags(game),
-        game.flagic,
-    );
+    fn draw(&mut self, app: &mut App) {
+        draw_rectangle(
+            0.0,
+            0.0,
+            screen_width(),
+            screen_height(),
+            Color::new(0.0, 0.0, 0.0, 0.6),
+        );
+        draw_centered("PAUSED", 160.0, 56.0, ACCENT);
+        draw_centered("Progress saved", 195.0, 18.0, ACCENT);
+        draw_menu(&PAUSE_OPTIONS, self.selected, 250.0);
+        let back = format!("{} to resume", app.actions.primary(Action::Back));
+        draw_centered(&back, 380.0, 18.0, ACCENT);
+    }
 
-    game.flag_state
-        .debug_assert_invariant(total_hippie_flags(&game.hippies));
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
 
-    if is_key_pressed(KeyCode::Escape) {
-        game.scene = Scene::ClassSelect;
+impl SettingsScene {
+    fn new() -> Self {
+        Self {
+            selected: 0,
+            capturing: false,
+            message: None,
+        }
     }
 }
 
-fn handle_movement(game: &mut Game) {
-    let input = movement::InputState {
-        up: is_key_down(KeyCode::W),
-        down: is_key_down(KeyCode::S),
-        left: is_key_down(KeyCode::A),
-        right: is_key_down(KeyCode::D),
-    };
+fn settings_rows() -> Vec<SettingsRow> {
+    let mut rows = vec![
+        SettingsRow::ZoomMin,
+        SettingsRow::ZoomMax,
+        SettingsRow::Volume,
+    ];
+    rows.extend(input::ALL_ACTIONS.iter().copied().map(SettingsRow::Keybind));
+    rows.push(SettingsRow::ResetKeybinds);
+    rows
+}
 
-    let direction = movement::input_direction(input);
-    if direction.length() > 0.0 {
-        game.player.facing = player::facing_from_direction(direction);
+/// Applies a left/right adjustment to a numeric settings row.
+fn adjust_setting(settings: &mut Settings, row: SettingsRow, direction: f32) {
+    match row {
+        SettingsRow::ZoomMin => {
+            settings.zoom_min =
+                (settings.zoom_min + direction * SETTINGS_ZOOM_STEP).min(settings.zoom_max);
+        }
+        SettingsRow::ZoomMax => {
+            settings.zoom_max =
+                (settings.zoom_max + direction * SETTINGS_ZOOM_STEP).max(settings.zoom_min);
+        }
+        SettingsRow::Volume => settings.volume += direction * SETTINGS_VOLUME_STEP,
+        SettingsRow::Keybind(_) | SettingsRow::ResetKeybinds => return,
     }
+    *settings = settings.clone().sanitized();
+}
 
-    let delta = movement::movement_delta(input, game.player_speed, get_frame_time());
-    game.player.pos += delta;
-
-    let max_x = (game.map.width - player::PLAYER_WIDTH).max(0.0);
-    let max_y = (game.map.height - player::PLAYER_HEIGHT).max(0.0);
-    game.player.pos.x = game.player.pos.x.clamp(0.0, max_x);
-    game.player.pos.y = game.player.pos.y.clamp(0.0, max_y);
+fn settings_row_label(row: SettingsRow, app: &App) -> String {
+    match row {
+        SettingsRow::ZoomMin => format!("Zoom min  {:.2}", app.settings.zoom_min),
+        SettingsRow::ZoomMax => format!("Zoom max  {:.2}", app.settings.zoom_max),
+        SettingsRow::Volume => format!("Volume  {:.0}%", app.settings.volume * 100.0),
+        SettingsRow::Keybind(action) => {
+            format!("{}  {}", action.name(), app.actions.describe(action))
+        }
+        SettingsRow::ResetKeybinds => "Reset keybinds".to_string(),
+    }
 }
 
-fn handle_flag_interactions(game: &mut Game) {
-    let field = game.map.field_rect();
+impl Scene<App> for SettingsScene {
+    fn update(&mut self, app: &mut App) -> Transition<App> {
+        let rows = settings_rows();
+        let row = rows[self.selected];
+
+        if self.capturing {
+            if app.actions.is_pressed(Action::Back) {
+                self.capturing = false;
+                self.message = None;
+            } else if let (SettingsRow::Keybind(action), Some(binding)) =
+                (row, input::pressed_binding())
+            {
+                let change = app.actions.toggle_binding(action, binding);
+                self.message = rebind_message(action, binding, &change);
+                self.capturing = false;
+            }
+            return Transition::None;
+        }
 
-    if is_mouse_button_pressed(MouseButton::Left) {
-        let placed =
-            game.flag_state
-                .try_place_from_player(game.player.pos, FLAG_PLACE_OFFSET, field);
-        if placed {
-            recompute_ley_state(game);
-            return;
+        let actions = &app.actions;
+        if actions.is_pressed(Action::Back) {
+            return Transition::Pop;
+        }
+        self.selected = menu_step(actions, self.selected, rows.len());
+        if actions.is_pressed(Action::MoveLeft) {
+            adjust_setting(&mut app.settings, row, -1.0);
+        }
+        if actions.is_pressed(Action::MoveRight) {
+            adjust_setting(&mut app.settings, row, 1.0);
+        }
+        if actions.is_pressed(Action::Confirm) {
+            match row {
+                SettingsRow::Keybind(_) => {
+                    self.capturing = true;
+                    self.message = None;
+                }
+                SettingsRow::ResetKeybinds => app.actions = ActionMap::default(),
+                _ => {}
+            }
         }
+        Transition::None
     }
 
-    if is_mouse_button_pressed(MouseButton::Right) {
-        if game
-            .flag_state
-            .try_pickup_to_player(game.player.pos, FLAG_INTERACT_RADIUS)
-        {
-            recompute_ley_state(game);
+    fn draw(&mut self, app: &mut App) {
+        clear_background(BLACK);
+        draw_centered("Settings", 50.0, 40.0, ACCENT);
+
+        let rows = settings_rows();
+        let visible = SETTINGS_VISIBLE_ROWS.min(rows.len());
+        let first = self
+            .selected
+            .saturating_sub(visible / 2)
+            .min(rows.len() - visible);
+        let labels: Vec<String> = rows[first..first + visible]
+            .iter()
+            .map(|row| settings_row_label(*row, app))
+            .collect();
+        let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+        draw_menu(&labels, self.selected - first, 100.0);
+
+        if let Some(message) = &self.message {
+            draw_centered(message, screen_height() - 55.0, 18.0, ACCENT);
         }
+        let hint = if self.capturing {
+            format!(
+                "Press a key or mouse button to add or remove it, {} to cancel",
+                app.actions.primary(Action::Back)
+            )
+        } else {
+            format!(
+                "{} adjust, {} rebind, {} save and go back",
+                [
+                    app.actions.primary(Action::MoveLeft),
+                    app.actions.primary(Action::MoveRight)
+                ]
+                .join("/"),
+                app.actions.primary(Action::Confirm),
+                app.actions.primary(Action::Back)
+            )
+        };
+        draw_centered(&hint, screen_height() - 30.0, 18.0, ACCENT);
+    }
 
-        let player_center =
-            game.player.pos + vec2(player::PLAYER_WIDTH * 0.5, player::PLAYER_HEIGHT * 0.5);
-        npc::try_steal_flag(
-            &mut game.hippies,
-            player_center,
-            HIPPIE_STEAL_RADIUS,
-            &mut game.flag_state,
-        );
+    fn on_exit(&mut self, app: &mut App) {
+        if let Err(err) = app.settings.save(settings::SETTINGS_PATH) {
+            eprintln!("could not save settings: {}", err);
+        }
+        if let Err(err) = app.actions.save(input::KEYBINDS_PATH) {
+            eprintln!("could not save keybinds: {}", err);
+        }
+    }
+}
+
+fn rebind_message(action: Action, binding: Binding, change: &BindingChange) -> Option<String> {
+    match change {
+        BindingChange::Added { shared_with } if !shared_with.is_empty() => {
+            let names: Vec<&str> = shared_with.iter().map(|other| other.name()).collect();
+            Some(format!(
+                "{} is also bound to {}",
+                binding.name(),
+                names.join(", ")
+            ))
+        }
+        BindingChange::Added { .. } | BindingChange::Removed => None,
+        BindingChange::KeptLast => Some(format!(
+            "{} needs at least one binding, kept {}",
+            action.name(),
+            binding.name()
+        )),
+    }
+}
+
+fn menu_step(actions: &ActionMap, selected: usize, len: usize) -> usize {
+    if actions.is_pressed(Action::MoveDown) {
+        return (selected + 1) % len;
+    }
+    if actions.is_pressed(Action::MoveUp) {
+        return (selected + len - 1) % len;
+    }
+    selected
+}
+
+fn draw_menu(options: &[&str], selected: usize, top: f32) {
+    for (index, option) in options.iter().enumerate() {
+        let line = if index == selected {
+            format!("> {} <", option)
+        } else {
+            option.to_string()
+        };
+        draw_centered(&line, top + index as f32 * 30.0, 24.0, ACCENT);
+    }
+}
+
+/// Held movement keys follow the latest poll, while click actions stay
+/// latched until a simulation step consumes them.
+fn latch_input_frame(pending: &mut world::InputFrame, polled: world::InputFrame) {
+    pending.movement = polled.movement;
+    pending.place_flag |= polled.place_flag;
+    pending.pickup_flag |= polled.pickup_flag;
+    if polled.move_to.is_some() {
+        pending.move_to = polled.move_to;
+    }
+    if polled.cast.is_some() {
+        pending.cast = polled.cast;
+    }
+}
+
+fn poll_input_frame(actions: &ActionMap) -> world::InputFrame {
+    world::InputFrame {
+        movement: actions.movement(),
+        place_flag: actions.is_pressed(Action::PlaceFlag),
+        pickup_flag: actions.is_pressed(Action::PickupFlag),
+        move_to: None,
+        cast: spells::ALL_SPELLS
+            .into_iter()
+            .find(|spell| actions.is_pressed(Action::cast(*spell))),
     }
 }
 
@@ -490,7 +1214,7 @@ fn draw_flag(flag: &flags::Flag, time: f32, wind: flags::Wind) {
 
 fn draw_ley_lines(lines: &[ley_lines::LeyLine], time: f32) {
     let cycle = 0.5 + 0.5 * (time * LEY_COLOR_CYCLE_SPEED).sin();
-    let pent_cycle = 0.5 + 0.5 * (time * PENTAGRAM_COLOR_CYCLE_SPEED).sin();
+    let sigil_cycle = 0.5 + 0.5 * (time * PENTAGRAM_COLOR_CYCLE_SPEED).sin();
     for line in lines {
         let sparkle_phase = (line.a.x + line.b.y) * LEY_SPARKLE_SPATIAL;
         let sparkle = 0.5 + 0.5 * (time * LEY_SPARKLE_SPEED + sparkle_phase).sin();
@@ -507,11 +1231,11 @@ fn draw_ley_lines(lines: &[ley_lines::LeyLine], time: f32) {
             bright_base,
             bright_scale,
             highlight,
-        ) = match line.kind {
-            ley_lines::LeyLineKind::Pentagram => (
-                PENTAGRAM_COLOR_RED,
-                PENTAGRAM_COLOR_ORANGE,
-                pent_cycle,
+        ) = match line.kind.sigil() {
+            Some(sigil) => (
+                sigil.colors().0,
+                sigil.colors().1,
+                sigil_cycle,
                 0.6,
                 PENTAGRAM_MIN_ALPHA,
                 1.8,
@@ -522,7 +1246,7 @@ fn draw_ley_lines(lines: &[ley_lines::LeyLine], time: f32) {
                 0.7,
                 Color::new(1.0, 0.9, 0.65, 1.0),
             ),
-            ley_lines::LeyLineKind::Normal => (
+            None => (
                 LEY_COLOR_PURPLE,
                 LEY_COLOR_PINK,
                 cycle,
@@ -558,68 +1282,8 @@ fn draw_ley_lines(lines: &[ley_lines::LeyLine], time: f32) {
     }
 }
 
-fn player_in_pentagram(pos: Vec2, centers: &[Vec2]) -> bool {
-    centers
-        .iter()
-        .any(|center| center.distance(pos) <= PENTAGRAM_CENTER_RADIUS)
-}
-
-fn update_flagic(flagic: &mut u8, accum: &mut f32, in_pentagram: bool, dt: f32) {
-    if !in_pentagram || dt <= 0.0 {
-        return;
-    }
-
-    if *flagic >= FLAGIC_MAX {
-        *flagic = FLAGIC_MAX;
-        *accum = 0.0;
-        return;
-    }
-
-    *accum += dt * FLAGIC_GAIN_RATE;
-    let inc = accum.floor() as u32;
-    if inc == 0 {
-        return;
-    }
-    let next = (*flagic as u32 + inc).min(FLAGIC_MAX as u32);
-    *flagic = next as u8;
-    *accum -= inc as f32;
-    if *flagic >= FLAGIC_MAX {
-        *flagic = FLAGIC_MAX;
-        *accum = 0.0;
-    }
-}
-
-fn current_total_flags(game: &Game) -> u32 {
-    game.flag_state
-        .current_total(total_hippie_flags(&game.hippies))
-}
-
-fn total_hippie_flags(hippies: &[npc::Hippie]) -> u32 {
-    hippies.iter().map(|h| h.carried_flags as u32).sum()
-}
-
-fn recompute_ley_state(game: &mut Game) {
-    let state = ley_lines::compute_ley_state(game.flag_state.ground_flags(), LEY_MAX_DISTANCE);
-    game.ley_lines = state.lines;
-    game.pentagram_centers = state.pentagram_centers;
-}
-
-fn update_camp_notices(game: &mut Game, player_center: Vec2, dt: f32) {
-    let mut now_inside = Vec::with_capacity(game.camp_notices.len());
-    for notice in &game.camp_notices {
-        let in_camp = game
-            .camp_regions
-            .iter()
-            .filter(|region| region.name == notice.camp_name)
-            .any(|region| region.contains_point(player_center));
-        now_inside.push(in_camp);
-    }
-
-    update_camp_notice_states(&mut game.camp_notices, &now_inside, dt);
-}
-
-fn draw_camp_notices(game: &Game) {
-    for notice in &game.camp_notices {
+fn draw_camp_notices(notices: &[world::CampNotice]) {
+    for notice in notices {
         if notice.timer < 0.0 {
             continue;
         }
@@ -630,7 +1294,7 @@ fn draw_camp_notices(game: &Game) {
         let mut color = ACCENT;
         color.a = alpha;
         draw_centered(
-            notice.text,
+            &notice.text,
             screen_height() * 0.5,
             REGION_NOTICE_SIZE,
             color,
@@ -638,42 +1302,8 @@ fn draw_camp_notices(game: &Game) {
     }
 }
 
-fn update_camp_notice_states(notices: &mut [CampNotice], now_inside: &[bool], dt: f32) {
-    debug_assert_eq!(notices.len(), now_inside.len());
-
-    let mut entered_index = None;
-    for (i, notice) in notices.iter().enumerate() {
-        if now_inside[i] && !notice.inside {
-            entered_index = Some(i);
-            break;
-        }
-    }
-
-    if let Some(index) = entered_index {
-        for (i, notice) in notices.iter_mut().enumerate() {
-            notice.inside = now_inside[i];
-            if i == index {
-                notice.timer = 0.0;
-            } else {
-                notice.timer = -1.0;
-            }
-        }
-        return;
-    }
-
-    for (i, notice) in notices.iter_mut().enumerate() {
-        notice.inside = now_inside[i];
-        if notice.timer >= 0.0 {
-            notice.timer += dt;
-            if notice.timer > REGION_NOTICE_DURATION {
-                notice.timer = -1.0;
-            }
-        }
-    }
-}
-
 fn camp_notice_alpha(elapsed: f32) -> f32 {
-    if elapsed < 0.0 || elapsed > REGION_NOTICE_DURATION {
+    if !(0.0..=REGION_NOTICE_DURATION).contains(&elapsed) {
         return 0.0;
     }
 
@@ -689,6 +1319,7 @@ fn camp_notice_alpha(elapsed: f32) -> f32 {
     1.0
 }
 
+#[allow(clippy::too_many_arguments)]
 fn update_pentagram_sparkles(
     sparkles: &mut Vec<PentagramSparkle>,
     spawn_accum: &mut f32,
@@ -821,7 +1452,7 @@ fn lerp(a: f32, b: f32, t: f32) -> f32 {
 }
 
 fn hash11(mut x: f32) -> f32 {
-    x = (x * 12.9898).sin() * 43758.5453;
+    x = (x * 12.9898).sin() * 43_758.547;
     x.fract().abs()
 }
 
@@ -868,47 +1499,356 @@ fn saturate_color(color: Color, amount: f32) -> Color {
     }
 }
 
-fn handle_camera(game: &mut Game) {
-    let (_, wheel_y) = mouse_wheel();
-    if wheel_y.abs() > 0.0 {
-        let zoom = game.camera.zoom * (1.0 + wheel_y * CAMERA_ZOOM_STEP);
-        game.camera.zoom = zoom.clamp(CAMERA_ZOOM_MIN, CAMERA_ZOOM_MAX);
+fn update_camp_editor(mode: &mut CampEditorMode, cursor: Vec2, radius: f32, app: &mut App) {
+    let actions = &app.actions;
+    let editor = &mut mode.editor;
+    if actions.is_pressed(Action::EditorGrab) {
+        editor.press(cursor, radius);
+    }
+    if actions.is_down(Action::EditorGrab) {
+        editor.drag_to(cursor);
+    }
+    if actions.is_released(Action::EditorGrab) {
+        editor.release();
+    }
+    if actions.is_pressed(Action::EditorRemove) {
+        editor.delete_at(cursor, radius);
+    }
+    if actions.is_pressed(Action::EditorDeleteSelected) {
+        editor.delete_selected();
+    }
+    if actions.is_pressed(Action::EditorNextField) {
+        editor.next_field();
+    }
+    if actions.is_pressed(Action::EditorPrevColor) {
+        editor.cycle_color(-1);
+    }
+    if actions.is_pressed(Action::EditorNextColor) {
+        editor.cycle_color(1);
+    }
+    if actions.is_pressed(Action::EditorBackspace) {
+        editor.backspace();
+    }
+    while let Some(c) = get_char_pressed() {
+        editor.type_char(c);
+    }
+
+    if actions.is_pressed(Action::EditorNextCamp) {
+        mode.source = (mode.source + 1) % (app.camps.len() + 1);
+        mode.editor = match mode.source {
+            0 => CampEditor::new(),
+            index => CampEditor::from_camp(&app.camps[index - 1]),
+        };
+        mode.message = None;
+    }
+    if app.actions.is_pressed(Action::Confirm) {
+        mode.message = Some(export_edited_camp(&mut mode.editor, app));
+    }
+}
+
+/// Applies scenery editor input. Returns `true` when the layout changed and
+/// the world's scenery needs refreshing.
+fn update_scenery_editor(
+    mode: &mut SceneryEditorMode,
+    cursor: Vec2,
+    radius: f32,
+    app: &mut App,
+) -> bool {
+    let actions = &app.actions;
+    let editor = &mut mode.editor;
+    let mut changed = false;
+    if actions.is_pressed(Action::EditorGrab) {
+        changed |= editor.press(cursor, radius);
+    }
+    if actions.is_down(Action::EditorGrab) {
+        changed |= editor.drag_to(cursor);
+    }
+    if actions.is_released(Action::EditorGrab) {
+        editor.release();
+    }
+    if actions.is_pressed(Action::EditorRemove) {
+        changed |= editor.delete_at(cursor, radius);
+    }
+    if actions.is_pressed(Action::EditorDeleteSelected) {
+        changed |= editor.delete_selected();
+    }
+    if actions.is_pressed(Action::EditorNextKind) {
+        editor.cycle_kind(1);
+    }
+    if actions.is_pressed(Action::EditorRotateLeft) {
+        changed |= editor.rotate_selected(-SCENERY_EDITOR_ROTATE_STEP);
+    }
+    if actions.is_pressed(Action::EditorRotateRight) {
+        changed |= editor.rotate_selected(SCENERY_EDITOR_ROTATE_STEP);
+    }
+    let rotating = actions.is_pressed(Action::EditorRotateLeft)
+        || actions.is_pressed(Action::EditorRotateRight);
+    if let Some(item) = editor
+        .selected_item()
+        .filter(|item| rotating && !item.kind.rotates())
+    {
+        mode.message = Some(format!("A {} is always drawn upright", item.kind.name()));
+    }
+    if actions.is_pressed(Action::EditorShrink) {
+        changed |= editor.scale_selected(1.0 / SCENERY_EDITOR_SCALE_FACTOR);
     }
+    if actions.is_pressed(Action::EditorGrow) {
+        changed |= editor.scale_selected(SCENERY_EDITOR_SCALE_FACTOR);
+    }
+    if actions.is_pressed(Action::EditorNextVariant) {
+        changed |= editor.cycle_variant();
+    }
+    if actions.is_pressed(Action::EditorToggleCrystal) {
+        changed |= editor.toggle_decoration(DomeDecoration::Crystal);
+    }
+
+    if actions.is_pressed(Action::Confirm) {
+        let layout = editor.to_layout();
+        let path = app.asset_root.join(scenery::SCENERY_PATH);
+        mode.message = Some(match layout.save(&path, app.map.field_rect()) {
+            Ok(()) => {
+                app.scenery = layout;
+                format!("Saved {}", path.display())
+            }
+            Err(err) => format!("Save failed: {}", err),
+        });
+    }
+    changed
+}
+
+/// Writes the edited camp to the camp directory and updates the camps new
+/// worlds are built from. Returns the status line to show.
+fn export_edited_camp(editor: &mut CampEditor, app: &mut App) -> String {
+    let camp = editor.to_camp();
+    let replaces = editor
+        .source_name
+        .clone()
+        .unwrap_or_else(|| camp.name.clone());
+    match camps::export_camp(
+        &camp,
+        &replaces,
+        app.asset_root.join(camps::CAMP_DIR),
+        app.map.field_rect(),
+    ) {
+        Ok(path) => {
+            match app.camps.iter_mut().find(|other| other.name == replaces) {
+                Some(existing) => *existing = camp.clone(),
+                None => app.camps.push(camp.clone()),
+            }
+            editor.source_name = Some(camp.name);
+            format!("Exported {}", path.display())
+        }
+        Err(err) => format!("Export failed: {}", err),
+    }
+}
+
+fn draw_camp_editor(editor: &CampEditor, zoom: f32) {
+    let mut fill = editor.color;
+    fill.a = 0.45;
+    let thin = 1.0 / zoom;
+    for tri in editor.triangles() {
+        draw_triangle(tri[0], tri[1], tri[2], fill);
+        draw_triangle_lines(tri[0], tri[1], tri[2], thin, WHITE);
+    }
+
+    let edge = if editor.warning().is_some() {
+        RED
+    } else {
+        ACCENT
+    };
+    let vertices = &editor.vertices;
+    for (index, start) in vertices.iter().enumerate() {
+        let end = vertices[(index + 1) % vertices.len()];
+        draw_line(start.x, start.y, end.x, end.y, thin * 2.0, edge);
+    }
+    for (index, vertex) in vertices.iter().enumerate() {
+        let color = if editor.selected == Some(index) {
+            ACCENT
+        } else {
+            WHITE
+        };
+        draw_circle(
+            vertex.x,
+            vertex.y,
+            CAMP_EDITOR_HANDLE_RADIUS * 0.5 / zoom,
+            color,
+        );
+    }
+}
+
+fn draw_scenery_editor(editor: &SceneryEditor, zoom: f32) {
+    let marker = SCENERY_EDITOR_PICK_RADIUS / zoom;
+    for (index, item) in editor.items.iter().enumerate() {
+        let (color, thickness) = if editor.selected == Some(index) {
+            (ACCENT, 3.0)
+        } else {
+            (WHITE, 1.0)
+        };
+        draw_circle_lines(item.pos.x, item.pos.y, marker, thickness / zoom, color);
+    }
+}
+
+fn draw_camp_editor_panel(mode: &CampEditorMode, cursor: Vec2, actions: &ActionMap) {
+    let editor = &mode.editor;
+    let field = |label: &str, text: &str, focused: bool| {
+        let cursor = if focused { "_" } else { "" };
+        format!("{}: {}{}", label, text, cursor)
+    };
+    let focus = editor.focus;
+    let status = match editor.warning() {
+        Some(warning) => (
+            format!("{} vertices - {}", editor.vertices.len(), warning.message()),
+            RED,
+        ),
+        None => (format!("{} vertices", editor.vertices.len()), ACCENT),
+    };
+    let lines = [
+        ("Camp editor".to_string(), ACCENT),
+        (
+            field("Name", &editor.name, focus == EditorField::Name),
+            ACCENT,
+        ),
+        (
+            field("Notice", &editor.notice_text, focus == EditorField::Notice),
+            ACCENT,
+        ),
+        status,
+        (format_cursor(cursor), ACCENT),
+        (mode.message.clone().unwrap_or_default(), ACCENT),
+        (
+            format!(
+                "{} add/drag, {} or {} remove, {} field, {}/{} color, {} next camp, {} export, {} next editor, {} close",
+                actions.primary(Action::EditorGrab),
+                actions.primary(Action::EditorRemove),
+                actions.primary(Action::EditorDeleteSelected),
+                actions.primary(Action::EditorNextField),
+                actions.primary(Action::EditorPrevColor),
+                actions.primary(Action::EditorNextColor),
+                actions.primary(Action::EditorNextCamp),
+                actions.primary(Action::Confirm),
+                actions.primary(Action::ToggleEditor),
+                actions.primary(Action::Back)
+            ),
+            ACCENT,
+        ),
+    ];
+    draw_editor_panel(&lines);
+}
+
+fn draw_scenery_editor_panel(mode: &SceneryEditorMode, cursor: Vec2, actions: &ActionMap) {
+    let editor = &mode.editor;
+    let selected = match editor.selected_item() {
+        Some(item) => {
+            let decorations = item
+                .decorations
+                .iter()
+                .map(|decoration| format!("{:?}", decoration).to_lowercase())
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!(
+                "Selected: {}  rotation {:.2}  scale {:.2}  variant {}  {}",
+                item.kind.name(),
+                item.rotation,
+                item.scale,
+                item.variant,
+                decorations
+            )
+        }
+        None => "Selected: none".to_string(),
+    };
+    let lines = [
+        ("Scenery editor".to_string(), ACCENT),
+        (format!("Placing: {}", editor.kind.name()), ACCENT),
+        (selected, ACCENT),
+        (
+            format!(
+                "{} items, {} random domes",
+                editor.items.len(),
+                editor.random_domes
+            ),
+            ACCENT,
+        ),
+        (format_cursor(cursor), ACCENT),
+        (mode.message.clone().unwrap_or_default(), ACCENT),
+        (
+            format!(
+                "{} place/drag, {} or {} remove, {} kind, {}/{} rotate, {}/{} scale, {} variant, {} crystal, {} save, {} next editor, {} close",
+                actions.primary(Action::EditorGrab),
+                actions.primary(Action::EditorRemove),
+                actions.primary(Action::EditorDeleteSelected),
+                actions.primary(Action::EditorNextKind),
+                actions.primary(Action::EditorRotateLeft),
+                actions.primary(Action::EditorRotateRight),
+                actions.primary(Action::EditorShrink),
+                actions.primary(Action::EditorGrow),
+                actions.primary(Action::EditorNextVariant),
+                actions.primary(Action::EditorToggleCrystal),
+                actions.primary(Action::Confirm),
+                actions.primary(Action::ToggleEditor),
+                actions.primary(Action::Back)
+            ),
+            ACCENT,
+        ),
+    ];
+    draw_editor_panel(&lines);
+}
+
+fn format_cursor(cursor: Vec2) -> String {
+    format!("Cursor X: {:.0}  Y: {:.0}", cursor.x, cursor.y)
+}
+
+fn draw_editor_panel(lines: &[(String, Color)]) {
+    let top = screen_height() - HUD_HEIGHT - 20.0 - lines.len() as f32 * 22.0;
+    draw_rectangle(
+        0.0,
+        top - 20.0,
+        screen_width(),
+        lines.len() as f32 * 22.0 + 16.0,
+        Color::new(0.0, 0.0, 0.0, 0.7),
+    );
+    for (index, (text, color)) in lines.iter().enumerate() {
+        draw_text(text, 16.0, top + index as f32 * 22.0, 20.0, *color);
+    }
+}
+
+fn handle_camera(
+    camera: &mut camera::CameraState,
+    actions: &ActionMap,
+    settings: &Settings,
+    tuning: &Tuning,
+) {
+    let (_, wheel_y) = mouse_wheel();
+    let zoom = camera.zoom * (1.0 + wheel_y * tuning.camera.zoom_step);
+    camera.zoom = settings.clamp_zoom(zoom);
 
     let mouse = vec2(mouse_position().0, mouse_position().1);
-    if is_mouse_button_pressed(MouseButton::Middle) {
-        game.camera.begin_drag(mouse);
+    if actions.is_pressed(Action::PanCamera) {
+        camera.begin_drag(mouse);
     }
-    if is_mouse_button_down(MouseButton::Middle) {
-        if let Some(delta) = game.camera.drag(mouse) {
-            game.camera.pan -= delta / game.camera.zoom;
+    if actions.is_down(Action::PanCamera) {
+        if let Some(delta) = camera.drag(mouse) {
+            camera.pan -= delta / camera.zoom;
         }
     } else {
-        game.camera.end_drag();
+        camera.end_drag();
     }
 }
 
-fn build_camera(game: &Game) -> Camera2D {
+fn build_camera(state: &camera::CameraState, map_size: Vec2, player_center: Vec2) -> Camera2D {
     let screen = vec2(screen_width(), screen_height());
-    let view = camera::view_size(screen, game.camera.zoom);
-    let player_center =
-        game.player.pos + vec2(player::PLAYER_WIDTH * 0.5, player::PLAYER_HEIGHT * 0.5);
-    let target = camera::clamp_target(
-        player_center + game.camera.pan,
-        vec2(game.map.width, game.map.height),
-        view,
-    );
+    let view = camera::view_size(screen, state.zoom);
+    let target = camera::clamp_target(player_center + state.pan, map_size, view);
 
     let mut cam = Camera2D::from_display_rect(Rect::new(0.0, 0.0, screen.x, screen.y));
     cam.target = target;
-    cam.zoom *= game.camera.zoom;
+    cam.zoom *= state.zoom;
     cam.zoom = camera::flip_zoom_y(cam.zoom);
     cam
 }
 
-fn camera_view_rect(game: &Game, target: Vec2) -> Rect {
+fn camera_view_rect(state: &camera::CameraState, target: Vec2) -> Rect {
     let screen = vec2(screen_width(), screen_height());
-    let view = camera::view_size(screen, game.camera.zoom);
+    let view = camera::view_size(screen, state.zoom);
     Rect::new(
         target.x - view.x * 0.5,
         target.y - view.y * 0.5,
@@ -917,6 +1857,70 @@ fn camera_view_rect(game: &Game, target: Vec2) -> Rect {
     )
 }
 
+/// The route of a click to move, from the player's feet to the destination.
+fn draw_move_path(feet: Vec2, path: &[Vec2], zoom: f32) {
+    let width = 1.5 / zoom;
+    let mut from = feet;
+    for point in path {
+        draw_line(from.x, from.y, point.x, point.y, width, MOVE_PATH_COLOR);
+        from = *point;
+    }
+    draw_circle_lines(from.x, from.y, 6.0 / zoom, width, MOVE_PATH_COLOR);
+}
+
+fn draw_waypoint_marker(pos: Vec2, size: f32) {
+    draw_poly(pos.x, pos.y, 4, size, 0.0, WAYPOINT_COLOR);
+    draw_poly_lines(pos.x, pos.y, 4, size, 0.0, size * 0.2, BLACK);
+}
+
+/// Points from the screen edge towards a waypoint outside the view, with
+/// the distance from the player.
+fn draw_waypoint_arrow(waypoint: Vec2, player: Vec2, view: Rect) {
+    let screen = vec2(screen_width(), screen_height() - HUD_HEIGHT);
+    let Some((pos, angle)) =
+        world_map::waypoint_arrow(waypoint, view, screen, WAYPOINT_ARROW_INSET)
+    else {
+        return;
+    };
+    let forward = Vec2::from_angle(angle);
+    let side = forward.perp();
+    let tip = pos + forward * 14.0;
+    let back = pos - forward * 8.0;
+    draw_triangle(tip, back + side * 9.0, back - side * 9.0, WAYPOINT_COLOR);
+    draw_triangle_lines(tip, back + side * 9.0, back - side * 9.0, 2.0, BLACK);
+    let distance = format!("{:.0}", waypoint.distance(player));
+    let metrics = measure_text(&distance, None, 18, 1.0);
+    let label = pos - forward * 24.0;
+    draw_text(
+        &distance,
+        label.x - metrics.width * 0.5,
+        label.y + 6.0,
+        18.0,
+        WAYPOINT_COLOR,
+    );
+}
+
+/// Splits `text` at spaces into lines no wider than `max_width`.
+fn wrap_text(text: &str, size: f32, max_width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split(' ') {
+        let candidate = if current.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", current, word)
+        };
+        if !current.is_empty() && measure_text(&candidate, None, size as u16, 1.0).width > max_width
+        {
+            lines.push(std::mem::replace(&mut current, word.to_string()));
+        } else {
+            current = candidate;
+        }
+    }
+    lines.push(current);
+    lines
+}
+
 fn draw_centered(text: &str, y: f32, size: f32, color: Color) {
     let metrics = measure_text(text, None, size as u16, 1.0);
     let x = (screen_width() - metrics.width) * 0.5;
@@ -948,19 +1952,6 @@ mod tests {
         assert!((mid.a - 0.8).abs() < 1e-6);
     }
 
-    #[test]
-    fn player_in_pentagram_center_respects_radius() {
-        let centers = vec![vec2(0.0, 0.0)];
-        assert!(player_in_pentagram(
-            vec2(PENTAGRAM_CENTER_RADIUS * 0.5, 0.0),
-            &centers
-        ));
-        assert!(!player_in_pentagram(
-            vec2(PENTAGRAM_CENTER_RADIUS * 1.1, 0.0),
-            &centers
-        ));
-    }
-
     #[test]
     fn sparkle_spawn_count_accumulates() {
         let mut accum = 0.0;
@@ -969,6 +1960,42 @@ mod tests {
         assert!((accum - 0.0).abs() < 1e-6);
     }
 
+    #[test]
+    fn latched_clicks_survive_until_consumed() {
+        let mut pending = world::InputFrame::default();
+        let click = world::InputFrame {
+            place_flag: true,
+            ..Default::default()
+        };
+        let mut held = world::InputFrame::default();
+        held.movement.left = true;
+
+        latch_input_frame(&mut pending, click);
+        latch_input_frame(&mut pending, held);
+        assert!(pending.place_flag);
+        assert!(pending.movement.left);
+        assert!(!pending.pickup_flag);
+    }
+
+    #[test]
+    fn settings_rows_cover_every_action() {
+        let rows = settings_rows();
+        for action in input::ALL_ACTIONS {
+            assert!(rows.contains(&SettingsRow::Keybind(action)));
+        }
+    }
+
+    #[test]
+    fn zoom_limits_cannot_cross() {
+        let mut settings = Settings::default();
+        for _ in 0..500 {
+            adjust_setting(&mut settings, SettingsRow::ZoomMin, 1.0);
+        }
+        assert!(settings.zoom_min <= settings.zoom_max);
+        adjust_setting(&mut settings, SettingsRow::Volume, -20.0);
+        assert_eq!(settings.volume, 0.0);
+    }
+
     #[test]
     fn sparkle_alpha_fades_to_zero_at_max_radius() {
         let alpha = sparkle_alpha(0.8, 100.0, 100.0);
@@ -995,73 +2022,4 @@ mod tests {
         assert!((camp_notice_alpha(3.75) - 0.5).abs() < 1e-4);
         assert!(camp_notice_alpha(4.0) <= 1e-6);
     }
-
-    #[test]
-    fn camp_notice_switches_on_new_entry() {
-        let mut notices = vec![CampNotice::new("a", "A"), CampNotice::new("b", "B")];
-
-        update_camp_notice_states(&mut notices, &[true, false], 0.1);
-        assert_eq!(notices[0].timer, 0.0);
-        assert!(notices[1].timer < 0.0);
-
-        update_camp_notice_states(&mut notices, &[false, true], 0.1);
-        assert!(notices[0].timer < 0.0);
-        assert_eq!(notices[1].timer, 0.0);
-    }
-
-    #[test]
-    fn flagic_increases_while_in_pentagram() {
-        let mut flagic = 0u8;
-        let mut accum = 0.0;
-        update_flagic(&mut flagic, &mut accum, true, 0.2);
-        assert_eq!(flagic, 1);
-        assert!(accum.abs() < 1e-6);
-    }
-
-    #[test]
-    fn flagic_does_not_increase_outside_pentagram() {
-        let mut flagic = 0u8;
-        let mut accum = 0.0;
-        update_flagic(&mut flagic, &mut accum, false, 1.0);
-        assert_eq!(flagic, 0);
-        assert!(accum.abs() < 1e-6);
-    }
-
-    #[test]
-    fn flagic_clamps_to_max() {
-        let mut flagic = 99u8;
-        let mut accum = 0.0;
-        update_flagic(&mut flagic, &mut accum, true, 1.0);
-        assert_eq!(flagic, FLAGIC_MAX);
-        assert!(accum.abs() < 1e-6);
-    }
-
-    #[test]
-    fn total_hippie_flags_sums_carried_flags() {
-        let camp = [vec2(0.0, 0.0), vec2(10.0, 0.0), vec2(0.0, 10.0)];
-        let hippies =
-            npc::spawn_hippies_with_flags(&[(vec2(1.0, 1.0), 1), (vec2(2.0, 2.0), 2)], 0, &camp);
-        assert_eq!(total_hippie_flags(&hippies), 3);
-    }
-
-    #[test]
-    fn stress_test_spawns_hippies_with_one_flag_each() {
-        let camp_configs = camps::camp_configs();
-        let hippies = spawn_stress_test_hippies(&camp_configs);
-        assert_eq!(hippies.len(), STRESS_TEST_HIPPIE_COUNT);
-        assert!(hippies
-            .iter()
-            .all(|hippie| hippie.carried_flags == STRESS_TEST_FLAGS_PER_HIPPIE));
-
-        let mut counts = vec![0usize; camp_configs.len()];
-        for hippie in &hippies {
-            counts[hippie.camp_index] += 1;
-        }
-        let per_camp = STRESS_TEST_HIPPIE_COUNT / camp_configs.len();
-        let remainder = STRESS_TEST_HIPPIE_COUNT % camp_configs.len();
-        for (index, count) in counts.iter().enumerate() {
-            let expected = per_camp + usize::from(index < remainder);
-            assert_eq!(*count, expected);
-        }
-    }
 }
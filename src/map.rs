@@ -1,8 +1,55 @@
-use crate::assets;
+use crate::assets::AssetError;
+use crate::constants::{
+    TILE_CACHE_CAPACITY, TILE_MAX_IN_FLIGHT, TILE_PLACEHOLDER_COLOR, TILE_PREFETCH_MARGIN,
+};
 use crate::geom;
+use crate::tile_cache::{LruCache, TileLoader};
 use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::path::Path;
 
+/// Written beside the tiles by the `slice_map` tool.
+pub const MANIFEST_FILE: &str = "manifest.toml";
+
+/// Size of a sliced map. `width` and `height` are the source image size;
+/// edge tiles are padded with transparency up to `tile_size`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MapManifest {
+    pub tile_size: u32,
+    pub columns: usize,
+    pub rows: usize,
+    pub width: u32,
+    pub height: u32,
+    /// Coarser levels written next to the base tiles. Missing levels are
+    /// built when first drawn.
+    #[serde(default)]
+    pub levels: usize,
+}
+
+impl MapManifest {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, AssetError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|error| AssetError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        toml::from_str(&text).map_err(|error| AssetError::ManifestParse {
+            path: path.to_path_buf(),
+            error,
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), AssetError> {
+        let path = path.as_ref();
+        let text = toml::to_string(self).map_err(AssetError::ManifestSerialize)?;
+        std::fs::write(path, text).map_err(|error| AssetError::Io {
+            path: path.to_path_buf(),
+            error,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct TileMap {
     pub tile_size: f32,
@@ -10,18 +57,27 @@ pub struct TileMap {
     pub rows: usize,
     pub width: f32,
     pub height: f32,
-    tiles: Vec<Option<Texture2D>>,
+    /// Per level, whether each tile has any base tile under it.
+    present: Vec<Vec<bool>>,
+    cache: LruCache<TileKey, Texture2D>,
+    /// Tiles requested from the loader and not yet received.
+    pending: HashSet<TileKey>,
+    /// Tiles that failed to load; not requested again.
+    failed: HashSet<TileKey>,
+    /// The single top-level tile, kept out of the cache for the minimap.
+    overview: Option<Texture2D>,
+    loader: TileLoader,
 }
 
 #[derive(Debug)]
 pub struct MapRegion {
-    pub name: &'static str,
+    pub name: String,
     triangles: Vec<[Vec2; 3]>,
     color: Color,
 }
 
 impl MapRegion {
-    pub fn new(name: &'static str, vertices: Vec<Vec2>, color: Color) -> Self {
+    pub fn new(name: String, vertices: Vec<Vec2>, color: Color) -> Self {
         let triangles = geom::triangulate_polygon(&vertices);
         Self {
             name,
@@ -36,6 +92,10 @@ impl MapRegion {
             .any(|tri| geom::point_in_triangle(point, tri[0], tri[1], tri[2]))
     }
 
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
     pub fn draw(&self) {
         for tri in &self.triangles {
             draw_triangle(tri[0], tri[1], tri[2], self.color);
@@ -44,24 +104,34 @@ impl MapRegion {
 }
 
 impl TileMap {
-    pub fn load_from_dir<P: AsRef<Path>>(path: P) -> Self {
+    /// Indexes the base tiles in `path` without decoding them. Tiles are
+    /// decoded in the background as they come near the view, and coarser
+    /// levels missing from the directory are built from the base tiles.
+    /// Only tile headers are read here, to check every tile is the same
+    /// square size. A `MANIFEST_FILE` beside the tiles sets the map size.
+    pub fn load_from_dir<P: AsRef<Path>>(path: P) -> Result<Self, AssetError> {
         let path = path.as_ref();
+        let io_error = |error| AssetError::Io {
+            path: path.to_path_buf(),
+            error,
+        };
         let mut entries = Vec::new();
 
-        for entry in std::fs::read_dir(path).expect("Failed to read map directory") {
-            let entry = entry.expect("Failed to read map entry");
-            let file_path = entry.path();
+        for entry in std::fs::read_dir(path).map_err(io_error)? {
+            let file_path = entry.map_err(io_error)?.path();
             if file_path.extension().and_then(|e| e.to_str()) != Some("png") {
                 continue;
             }
-            let file_name = file_path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .expect("Invalid filename");
+            let Some(file_name) = file_path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
             if let Some((x, y)) = parse_tile_filename(file_name) {
                 entries.push((x, y, file_path));
             }
         }
+        if entries.is_empty() {
+            return Err(AssetError::NoTiles(path.to_path_buf()));
+        }
 
         entries.sort_by(|a, b| (a.1, a.0).cmp(&(b.1, b.0)));
         let max_x = entries.iter().map(|(x, _, _)| *x).max().unwrap_or(0);
@@ -70,56 +140,127 @@ impl TileMap {
         let columns = max_x + 1;
         let rows = max_y + 1;
 
-        let mut tiles: Vec<Option<Texture2D>> = vec![None; columns * rows];
-        let mut tile_size = None;
-
-        for (x, y, file_path) in entries {
-            let raster =
-                assets::load_png_rgba(file_path.to_str().expect("Invalid tile path string"));
-            tile_size.get_or_insert(raster.width as f32);
-            if (raster.width as f32 - tile_size.unwrap()).abs() > f32::EPSILON {
-                panic!("Tile width mismatch for {:?}", file_path);
-            }
-            if (raster.height as f32 - tile_size.unwrap()).abs() > f32::EPSILON {
-                panic!("Tile height mismatch for {:?}", file_path);
+        let mut tile_px = None;
+        for (_, _, file_path) in &entries {
+            let (width, height) =
+                image::image_dimensions(file_path).map_err(|error| AssetError::Decode {
+                    path: file_path.clone(),
+                    error,
+                })?;
+            let expected = *tile_px.get_or_insert(width.min(u16::MAX as u32) as u16);
+            if width != height || width != expected as u32 {
+                return Err(AssetError::TileSize {
+                    path: file_path.clone(),
+                    expected,
+                    width,
+                    height,
+                });
             }
+        }
+        let tile_px = tile_px.unwrap_or(1);
+        let tile_size = tile_px as f32;
 
-            let texture = Texture2D::from_rgba8(raster.width, raster.height, &raster.pixels);
-            texture.set_filter(FilterMode::Linear);
-
-            let index = y * columns + x;
-            tiles[index] = Some(texture);
+        let mut base = vec![false; columns * rows];
+        for (x, y, _) in &entries {
+            base[y * columns + x] = true;
         }
+        let present = level_presence(base, columns, rows);
 
-        let tile_size = tile_size.unwrap_or(1.0);
-        let width = tile_size * columns as f32;
-        let height = tile_size * rows as f32;
+        let mut width = tile_size * columns as f32;
+        let mut height = tile_size * rows as f32;
+        let manifest_path = path.join(MANIFEST_FILE);
+        if manifest_path.exists() {
+            let manifest = MapManifest::load(&manifest_path)?;
+            if manifest.tile_size != tile_px as u32
+                || manifest.columns != columns
+                || manifest.rows != rows
+                || manifest.width as f32 > width
+                || manifest.height as f32 > height
+            {
+                return Err(AssetError::ManifestMismatch(manifest_path));
+            }
+            width = manifest.width as f32;
+            height = manifest.height as f32;
+        }
 
-        Self {
+        Ok(Self {
             tile_size,
             columns,
             rows,
             width,
             height,
-            tiles,
-        }
+            present,
+            cache: LruCache::new(TILE_CACHE_CAPACITY),
+            pending: HashSet::new(),
+            failed: HashSet::new(),
+            overview: None,
+            loader: TileLoader::spawn(path.to_path_buf(), tile_px),
+        })
+    }
+
+    /// Number of detail levels; the last one covers the map in one tile.
+    pub fn level_count(&self) -> usize {
+        self.present.len()
     }
 
-    pub fn draw(&self, view: Rect) {
-        let range = tile_range(view, self.tile_size, self.columns, self.rows);
+    /// Tile columns and rows at `level`.
+    pub fn level_size(&self, level: usize) -> (usize, usize) {
+        (
+            self.columns.div_ceil(1 << level),
+            self.rows.div_ceil(1 << level),
+        )
+    }
+
+    /// Draws the tiles under `view` at the level of detail that matches the
+    /// on-screen scale, and queues loads for tiles in and around it. Tiles
+    /// still loading show a coarser cached level, or a placeholder.
+    pub fn draw(&mut self, view: Rect) {
+        self.receive_tiles();
+
+        let level = lod_for_scale(view.w / screen_width(), self.level_count() - 1);
+        let size = self.tile_size * (1 << level) as f32;
+        let (columns, rows) = self.level_size(level);
+        let range = tile_range(view, size, columns, rows);
+        let prefetch = range.expand(TILE_PREFETCH_MARGIN, columns, rows);
+        // Never evict tiles the current view still needs.
+        self.cache
+            .set_capacity(TILE_CACHE_CAPACITY.max(prefetch.tile_count()));
+        for (x, y) in prefetch_order(prefetch, view.center(), size) {
+            let key = TileKey { level, x, y };
+            if self.cache.get(&key).is_some() {
+                continue;
+            }
+            if self.pending.len() >= TILE_MAX_IN_FLIGHT
+                || self.pending.contains(&key)
+                || self.failed.contains(&key)
+                || !self.is_present(key)
+            {
+                continue;
+            }
+            self.pending.insert(key);
+            self.loader.request(key);
+        }
+
         for y in range.y0..=range.y1 {
             for x in range.x0..=range.x1 {
-                if let Some(texture) = &self.tiles[y * self.columns + x] {
-                    draw_texture_ex(
-                        texture,
-                        x as f32 * self.tile_size,
-                        y as f32 * self.tile_size,
+                let key = TileKey { level, x, y };
+                if !self.is_present(key) {
+                    continue;
+                }
+                let pos = vec2(x as f32, y as f32) * size;
+                match self.cached_or_ancestor(key) {
+                    Some((texture, source)) => draw_texture_ex(
+                        &texture,
+                        pos.x,
+                        pos.y,
                         WHITE,
                         DrawTextureParams {
-                            dest_size: Some(vec2(self.tile_size, self.tile_size)),
+                            dest_size: Some(vec2(size, size)),
+                            source,
                             ..Default::default()
                         },
-                    );
+                    ),
+                    None => draw_rectangle(pos.x, pos.y, size, size, TILE_PLACEHOLDER_COLOR),
                 }
             }
         }
@@ -128,6 +269,153 @@ impl TileMap {
     pub fn field_rect(&self) -> Rect {
         Rect::new(0.0, 0.0, self.width, self.height)
     }
+
+    /// The whole map as one downsampled texture, with the texel rect that
+    /// covers the field. Requests it on first use; `None` until it loads.
+    pub fn overview(&mut self) -> Option<(Texture2D, Rect)> {
+        self.receive_tiles();
+        let key = self.overview_key();
+        let Some(texture) = &self.overview else {
+            if !self.pending.contains(&key) && !self.failed.contains(&key) {
+                self.pending.insert(key);
+                self.loader.request(key);
+            }
+            return None;
+        };
+        let span = (1 << key.level) as f32;
+        let source = Rect::new(0.0, 0.0, self.width / span, self.height / span);
+        Some((texture.clone(), source))
+    }
+
+    fn overview_key(&self) -> TileKey {
+        TileKey {
+            level: self.level_count() - 1,
+            x: 0,
+            y: 0,
+        }
+    }
+
+    fn is_present(&self, key: TileKey) -> bool {
+        let (columns, _) = self.level_size(key.level);
+        self.present
+            .get(key.level)
+            .and_then(|level| level.get(key.y * columns + key.x))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// The texture for `key`, or the part of the nearest cached ancestor
+    /// that covers it.
+    fn cached_or_ancestor(&mut self, key: TileKey) -> Option<(Texture2D, Option<Rect>)> {
+        if let Some(texture) = self.cache.get(&key) {
+            return Some((texture.clone(), None));
+        }
+        for depth in 1..self.level_count() - key.level {
+            let ancestor = key.ancestor(depth);
+            if let Some(texture) = self.cache.get(&ancestor) {
+                let span = 1 << depth;
+                let part = self.tile_size / span as f32;
+                let source = Rect::new(
+                    (key.x % span) as f32 * part,
+                    (key.y % span) as f32 * part,
+                    part,
+                    part,
+                );
+                return Some((texture.clone(), Some(source)));
+            }
+        }
+        None
+    }
+
+    /// Uploads tiles the loader has finished decoding.
+    fn receive_tiles(&mut self) {
+        for tile in self.loader.poll() {
+            self.pending.remove(&tile.key);
+            let raster = match tile.raster {
+                Ok(raster) => raster,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    self.failed.insert(tile.key);
+                    continue;
+                }
+            };
+            let texture = Texture2D::from_rgba8(raster.width, raster.height, &raster.pixels);
+            texture.set_filter(FilterMode::Linear);
+            if tile.key == self.overview_key() {
+                self.overview = Some(texture.clone());
+            }
+            self.cache.insert(tile.key, texture);
+        }
+    }
+}
+
+/// One tile of the map pyramid. Level 0 holds the base tiles; each level up
+/// halves the resolution, so a level `z` tile covers `2^z` base tiles per
+/// side at the same pixel size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TileKey {
+    pub level: usize,
+    pub x: usize,
+    pub y: usize,
+}
+
+impl TileKey {
+    /// `tile_{x}_{y}.png` for base tiles, `tile_{z}_{x}_{y}.png` above.
+    pub fn file_name(self) -> String {
+        match self.level {
+            0 => format!("tile_{}_{}.png", self.x, self.y),
+            level => format!("tile_{}_{}_{}.png", level, self.x, self.y),
+        }
+    }
+
+    /// The key `depth` levels up that covers this tile.
+    pub fn ancestor(self, depth: usize) -> Self {
+        Self {
+            level: self.level + depth,
+            x: self.x >> depth,
+            y: self.y >> depth,
+        }
+    }
+
+    /// The four tiles one level down, in reading order.
+    pub fn children(self) -> [Self; 4] {
+        let level = self.level.saturating_sub(1);
+        let (x, y) = (self.x * 2, self.y * 2);
+        [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(dx, dy)| Self {
+            level,
+            x: x + dx,
+            y: y + dy,
+        })
+    }
+}
+
+/// Which tiles exist at each level, given the base tiles. A coarser tile
+/// exists if any base tile under it does; the last level is a single tile.
+fn level_presence(base: Vec<bool>, columns: usize, rows: usize) -> Vec<Vec<bool>> {
+    let mut levels = vec![base];
+    let (mut columns, mut rows) = (columns, rows);
+    while columns > 1 || rows > 1 {
+        let below = levels.last().expect("base level");
+        let (up_columns, up_rows) = (columns.div_ceil(2), rows.div_ceil(2));
+        let mut level = vec![false; up_columns * up_rows];
+        for y in 0..rows {
+            for x in 0..columns {
+                level[(y / 2) * up_columns + x / 2] |= below[y * columns + x];
+            }
+        }
+        levels.push(level);
+        (columns, rows) = (up_columns, up_rows);
+    }
+    levels
+}
+
+/// Level of detail for a view showing `world_per_pixel` map units per screen
+/// pixel: the coarsest level that still has at least one texel per pixel.
+pub fn lod_for_scale(world_per_pixel: f32, max_level: usize) -> usize {
+    if world_per_pixel.is_nan() || world_per_pixel <= 1.0 {
+        return 0;
+    }
+    (world_per_pixel.log2().floor() as usize).min(max_level)
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -138,6 +426,22 @@ pub struct TileRange {
     pub y1: usize,
 }
 
+impl TileRange {
+    pub fn tile_count(&self) -> usize {
+        (self.x1 - self.x0 + 1) * (self.y1 - self.y0 + 1)
+    }
+
+    /// Grows the range by `margin` tiles on every side, within the map.
+    pub fn expand(self, margin: usize, columns: usize, rows: usize) -> Self {
+        Self {
+            x0: self.x0.saturating_sub(margin),
+            x1: (self.x1 + margin).min(columns.saturating_sub(1)),
+            y0: self.y0.saturating_sub(margin),
+            y1: (self.y1 + margin).min(rows.saturating_sub(1)),
+        }
+    }
+}
+
 pub fn tile_range(view: Rect, tile_size: f32, columns: usize, rows: usize) -> TileRange {
     let x0 = (view.x / tile_size).floor().max(0.0) as usize;
     let y0 = (view.y / tile_size).floor().max(0.0) as usize;
@@ -152,15 +456,44 @@ pub fn tile_range(view: Rect, tile_size: f32, columns: usize, rows: usize) -> Ti
     }
 }
 
+/// Tiles in `range`, nearest to `center` first, so visible tiles load before
+/// the prefetch margin.
+pub fn prefetch_order(range: TileRange, center: Vec2, tile_size: f32) -> Vec<(usize, usize)> {
+    let mut tiles: Vec<(usize, usize)> = (range.y0..=range.y1)
+        .flat_map(|y| (range.x0..=range.x1).map(move |x| (x, y)))
+        .collect();
+    let distance = |&(x, y): &(usize, usize)| {
+        let tile_center = (vec2(x as f32, y as f32) + 0.5) * tile_size;
+        tile_center.distance_squared(center)
+    };
+    tiles.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+    tiles
+}
+
+/// Base tile position from a `tile_{x}_{y}.png` name.
 pub fn parse_tile_filename(name: &str) -> Option<(usize, usize)> {
+    match parse_tile_key(name)? {
+        TileKey { level: 0, x, y } => Some((x, y)),
+        _ => None,
+    }
+}
+
+/// Parses `tile_{x}_{y}.png` as a base tile and `tile_{z}_{x}_{y}.png` as a
+/// tile of level `z`.
+pub fn parse_tile_key(name: &str) -> Option<TileKey> {
     let name = name.strip_suffix(".png")?;
     let mut parts = name.split('_');
     if parts.next()? != "tile" {
         return None;
     }
-    let x = parts.next()?.parse::<usize>().ok()?;
-    let y = parts.next()?.parse::<usize>().ok()?;
-    Some((x, y))
+    let numbers = parts
+        .map(|part| part.parse::<usize>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    match numbers[..] {
+        [x, y] => Some(TileKey { level: 0, x, y }),
+        [level, x, y] if level > 0 => Some(TileKey { level, x, y }),
+        _ => None,
+    }
 }
 
 pub fn travel_speed(map_width: f32, map_height: f32, minutes: f32) -> f32 {
@@ -193,10 +526,121 @@ mod tests {
         assert_eq!(parse_tile_filename("map_1_2.png"), None);
     }
 
+    #[test]
+    fn parse_tile_key_reads_levels() {
+        assert_eq!(
+            parse_tile_key("tile_2_3_1.png"),
+            Some(TileKey {
+                level: 2,
+                x: 3,
+                y: 1
+            })
+        );
+        assert_eq!(parse_tile_filename("tile_2_3_1.png"), None);
+        assert_eq!(parse_tile_key("tile_0_3_1.png"), None);
+        let key = TileKey {
+            level: 1,
+            x: 4,
+            y: 2,
+        };
+        assert_eq!(parse_tile_key(&key.file_name()), Some(key));
+    }
+
+    #[test]
+    fn tile_keys_walk_the_pyramid() {
+        let key = TileKey {
+            level: 0,
+            x: 5,
+            y: 2,
+        };
+        let parent = key.ancestor(1);
+        assert_eq!(
+            parent,
+            TileKey {
+                level: 1,
+                x: 2,
+                y: 1
+            }
+        );
+        assert!(parent.children().contains(&key));
+        assert_eq!(
+            key.ancestor(3),
+            TileKey {
+                level: 3,
+                x: 0,
+                y: 0
+            }
+        );
+    }
+
+    #[test]
+    fn level_presence_reaches_single_tile() {
+        let mut base = vec![false; 9 * 6];
+        base[5 * 9 + 8] = true;
+        let levels = level_presence(base, 9, 6);
+        assert_eq!(levels.len(), 5);
+        assert_eq!(levels[1].len(), 5 * 3);
+        assert!(levels[1][2 * 5 + 4]);
+        assert_eq!(levels[1].iter().filter(|present| **present).count(), 1);
+        assert_eq!(levels[4], vec![true]);
+    }
+
+    #[test]
+    fn lod_matches_screen_scale() {
+        assert_eq!(lod_for_scale(0.25, 4), 0);
+        assert_eq!(lod_for_scale(1.9, 4), 0);
+        assert_eq!(lod_for_scale(2.0, 4), 1);
+        assert_eq!(lod_for_scale(9.6, 4), 3);
+        assert_eq!(lod_for_scale(100.0, 4), 4);
+    }
+
+    #[test]
+    fn load_from_dir_reports_bad_directories() {
+        let missing = std::env::temp_dir().join("flaghack2-no-such-map");
+        assert!(matches!(
+            TileMap::load_from_dir(&missing),
+            Err(AssetError::Io { .. })
+        ));
+
+        let dir = std::env::temp_dir().join(format!("flaghack2-map-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create map dir");
+        assert!(matches!(
+            TileMap::load_from_dir(&dir),
+            Err(AssetError::NoTiles(_))
+        ));
+        let tile = |size: u16| crate::assets::RasterImage {
+            width: size,
+            height: size,
+            pixels: vec![0; size as usize * size as usize * 4],
+        };
+        crate::assets::save_png_rgba(dir.join("tile_0_0.png"), &tile(4)).expect("save tile");
+        crate::assets::save_png_rgba(dir.join("tile_1_0.png"), &tile(8)).expect("save tile");
+        let err = TileMap::load_from_dir(&dir).unwrap_err();
+        assert!(matches!(
+            err,
+            AssetError::TileSize {
+                expected: 4,
+                width: 8,
+                ..
+            }
+        ));
+        std::fs::remove_dir_all(&dir).expect("remove map dir");
+    }
+
+    #[test]
+    fn shipped_map_matches_its_manifest() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(crate::constants::MAP_TILE_DIR);
+        let manifest = MapManifest::load(dir.join(MANIFEST_FILE)).expect("shipped manifest");
+        let map = TileMap::load_from_dir(&dir).expect("shipped map");
+        assert_eq!(map.width, manifest.width as f32);
+        assert_eq!(map.height, manifest.height as f32);
+        assert_eq!(map.tile_size, manifest.tile_size as f32);
+    }
+
     #[test]
     fn map_region_contains_point_inside_triangle() {
         let region = MapRegion::new(
-            "test",
+            "test".to_string(),
             vec![vec2(0.0, 0.0), vec2(10.0, 0.0), vec2(0.0, 10.0)],
             WHITE,
         );
@@ -214,6 +658,41 @@ mod tests {
         assert_eq!(range.y1, 1);
     }
 
+    #[test]
+    fn tile_range_expands_within_map() {
+        let range = TileRange {
+            x0: 0,
+            x1: 1,
+            y0: 2,
+            y1: 2,
+        };
+        let expanded = range.expand(1, 3, 4);
+        assert_eq!(
+            expanded,
+            TileRange {
+                x0: 0,
+                x1: 2,
+                y0: 1,
+                y1: 3,
+            }
+        );
+        assert_eq!(expanded.tile_count(), 9);
+    }
+
+    #[test]
+    fn prefetch_order_starts_nearest_center() {
+        let range = TileRange {
+            x0: 0,
+            x1: 2,
+            y0: 0,
+            y1: 2,
+        };
+        let order = prefetch_order(range, vec2(96.0, 96.0), 64.0);
+        assert_eq!(order.len(), 9);
+        assert_eq!(order[0], (1, 1));
+        assert!(order[5..].iter().all(|&(x, y)| x != 1 && y != 1));
+    }
+
     #[test]
     fn travel_speed_uses_max_dimension() {
         let speed = travel_speed(1200.0, 600.0, 10.0);
@@ -0,0 +1,152 @@
+use macroquad::prelude::*;
+
+use crate::constants::{ACCENT, MINIMAP_MARGIN, MINIMAP_SIZE};
+use crate::world::World;
+
+const MINIMAP_BACKGROUND: Color = Color::new(0.0, 0.0, 0.0, 0.6);
+const MINIMAP_FLAG_COLOR: Color = Color::new(1.0, 0.35, 0.75, 1.0);
+const MINIMAP_HIPPIE_COLOR: Color = Color::new(0.75, 0.75, 0.75, 1.0);
+
+/// Where the minimap sits on screen and how map positions map onto it. The
+/// map keeps its aspect ratio inside a `MINIMAP_SIZE` square in the top
+/// right corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Minimap {
+    pub rect: Rect,
+    field: Rect,
+}
+
+impl Minimap {
+    pub fn new(field: Rect, screen: Vec2) -> Self {
+        let scale = MINIMAP_SIZE / field.w.max(field.h).max(1.0);
+        let size = vec2(field.w, field.h) * scale;
+        let rect = Rect::new(
+            screen.x - MINIMAP_MARGIN - size.x,
+            MINIMAP_MARGIN,
+            size.x,
+            size.y,
+        );
+        Self { rect, field }
+    }
+
+    /// Screen pixels per map unit.
+    pub fn scale(&self) -> f32 {
+        self.rect.w / self.field.w.max(1.0)
+    }
+
+    pub fn to_screen(&self, pos: Vec2) -> Vec2 {
+        self.rect.point() + (pos - self.field.point()) * self.scale()
+    }
+
+    /// `view` in minimap space, clipped to the minimap.
+    pub fn view_rect(&self, view: Rect) -> Rect {
+        let min = self.to_screen(view.point()).max(self.rect.point());
+        let max = self
+            .to_screen(view.point() + view.size())
+            .min(self.rect.point() + self.rect.size());
+        Rect::new(
+            min.x,
+            min.y,
+            (max.x - min.x).max(0.0),
+            (max.y - min.y).max(0.0),
+        )
+    }
+}
+
+/// Draws the minimap with `background` (a texture and the texel rect that
+/// covers the map) under the camps, flags, hippies, sigil centers, the
+/// player at `player` and the camera `view`.
+pub fn draw_minimap(
+    minimap: &Minimap,
+    background: Option<(Texture2D, Rect)>,
+    world: &World,
+    player: Vec2,
+    view: Rect,
+) {
+    let rect = minimap.rect;
+    draw_rectangle(rect.x, rect.y, rect.w, rect.h, MINIMAP_BACKGROUND);
+    if let Some((texture, source)) = background {
+        draw_texture_ex(
+            &texture,
+            rect.x,
+            rect.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(rect.size()),
+                source: Some(source),
+                ..Default::default()
+            },
+        );
+    }
+
+    for (region, vertices) in world.camp_regions.iter().zip(&world.camp_vertices) {
+        let mut color = region.color();
+        color.a = 0.9;
+        for (index, vertex) in vertices.iter().enumerate() {
+            let next = vertices[(index + 1) % vertices.len()];
+            let (a, b) = (minimap.to_screen(*vertex), minimap.to_screen(next));
+            draw_line(a.x, a.y, b.x, b.y, 1.5, color);
+        }
+    }
+
+    for flag in world.flag_state.ground_flags() {
+        let pos = minimap.to_screen(flag.pos);
+        draw_rectangle(pos.x - 1.5, pos.y - 1.5, 3.0, 3.0, MINIMAP_FLAG_COLOR);
+    }
+
+    for hippie in &world.hippies {
+        let pos = minimap.to_screen(hippie.pos);
+        let (color, radius) = if hippie.angry {
+            (RED, 3.0)
+        } else if hippie.carried_flags > 0 {
+            (ACCENT, 3.0)
+        } else {
+            (MINIMAP_HIPPIE_COLOR, 1.5)
+        };
+        draw_circle(pos.x, pos.y, radius, color);
+    }
+
+    for sigil in &world.sigil_centers {
+        let pos = minimap.to_screen(sigil.pos);
+        let (edge, dot) = sigil.kind.colors();
+        let sides = sigil.kind.points() as u8;
+        draw_poly_lines(pos.x, pos.y, sides, 5.0, -90.0, 1.5, edge);
+        draw_circle(pos.x, pos.y, 1.5, dot);
+    }
+
+    let view = minimap.view_rect(view);
+    draw_rectangle_lines(view.x, view.y, view.w, view.h, 1.5, WHITE);
+
+    let pos = minimap.to_screen(player);
+    draw_circle(pos.x, pos.y, 3.5, BLACK);
+    draw_circle(pos.x, pos.y, 2.5, WHITE);
+
+    draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 2.0, ACCENT);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minimap_keeps_aspect_in_top_right() {
+        let minimap = Minimap::new(Rect::new(0.0, 0.0, 9216.0, 6144.0), vec2(960.0, 540.0));
+        assert_eq!(minimap.rect.w, MINIMAP_SIZE);
+        assert!((minimap.rect.h - MINIMAP_SIZE * 2.0 / 3.0).abs() < 0.001);
+        assert_eq!(minimap.rect.x + minimap.rect.w, 960.0 - MINIMAP_MARGIN);
+        assert_eq!(minimap.rect.y, MINIMAP_MARGIN);
+    }
+
+    #[test]
+    fn positions_and_view_map_into_minimap() {
+        let minimap = Minimap::new(Rect::new(0.0, 0.0, 1000.0, 500.0), vec2(800.0, 600.0));
+        assert_eq!(minimap.to_screen(vec2(0.0, 0.0)), minimap.rect.point());
+        let far = minimap.to_screen(vec2(1000.0, 500.0));
+        assert!((far - (minimap.rect.point() + minimap.rect.size())).length() < 0.001);
+
+        let view = minimap.view_rect(Rect::new(-100.0, 250.0, 600.0, 500.0));
+        assert_eq!(view.x, minimap.rect.x);
+        assert!((view.w - 500.0 * minimap.scale()).abs() < 0.001);
+        assert!((view.y + view.h - (minimap.rect.y + minimap.rect.h)).abs() < 0.001);
+    }
+}
@@ -1,6 +1,6 @@
 use macroquad::prelude::Vec2;
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct InputState {
     pub up: bool,
     pub down: bool,
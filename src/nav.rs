@@ -0,0 +1,475 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use macroquad::prelude::*;
+
+use crate::collision::{self, Collider};
+use crate::geom;
+use crate::spatial::SpatialHash;
+
+const NO_CAMP: u16 = u16::MAX;
+/// How many cells around a blocked start or goal are searched for an open
+/// one to path from or to instead.
+const SNAP_RADIUS: i32 = 6;
+
+/// Walkable cells over the field, built from the map bounds, the scenery
+/// colliders and the camp polygons. Paths are A* over the cells, then
+/// straightened wherever the straight line stays walkable.
+#[derive(Clone, Debug, Default)]
+pub struct NavGrid {
+    origin: Vec2,
+    cell: f32,
+    cols: usize,
+    rows: usize,
+    blocked: Vec<bool>,
+    camps: Vec<u16>,
+    colliders: Vec<Collider>,
+    /// Collider bounding-circle centers, so resolving a walker only tests
+    /// the colliders near it.
+    collider_index: SpatialHash,
+    max_reach: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Cell {
+    x: i32,
+    y: i32,
+}
+
+/// Open-set entry, ordered so the `BinaryHeap` pops the lowest estimate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Frontier {
+    estimate: f32,
+    cell: Cell,
+}
+
+impl Eq for Frontier {}
+
+impl Ord for Frontier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl NavGrid {
+    /// Cells of `cell` size over `field`. A cell is blocked when an agent of
+    /// `clearance` radius standing at its center would overlap a collider.
+    pub fn new(
+        field: Rect,
+        cell: f32,
+        clearance: f32,
+        colliders: Vec<Collider>,
+        camps: &[Vec<Vec2>],
+    ) -> Self {
+        let cols = (field.w / cell).ceil().max(0.0) as usize;
+        let rows = (field.h / cell).ceil().max(0.0) as usize;
+        let max_reach = colliders
+            .iter()
+            .map(|collider| collider.bounding_circle().1)
+            .fold(0.0, f32::max);
+        let mut grid = Self {
+            origin: field.point(),
+            cell,
+            cols,
+            rows,
+            blocked: vec![false; cols * rows],
+            camps: vec![NO_CAMP; cols * rows],
+            // Buckets about as wide as the biggest collider keep a query
+            // down to a few cells.
+            collider_index: SpatialHash::from_positions(
+                max_reach.max(cell),
+                colliders
+                    .iter()
+                    .map(|collider| collider.bounding_circle().0),
+            ),
+            max_reach,
+            colliders,
+        };
+        if cols == 0 || rows == 0 {
+            return grid;
+        }
+
+        for collider in &grid.colliders {
+            let (center, reach) = collider.bounding_circle();
+            let margin = Vec2::splat(reach + clearance);
+            for cell in grid.cells_between(center - margin, center + margin) {
+                let index = grid.index(cell);
+                if !grid.blocked[index] {
+                    grid.blocked[index] = collider
+                        .push_out(grid.cell_center(cell), clearance)
+                        .is_some();
+                }
+            }
+        }
+
+        for (camp_index, vertices) in camps.iter().enumerate().take(NO_CAMP as usize) {
+            let Some((min, max)) = geom::polygon_bounds(vertices) else {
+                continue;
+            };
+            for cell in grid.cells_between(min, max) {
+                let index = grid.index(cell);
+                if grid.camps[index] == NO_CAMP
+                    && geom::point_in_polygon(grid.cell_center(cell), vertices)
+                {
+                    grid.camps[index] = camp_index as u16;
+                }
+            }
+        }
+        grid
+    }
+
+    pub fn colliders(&self) -> &[Collider] {
+        &self.colliders
+    }
+
+    /// `collision::resolve_circle` against the colliders near `pos`.
+    pub fn resolve_circle(&self, pos: Vec2, radius: f32) -> Vec2 {
+        // Resolving moves the circle by up to about its radius, so gather
+        // the colliders it could reach from there too.
+        let nearby = self
+            .collider_index
+            .within(pos, self.max_reach + radius * 2.0);
+        collision::resolve_circle(
+            pos,
+            radius,
+            nearby.iter().map(|index| &self.colliders[*index]),
+        )
+    }
+
+    /// Whether an agent can stand at `pos`, optionally only inside `camp`.
+    pub fn is_walkable(&self, pos: Vec2, camp: Option<usize>) -> bool {
+        self.cell_at(pos)
+            .is_some_and(|cell| self.is_open(cell, camp))
+    }
+
+    /// Waypoints from `start` to `goal`, not including `start`. Paths with
+    /// `camp` set stay on that camp's cells. A start or goal inside an
+    /// obstacle snaps to the nearest open cell. `None` when no path is
+    /// found within `max_expansions` cells.
+    pub fn find_path(
+        &self,
+        start: Vec2,
+        goal: Vec2,
+        camp: Option<usize>,
+        max_expansions: usize,
+    ) -> Option<Vec<Vec2>> {
+        let start_cell = self.snap(self.clamped_cell(start), camp)?;
+        let goal_cell = self.snap(self.clamped_cell(goal), camp)?;
+        let cells = self.search(start_cell, goal_cell, camp, max_expansions)?;
+
+        let mut points: Vec<Vec2> = cells.iter().map(|cell| self.cell_center(*cell)).collect();
+        if self.cell_at(goal) == Some(goal_cell) {
+            *points.last_mut()? = goal;
+        }
+        Some(self.straighten(start, &points, camp))
+    }
+
+    fn search(
+        &self,
+        start: Cell,
+        goal: Cell,
+        camp: Option<usize>,
+        max_expansions: usize,
+    ) -> Option<Vec<Cell>> {
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<Cell, (Cell, f32)> = HashMap::new();
+        came_from.insert(start, (start, 0.0));
+        open.push(Frontier {
+            estimate: octile(start, goal),
+            cell: start,
+        });
+
+        let mut expanded = 0;
+        while let Some(Frontier { cell, estimate }) = open.pop() {
+            if cell == goal {
+                let mut cells = vec![goal];
+                let mut current = goal;
+                while current != start {
+                    current = came_from[&current].0;
+                    cells.push(current);
+                }
+                cells.reverse();
+                return Some(cells);
+            }
+            let cost = came_from[&cell].1;
+            if estimate > cost + octile(cell, goal) + 1.0e-3 {
+                // A cheaper route to this cell was queued after this entry.
+                continue;
+            }
+            expanded += 1;
+            if expanded > max_expansions {
+                return None;
+            }
+
+            for (dx, dy) in NEIGHBOURS {
+                let next = Cell {
+                    x: cell.x + dx,
+                    y: cell.y + dy,
+                };
+                if !self.is_open(next, camp) {
+                    continue;
+                }
+                let diagonal = dx != 0 && dy != 0;
+                // No cutting past the corner of a blocked cell.
+                if diagonal
+                    && !(self.is_open(
+                        Cell {
+                            x: next.x,
+                            y: cell.y,
+                        },
+                        camp,
+                    ) && self.is_open(
+                        Cell {
+                            x: cell.x,
+                            y: next.y,
+                        },
+                        camp,
+                    ))
+                {
+                    continue;
+                }
+                let step = if diagonal {
+                    std::f32::consts::SQRT_2
+                } else {
+                    1.0
+                };
+                let next_cost = cost + step;
+                if came_from
+                    .get(&next)
+                    .is_some_and(|(_, known)| *known <= next_cost)
+                {
+                    continue;
+                }
+                came_from.insert(next, (cell, next_cost));
+                open.push(Frontier {
+                    estimate: next_cost + octile(next, goal),
+                    cell: next,
+                });
+            }
+        }
+        None
+    }
+
+    /// Drops waypoints that can be skipped by walking straight to a later
+    /// one.
+    fn straighten(&self, start: Vec2, points: &[Vec2], camp: Option<usize>) -> Vec<Vec2> {
+        let mut path = Vec::new();
+        let mut from = start;
+        let mut index = 0;
+        while index < points.len() {
+            let mut reach = index;
+            while reach + 1 < points.len() && self.line_is_open(from, points[reach + 1], camp) {
+                reach += 1;
+            }
+            path.push(points[reach]);
+            from = points[reach];
+            index = reach + 1;
+        }
+        path
+    }
+
+    fn line_is_open(&self, from: Vec2, to: Vec2, camp: Option<usize>) -> bool {
+        let samples = (from.distance(to) / (self.cell * 0.25)).ceil() as usize;
+        (0..=samples).all(|i| {
+            let t = i as f32 / samples.max(1) as f32;
+            self.is_walkable(from.lerp(to, t), camp)
+        })
+    }
+
+    fn snap(&self, cell: Cell, camp: Option<usize>) -> Option<Cell> {
+        if self.is_open(cell, camp) {
+            return Some(cell);
+        }
+        for radius in 1..=SNAP_RADIUS {
+            let ring = (-radius..=radius).flat_map(|dy| {
+                (-radius..=radius)
+                    .filter(move |dx| dx.abs() == radius || dy.abs() == radius)
+                    .map(move |dx| Cell {
+                        x: cell.x + dx,
+                        y: cell.y + dy,
+                    })
+            });
+            let nearest = ring
+                .filter(|candidate| self.is_open(*candidate, camp))
+                .min_by(|a, b| {
+                    let da = (a.x - cell.x).pow(2) + (a.y - cell.y).pow(2);
+                    let db = (b.x - cell.x).pow(2) + (b.y - cell.y).pow(2);
+                    da.cmp(&db)
+                });
+            if nearest.is_some() {
+                return nearest;
+            }
+        }
+        None
+    }
+
+    fn is_open(&self, cell: Cell, camp: Option<usize>) -> bool {
+        if cell.x < 0 || cell.y < 0 || cell.x as usize >= self.cols || cell.y as usize >= self.rows
+        {
+            return false;
+        }
+        let index = self.index(cell);
+        !self.blocked[index] && camp.is_none_or(|camp| self.camps[index] as usize == camp)
+    }
+
+    fn index(&self, cell: Cell) -> usize {
+        cell.y as usize * self.cols + cell.x as usize
+    }
+
+    fn cell_at(&self, pos: Vec2) -> Option<Cell> {
+        let local = (pos - self.origin) / self.cell;
+        if local.x < 0.0 || local.y < 0.0 {
+            return None;
+        }
+        let cell = Cell {
+            x: local.x as i32,
+            y: local.y as i32,
+        };
+        (cell.x < self.cols as i32 && cell.y < self.rows as i32).then_some(cell)
+    }
+
+    fn clamped_cell(&self, pos: Vec2) -> Cell {
+        let local = ((pos - self.origin) / self.cell).floor();
+        Cell {
+            x: (local.x as i32).min(self.cols as i32 - 1).max(0),
+            y: (local.y as i32).min(self.rows as i32 - 1).max(0),
+        }
+    }
+
+    fn cell_center(&self, cell: Cell) -> Vec2 {
+        self.origin + (vec2(cell.x as f32, cell.y as f32) + 0.5) * self.cell
+    }
+
+    /// The cells overlapping the box from `min` to `max`, clipped to the grid.
+    fn cells_between(&self, min: Vec2, max: Vec2) -> impl Iterator<Item = Cell> {
+        let low = self.clamped_cell(min);
+        let high = self.clamped_cell(max);
+        (low.y..=high.y).flat_map(move |y| (low.x..=high.x).map(move |x| Cell { x, y }))
+    }
+}
+
+const NEIGHBOURS: [(i32, i32); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+fn octile(a: Cell, b: Cell) -> f32 {
+    let dx = (a.x - b.x).abs() as f32;
+    let dy = (a.y - b.y).abs() as f32;
+    dx.max(dy) + (std::f32::consts::SQRT_2 - 1.0) * dx.min(dy)
+}
+
+/// Moves `pos` up to `step` along `path`, dropping waypoints as they are
+/// reached.
+pub fn follow_path(pos: Vec2, path: &mut Vec<Vec2>, step: f32) -> Vec2 {
+    let mut pos = pos;
+    let mut left = step;
+    while let Some(&next) = path.first() {
+        let distance = pos.distance(next);
+        if distance > left {
+            return pos + (next - pos) / distance * left;
+        }
+        pos = next;
+        left -= distance;
+        path.remove(0);
+    }
+    pos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision::Shape;
+
+    fn wall_grid() -> NavGrid {
+        // A wall across the middle with a gap at the bottom.
+        let wall = Collider::new(Shape::Rect {
+            center: vec2(50.0, 40.0),
+            size: vec2(4.0, 80.0),
+            rotation: 0.0,
+        });
+        let camp = vec![
+            vec2(0.0, 0.0),
+            vec2(40.0, 0.0),
+            vec2(40.0, 100.0),
+            vec2(0.0, 100.0),
+        ];
+        NavGrid::new(
+            Rect::new(0.0, 0.0, 100.0, 100.0),
+            4.0,
+            2.0,
+            vec![wall],
+            &[camp],
+        )
+    }
+
+    fn path_is_walkable(grid: &NavGrid, start: Vec2, path: &[Vec2]) -> bool {
+        let mut from = start;
+        path.iter().all(|point| {
+            let open = grid.line_is_open(from, *point, None);
+            from = *point;
+            open
+        })
+    }
+
+    #[test]
+    fn paths_go_around_obstacles() {
+        let grid = wall_grid();
+        assert!(!grid.is_walkable(vec2(50.0, 20.0), None));
+        assert!(grid.is_walkable(vec2(50.0, 90.0), None));
+
+        let start = vec2(20.0, 20.0);
+        let goal = vec2(80.0, 20.0);
+        let path = grid.find_path(start, goal, None, 10_000).expect("path");
+        assert_eq!(path.last(), Some(&goal));
+        assert!(path.iter().any(|point| point.y > 80.0));
+        assert!(path_is_walkable(&grid, start, &path));
+        // Straightened: a handful of corners rather than every cell.
+        assert!(path.len() <= 4);
+    }
+
+    #[test]
+    fn camp_paths_stay_in_camp_and_searches_are_bounded() {
+        let grid = wall_grid();
+        let path = grid
+            .find_path(vec2(10.0, 10.0), vec2(30.0, 90.0), Some(0), 10_000)
+            .expect("path inside camp");
+        assert!(path.iter().all(|point| grid.is_walkable(*point, Some(0))));
+        assert_eq!(
+            grid.find_path(vec2(10.0, 10.0), vec2(45.0, 20.0), Some(0), 10_000)
+                .map(|path| grid.is_walkable(*path.last().unwrap(), Some(0))),
+            Some(true)
+        );
+        assert_eq!(
+            grid.find_path(vec2(20.0, 20.0), vec2(80.0, 20.0), None, 10),
+            None
+        );
+        assert_eq!(
+            NavGrid::default().find_path(Vec2::ZERO, Vec2::ONE, None, 10),
+            None
+        );
+    }
+
+    #[test]
+    fn follow_path_walks_through_waypoints() {
+        let mut path = vec![vec2(10.0, 0.0), vec2(10.0, 10.0)];
+        let pos = follow_path(Vec2::ZERO, &mut path, 15.0);
+        assert!((pos - vec2(10.0, 5.0)).length() < 0.001);
+        assert_eq!(path, vec![vec2(10.0, 10.0)]);
+        let pos = follow_path(pos, &mut path, 15.0);
+        assert_eq!(pos, vec2(10.0, 10.0));
+        assert!(path.is_empty());
+    }
+}
@@ -3,8 +3,12 @@ use macroquad::prelude::*;
 use crate::constants;
 use crate::flag_state;
 use crate::geom;
+use crate::nav;
 use crate::player;
+use crate::rng::Rng;
 use crate::scale;
+use crate::spatial::SpatialHash;
+use crate::tuning::HippieTuning;
 
 const HIPPIE_SPEED: f32 = 18.0 * scale::MODEL_SCALE;
 const HIPPIE_TARGET_EPSILON: f32 = 4.0 * scale::MODEL_SCALE;
@@ -32,38 +36,71 @@ pub struct Hippie {
     pub drop_check_timer: f32,
     pub ignore_flags_timer: f32,
     pub camp_index: usize,
-    target: Vec2,
-    speed: f32,
-    rng_state: u32,
+    pub(crate) target: Vec2,
+    pub(crate) speed: f32,
+    /// Nav waypoints toward `path_goal`, followed while chasing, fleeing or
+    /// heading back to camp.
+    pub(crate) path: Vec<Vec2>,
+    pub(crate) path_goal: Vec2,
+    pub(crate) repath_timer: f32,
 }
 
+/// `spatial` must index `hippies` by position.
 pub fn try_steal_flag(
     hippies: &mut [Hippie],
+    spatial: &SpatialHash,
     origin: Vec2,
     radius: f32,
     flag_state: &mut flag_state::FlagState,
+    tuning: &HippieTuning,
 ) -> bool {
-    if let Some(index) = nearest_hippie_with_flag(hippies, origin, radius) {
+    if let Some(index) = nearest_hippie_with_flag(hippies, spatial, origin, radius) {
         if !flag_state.steal_from_hippie(&mut hippies[index].carried_flags) {
             return false;
         }
         hippies[index].angry = true;
-        hippies[index].anger_timer = constants::HIPPIE_ANGER_DURATION;
-        hippies[index].anger_delay = constants::HIPPIE_ANGER_DELAY;
+        hippies[index].anger_timer = tuning.anger_duration;
+        hippies[index].anger_delay = tuning.anger_delay;
         hippies[index].steal_cooldown = 0.0;
         return true;
     }
     false
 }
 
-pub fn spawn_hippies(positions: &[Vec2], camp_index: usize, camp_vertices: &[Vec2]) -> Vec<Hippie> {
+/// Calms every angry hippie within `radius` of `origin` and returns how
+/// many there were. `spatial` must index `hippies` by position.
+pub fn calm_hippies(
+    hippies: &mut [Hippie],
+    spatial: &SpatialHash,
+    origin: Vec2,
+    radius: f32,
+) -> usize {
+    let mut calmed = 0;
+    for i in spatial.within(origin, radius) {
+        let hippie = &mut hippies[i];
+        if !hippie.angry {
+            continue;
+        }
+        hippie.angry = false;
+        hippie.anger_timer = 0.0;
+        hippie.anger_delay = 0.0;
+        calmed += 1;
+    }
+    calmed
+}
+
+pub fn spawn_hippies(
+    positions: &[Vec2],
+    camp_index: usize,
+    camp_vertices: &[Vec2],
+    rng: &mut Rng,
+    tuning: &HippieTuning,
+) -> Vec<Hippie> {
     positions
         .iter()
-        .enumerate()
-        .map(|(i, &pos)| {
-            let mut rng_state = hash_seed(pos, i as u32);
-            let carried_flags = initial_carried_flags(&mut rng_state);
-            let target = random_point_in_polygon(camp_vertices, &mut rng_state);
+        .map(|&pos| {
+            let carried_flags = initial_carried_flags(rng, tuning);
+            let target = random_point_in_polygon(camp_vertices, rng);
             Hippie {
                 pos,
                 facing: player::Facing::Down,
@@ -73,12 +110,14 @@ pub fn spawn_hippies(positions: &[Vec2], camp_index: usize, camp_vertices: &[Vec
                 anger_delay: 0.0,
                 steal_cooldown: 0.0,
                 flee_timer: 0.0,
-                drop_check_timer: next_f32(&mut rng_state) * constants::HIPPIE_FLAG_DROP_INTERVAL,
+                drop_check_timer: rng.next_f32() * tuning.flag_drop_interval,
                 ignore_flags_timer: 0.0,
                 camp_index,
                 target,
                 speed: HIPPIE_SPEED,
-                rng_state,
+                path: Vec::new(),
+                path_goal: Vec2::ZERO,
+                repath_timer: 0.0,
             }
         })
         .collect()
@@ -88,13 +127,13 @@ pub fn spawn_hippies_with_flags(
     spawns: &[(Vec2, u8)],
     camp_index: usize,
     camp_vertices: &[Vec2],
+    rng: &mut Rng,
+    tuning: &HippieTuning,
 ) -> Vec<Hippie> {
     spawns
         .iter()
-        .enumerate()
-        .map(|(i, &(pos, carried))| {
-            let mut rng_state = hash_seed(pos, i as u32);
-            let target = random_point_in_polygon(camp_vertices, &mut rng_state);
+        .map(|&(pos, carried)| {
+            let target = random_point_in_polygon(camp_vertices, rng);
             Hippie {
                 pos,
                 facing: player::Facing::Down,
@@ -104,17 +143,20 @@ pub fn spawn_hippies_with_flags(
                 anger_delay: 0.0,
                 steal_cooldown: 0.0,
                 flee_timer: 0.0,
-                drop_check_timer: next_f32(&mut rng_state) * constants::HIPPIE_FLAG_DROP_INTERVAL,
+                drop_check_timer: rng.next_f32() * tuning.flag_drop_interval,
                 ignore_flags_timer: 0.0,
                 camp_index,
                 target,
                 speed: HIPPIE_SPEED,
-                rng_state,
+                path: Vec::new(),
+                path_goal: Vec2::ZERO,
+                repath_timer: 0.0,
             }
         })
         .collect()
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn update_hippies(
     hippies: &mut [Hippie],
     dt: f32,
@@ -122,6 +164,9 @@ pub fn update_hippies(
     flag_state: &mut flag_state::FlagState,
     player_pos: Vec2,
     player_speed: f32,
+    rng: &mut Rng,
+    tuning: &HippieTuning,
+    nav: &nav::NavGrid,
 ) -> bool {
     let mut picked_any = false;
     let player_has_flags = flag_state.player_inventory() > 0;
@@ -131,7 +176,7 @@ pub fn update_hippies(
     for hippie in hippies.iter_mut() {
         let camp = camp_for_index(camp_vertices, hippie.camp_index);
         let inside_camp = geom::point_in_polygon(hippie.pos, camp);
-        update_hippie_drop(hippie, dt, flag_state);
+        update_hippie_drop(hippie, dt, flag_state, rng, tuning);
 
         if hippie.ignore_flags_timer <= 0.0 && hippie.carried_flags < HIPPIE_FLAG_CAPACITY {
             picked_any |= flag_state.transfer_ground_to_hippie(
@@ -142,21 +187,21 @@ pub fn update_hippies(
             );
         }
 
-        update_hippie_anger(hippie, player_pos, player_has_flags, dt);
+        update_hippie_anger(hippie, player_pos, player_has_flags, dt, tuning);
         update_hippie_flee(hippie, dt);
         let angry = hippie.angry;
 
         if angry && hippie.anger_delay <= 0.0 {
-            steal_from_player(hippie, player_pos, flag_state, dt);
+            steal_from_player(hippie, player_pos, flag_state, dt, tuning);
         }
 
         if !angry && hippie.flee_timer <= 0.0 {
             if inside_camp {
                 if hippie.pos.distance(hippie.target) <= HIPPIE_TARGET_EPSILON {
-                    hippie.target = random_point_in_polygon(camp, &mut hippie.rng_state);
+                    hippie.target = random_point_in_polygon(camp, rng);
                 }
             } else if !geom::point_in_polygon(hippie.target, camp) {
-                hippie.target = random_point_in_polygon(camp, &mut hippie.rng_state);
+                hippie.target = random_point_in_polygon(camp, rng);
             }
         }
 
@@ -173,15 +218,18 @@ pub fn update_hippies(
         }
 
         let speed = if angry {
-            chase_speed(player_speed)
+            chase_speed(player_speed, tuning)
         } else {
             hippie.speed
         };
         let step = speed * dt;
-        let next_pos = if to_target.length() <= step || step <= 0.0 {
-            target
+        // Wandering stays a straight walk inside the camp; crossing open
+        // ground follows the nav grid around scenery.
+        let next_pos = if angry || hippie.flee_timer > 0.0 || !inside_camp {
+            path_step(hippie, target, step, dt, nav, tuning.collision_radius)
         } else {
-            hippie.pos + to_target.normalize() * step
+            hippie.path.clear();
+            straight_step(hippie.pos, target, step)
         };
 
         let desired = if angry || !inside_camp {
@@ -190,7 +238,7 @@ pub fn update_hippies(
             next_pos
         } else {
             if hippie.flee_timer <= 0.0 {
-                hippie.target = random_point_in_polygon(camp, &mut hippie.rng_state);
+                hippie.target = random_point_in_polygon(camp, rng);
             }
             hippie.pos
         };
@@ -200,16 +248,20 @@ pub fn update_hippies(
         angry_flags.push(angry);
     }
 
-    resolve_hippie_collisions(
-        &mut desired_positions,
-        constants::HIPPIE_COLLISION_RADIUS * 2.0,
-    );
+    resolve_hippie_collisions(&mut desired_positions, tuning.collision_radius * 2.0);
 
     for (idx, hippie) in hippies.iter_mut().enumerate() {
         let camp = camp_for_index(camp_vertices, hippie.camp_index);
-        let desired = desired_positions[idx];
+        let wanted = desired_positions[idx];
+        let desired = resolve_obstacles(wanted, tuning.collision_radius, nav);
         let inside_camp = inside_camps[idx];
         let angry = angry_flags[idx];
+        // Wandering into scenery: pick somewhere else rather than pushing
+        // against it until the next target.
+        let blocked = hippie.pos.distance(desired) < hippie.pos.distance(wanted) * 0.5;
+        if blocked && !angry && hippie.flee_timer <= 0.0 {
+            hippie.target = random_point_in_polygon(camp, rng);
+        }
         if !angry && inside_camp && !geom::point_in_polygon(desired, camp) {
             continue;
         }
@@ -218,14 +270,11 @@ pub fn update_hippies(
     picked_any
 }
 
-pub fn draw_hippies(hippies: &[Hippie]) {
-    for hippie in hippies {
-        draw_hippie(
-            hippie.pos,
-            hippie.facing,
-            hippie.carried_flags,
-            hippie.angry,
-        );
+/// Draws each hippie at the matching entry of `positions`, so rendering can
+/// place them between simulation steps.
+pub fn draw_hippies(hippies: &[Hippie], positions: &[Vec2]) {
+    for (hippie, &pos) in hippies.iter().zip(positions) {
+        draw_hippie(pos, hippie.facing, hippie.carried_flags, hippie.angry);
     }
 }
 
@@ -400,21 +449,27 @@ fn rotate_vec(point: Vec2, angle: f32) -> Vec2 {
     )
 }
 
-fn update_hippie_drop(hippie: &mut Hippie, dt: f32, flag_state: &mut flag_state::FlagState) {
+fn update_hippie_drop(
+    hippie: &mut Hippie,
+    dt: f32,
+    flag_state: &mut flag_state::FlagState,
+    rng: &mut Rng,
+    tuning: &HippieTuning,
+) {
     if hippie.ignore_flags_timer > 0.0 {
         hippie.ignore_flags_timer = (hippie.ignore_flags_timer - dt).max(0.0);
     }
 
     hippie.drop_check_timer -= dt;
     while hippie.drop_check_timer <= 0.0 {
-        hippie.drop_check_timer += constants::HIPPIE_FLAG_DROP_INTERVAL;
+        hippie.drop_check_timer += tuning.flag_drop_interval;
         if hippie.carried_flags == 0 {
             continue;
         }
-        let roll = next_f32(&mut hippie.rng_state);
-        if roll <= constants::HIPPIE_FLAG_DROP_CHANCE {
+        let roll = rng.next_f32();
+        if roll <= tuning.flag_drop_chance {
             flag_state.drop_from_hippie(&mut hippie.carried_flags, 1, hippie.pos);
-            hippie.ignore_flags_timer = constants::HIPPIE_FLAG_IGNORE_DURATION;
+            hippie.ignore_flags_timer = tuning.flag_ignore_duration;
         }
     }
 }
@@ -424,6 +479,7 @@ fn steal_from_player(
     player_pos: Vec2,
     flag_state: &mut flag_state::FlagState,
     dt: f32,
+    tuning: &HippieTuning,
 ) {
     if hippie.steal_cooldown > 0.0 {
         hippie.steal_cooldown = (hippie.steal_cooldown - dt).max(0.0);
@@ -433,7 +489,7 @@ fn steal_from_player(
         return;
     }
 
-    if hippie.pos.distance(player_pos) > constants::HIPPIE_STEAL_BACK_RADIUS {
+    if hippie.pos.distance(player_pos) > tuning.steal_back_radius {
         return;
     }
 
@@ -445,15 +501,21 @@ fn steal_from_player(
     );
 
     if stolen > 0 {
-        hippie.steal_cooldown = constants::HIPPIE_STEAL_COOLDOWN;
+        hippie.steal_cooldown = tuning.steal_cooldown;
         hippie.angry = false;
         hippie.anger_timer = 0.0;
         hippie.anger_delay = 0.0;
-        hippie.flee_timer = constants::HIPPIE_FLEE_DURATION;
+        hippie.flee_timer = tuning.flee_duration;
     }
 }
 
-fn update_hippie_anger(hippie: &mut Hippie, player_pos: Vec2, player_has_flags: bool, dt: f32) {
+fn update_hippie_anger(
+    hippie: &mut Hippie,
+    player_pos: Vec2,
+    player_has_flags: bool,
+    dt: f32,
+    tuning: &HippieTuning,
+) {
     if !hippie.angry {
         return;
     }
@@ -474,7 +536,7 @@ fn update_hippie_anger(hippie: &mut Hippie, player_pos: Vec2, player_has_flags:
     }
 
     if hippie.anger_timer <= 0.0 {
-        let close = hippie.pos.distance(player_pos) <= constants::HIPPIE_ANGER_RADIUS;
+        let close = hippie.pos.distance(player_pos) <= tuning.anger_radius;
         if !close {
             hippie.angry = false;
         }
@@ -508,8 +570,61 @@ fn angry_head_color(time: f32) -> Color {
     )
 }
 
-fn chase_speed(player_speed: f32) -> f32 {
-    player_speed * constants::HIPPIE_CHASE_SPEED_FACTOR
+fn chase_speed(player_speed: f32, tuning: &HippieTuning) -> f32 {
+    player_speed * tuning.chase_speed_factor
+}
+
+fn straight_step(pos: Vec2, target: Vec2, step: f32) -> Vec2 {
+    let to_target = target - pos;
+    if to_target.length() <= step || step <= 0.0 {
+        target
+    } else {
+        pos + to_target.normalize() * step
+    }
+}
+
+/// Steps along a nav path to `target`, planning again every
+/// `NAV_REPATH_INTERVAL` or once the target has wandered off the end of the
+/// path. Falls back to a straight step when no path is found.
+fn path_step(
+    hippie: &mut Hippie,
+    target: Vec2,
+    step: f32,
+    dt: f32,
+    nav: &nav::NavGrid,
+    radius: f32,
+) -> Vec2 {
+    let feet = feet_offset(radius);
+    hippie.repath_timer -= dt;
+    if hippie.repath_timer <= 0.0
+        || hippie.path_goal.distance(target) > constants::NAV_REPATH_DISTANCE
+    {
+        hippie.repath_timer = constants::NAV_REPATH_INTERVAL;
+        hippie.path_goal = target;
+        hippie.path = nav
+            .find_path(
+                hippie.pos + feet,
+                target + feet,
+                None,
+                constants::NAV_MAX_EXPANSIONS,
+            )
+            .unwrap_or_default();
+    }
+    if hippie.path.is_empty() {
+        return straight_step(hippie.pos, target, step);
+    }
+    nav::follow_path(hippie.pos + feet, &mut hippie.path, step) - feet
+}
+
+/// From a hippie's position to the center of the circle its feet take up.
+fn feet_offset(radius: f32) -> Vec2 {
+    vec2(0.0, HIPPIE_BODY_LENGTH * 0.5 + HIPPIE_LEG_LENGTH - radius)
+}
+
+/// Keeps a hippie's feet, a circle of `radius`, out of the scenery.
+fn resolve_obstacles(pos: Vec2, radius: f32, nav: &nav::NavGrid) -> Vec2 {
+    let feet = feet_offset(radius);
+    nav.resolve_circle(pos + feet, radius) - feet
 }
 
 fn resolve_hippie_collisions(positions: &mut [Vec2], min_distance: f32) {
@@ -518,9 +633,15 @@ fn resolve_hippie_collisions(positions: &mut [Vec2], min_distance: f32) {
     }
 
     let min_sq = min_distance * min_distance;
+    // Pushes move hippies while a pass runs, so neighbors are gathered with
+    // some slack and the index follows every push.
+    let mut spatial = SpatialHash::from_positions(min_distance * 2.0, positions.iter().copied());
     for _ in 0..2 {
         for i in 0..positions.len() {
-            for j in (i + 1)..positions.len() {
+            for j in spatial.within(positions[i], min_distance * 2.0) {
+                if j <= i {
+                    continue;
+                }
                 let delta = positions[i] - positions[j];
                 let dist_sq = delta.length_squared();
                 if dist_sq >= min_sq {
@@ -541,15 +662,23 @@ fn resolve_hippie_collisions(positions: &mut [Vec2], min_distance: f32) {
 
                 positions[i] += dir * push;
                 positions[j] -= dir * push;
+                spatial.set_position(i, positions[i]);
+                spatial.set_position(j, positions[j]);
             }
         }
     }
 }
 
-fn nearest_hippie_with_flag(hippies: &[Hippie], origin: Vec2, radius: f32) -> Option<usize> {
+fn nearest_hippie_with_flag(
+    hippies: &[Hippie],
+    spatial: &SpatialHash,
+    origin: Vec2,
+    radius: f32,
+) -> Option<usize> {
     let mut best = None;
     let mut best_d2 = radius * radius;
-    for (i, hippie) in hippies.iter().enumerate() {
+    for i in spatial.within(origin, radius) {
+        let hippie = &hippies[i];
         if hippie.carried_flags == 0 {
             continue;
         }
@@ -562,13 +691,13 @@ fn nearest_hippie_with_flag(hippies: &[Hippie], origin: Vec2, radius: f32) -> Op
     best
 }
 
-pub(crate) fn random_point_in_polygon(vertices: &[Vec2], rng_state: &mut u32) -> Vec2 {
+pub fn random_point_in_polygon(vertices: &[Vec2], rng: &mut Rng) -> Vec2 {
     let Some((min, max)) = geom::polygon_bounds(vertices) else {
         return Vec2::ZERO;
     };
     for _ in 0..HIPPIE_BOUNDS_ATTEMPTS {
-        let x = lerp(min.x, max.x, next_f32(rng_state));
-        let y = lerp(min.y, max.y, next_f32(rng_state));
+        let x = lerp(min.x, max.x, rng.next_f32());
+        let y = lerp(min.y, max.y, rng.next_f32());
         let candidate = vec2(x, y);
         if geom::point_in_polygon(candidate, vertices) {
             return candidate;
@@ -577,13 +706,13 @@ pub(crate) fn random_point_in_polygon(vertices: &[Vec2], rng_state: &mut u32) ->
     vertices[0]
 }
 
-fn initial_carried_flags(rng_state: &mut u32) -> u8 {
-    carried_flags_from_roll(next_f32(rng_state))
+fn initial_carried_flags(rng: &mut Rng, tuning: &HippieTuning) -> u8 {
+    carried_flags_from_roll(rng.next_f32(), tuning)
 }
 
-fn carried_flags_from_roll(roll: f32) -> u8 {
-    let two = constants::HIPPIE_START_TWO_FLAG_CHANCE;
-    let one = constants::HIPPIE_START_ONE_FLAG_CHANCE;
+fn carried_flags_from_roll(roll: f32, tuning: &HippieTuning) -> u8 {
+    let two = tuning.start_two_flag_chance;
+    let one = tuning.start_one_flag_chance;
     if roll < two {
         2
     } else if roll < two + one {
@@ -603,18 +732,6 @@ fn camp_for_index<'a>(camps: &'a [Vec<Vec2>], index: usize) -> &'a [Vec2] {
         .unwrap_or_else(|| camps[0].as_slice())
 }
 
-fn next_f32(state: &mut u32) -> f32 {
-    *state = state.wrapping_mul(1664525).wrapping_add(1013904223);
-    let v = (*state >> 8) as f32;
-    v / ((u32::MAX >> 8) as f32 + 1.0)
-}
-
-fn hash_seed(pos: Vec2, index: u32) -> u32 {
-    let x = (pos.x * 10.0).to_bits();
-    let y = (pos.y * 10.0).to_bits();
-    x ^ y ^ index.rotate_left(13)
-}
-
 fn lerp(a: f32, b: f32, t: f32) -> f32 {
     a + (b - a) * t.clamp(0.0, 1.0)
 }
@@ -645,7 +762,7 @@ mod tests {
             vec2(10.0, 10.0),
             vec2(0.0, 10.0),
         ];
-        let mut rng = 1u32;
+        let mut rng = Rng::new(1);
         for _ in 0..32 {
             let p = random_point_in_polygon(&square, &mut rng);
             assert!(geom::point_in_polygon(p, &square));
@@ -654,9 +771,9 @@ mod tests {
 
     #[test]
     fn carried_flags_roll_respects_chances() {
-        assert_eq!(carried_flags_from_roll(0.05), 2);
-        assert_eq!(carried_flags_from_roll(0.2), 1);
-        assert_eq!(carried_flags_from_roll(0.9), 0);
+        assert_eq!(carried_flags_from_roll(0.05, &HippieTuning::default()), 2);
+        assert_eq!(carried_flags_from_roll(0.2, &HippieTuning::default()), 1);
+        assert_eq!(carried_flags_from_roll(0.9, &HippieTuning::default()), 0);
     }
 
     #[test]
@@ -667,7 +784,13 @@ mod tests {
             vec2(20.0, 20.0),
             vec2(0.0, 20.0),
         ];
-        let mut hippies = spawn_hippies_with_flags(&[(vec2(10.0, 10.0), 0)], 0, &square);
+        let mut hippies = spawn_hippies_with_flags(
+            &[(vec2(10.0, 10.0), 0)],
+            0,
+            &square,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+        );
         let mut flag_state = FlagState::new(Vec::new(), 0, 0);
         let camps = vec![square.clone()];
         for _ in 0..60 {
@@ -678,6 +801,9 @@ mod tests {
                 &mut flag_state,
                 vec2(50.0, 50.0),
                 100.0,
+                &mut Rng::new(1),
+                &HippieTuning::default(),
+                &nav::NavGrid::default(),
             );
             assert!(geom::point_in_polygon(hippies[0].pos, &square));
         }
@@ -691,11 +817,22 @@ mod tests {
             vec2(20.0, 20.0),
             vec2(0.0, 20.0),
         ];
-        let mut hippies = spawn_hippies_with_flags(&[(vec2(5.0, 5.0), 1)], 0, &square);
+        let mut hippies = spawn_hippies_with_flags(
+            &[(vec2(5.0, 5.0), 1)],
+            0,
+            &square,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+        );
         hippies[0].drop_check_timer = 0.0;
-        hippies[0].rng_state = 0;
         let mut flag_state = FlagState::new(Vec::new(), 0, 1);
-        update_hippie_drop(&mut hippies[0], 0.1, &mut flag_state);
+        update_hippie_drop(
+            &mut hippies[0],
+            0.1,
+            &mut flag_state,
+            &mut Rng::new(0),
+            &HippieTuning::default(),
+        );
         assert_eq!(hippies[0].carried_flags, 0);
         assert_eq!(flag_state.ground_flags().len(), 1);
         assert!(
@@ -711,7 +848,13 @@ mod tests {
             vec2(20.0, 20.0),
             vec2(0.0, 20.0),
         ];
-        let mut hippies = spawn_hippies_with_flags(&[(vec2(5.0, 5.0), 0)], 0, &square);
+        let mut hippies = spawn_hippies_with_flags(
+            &[(vec2(5.0, 5.0), 0)],
+            0,
+            &square,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+        );
         hippies[0].ignore_flags_timer = constants::HIPPIE_FLAG_IGNORE_DURATION;
         let mut flag_state = FlagState::new(
             vec![flags::Flag {
@@ -729,6 +872,9 @@ mod tests {
             &mut flag_state,
             vec2(0.0, 0.0),
             100.0,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+            &nav::NavGrid::default(),
         );
         assert_eq!(flag_state.ground_flags().len(), 1);
         assert_eq!(hippies[0].carried_flags, 0);
@@ -742,7 +888,13 @@ mod tests {
             vec2(20.0, 20.0),
             vec2(0.0, 20.0),
         ];
-        let mut hippies = spawn_hippies_with_flags(&[(vec2(10.0, 10.0), 0)], 0, &square);
+        let mut hippies = spawn_hippies_with_flags(
+            &[(vec2(10.0, 10.0), 0)],
+            0,
+            &square,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+        );
         let flags = vec![
             flags::Flag {
                 pos: vec2(10.0, 11.0),
@@ -766,6 +918,9 @@ mod tests {
             &mut flag_state,
             vec2(0.0, 0.0),
             100.0,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+            &nav::NavGrid::default(),
         );
         assert!(picked);
         assert_eq!(hippies[0].carried_flags, 2);
@@ -780,7 +935,13 @@ mod tests {
             vec2(20.0, 20.0),
             vec2(0.0, 20.0),
         ];
-        let mut hippies = spawn_hippies_with_flags(&[(vec2(10.0, 10.0), 0)], 0, &square);
+        let mut hippies = spawn_hippies_with_flags(
+            &[(vec2(10.0, 10.0), 0)],
+            0,
+            &square,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+        );
         hippies[0].carried_flags = HIPPIE_FLAG_CAPACITY;
         let mut flag_state = FlagState::new(
             vec![flags::Flag {
@@ -798,6 +959,9 @@ mod tests {
             &mut flag_state,
             vec2(0.0, 0.0),
             100.0,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+            &nav::NavGrid::default(),
         );
         assert!(!picked);
         assert_eq!(flag_state.ground_flags().len(), 1);
@@ -811,7 +975,13 @@ mod tests {
             vec2(20.0, 20.0),
             vec2(0.0, 20.0),
         ];
-        let hippies = spawn_hippies_with_flags(&[(vec2(5.0, 5.0), 5)], 0, &square);
+        let hippies = spawn_hippies_with_flags(
+            &[(vec2(5.0, 5.0), 5)],
+            0,
+            &square,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+        );
         assert_eq!(hippies[0].carried_flags, HIPPIE_FLAG_CAPACITY);
     }
 
@@ -853,7 +1023,9 @@ mod tests {
                 camp_index: 0,
                 target: vec2(0.0, 0.0),
                 speed: HIPPIE_SPEED,
-                rng_state: 1,
+                path: Vec::new(),
+                path_goal: Vec2::ZERO,
+                repath_timer: 0.0,
             },
             Hippie {
                 pos: vec2(3.0, 0.0),
@@ -869,12 +1041,22 @@ mod tests {
                 camp_index: 0,
                 target: vec2(0.0, 0.0),
                 speed: HIPPIE_SPEED,
-                rng_state: 2,
+                path: Vec::new(),
+                path_goal: Vec2::ZERO,
+                repath_timer: 0.0,
             },
         ];
 
         let mut flag_state = FlagState::new(Vec::new(), 0, 3);
-        let stolen = try_steal_flag(&mut hippies, vec2(2.5, 0.0), 4.0, &mut flag_state);
+        let index = SpatialHash::from_positions(10.0, hippies.iter().map(|hippie| hippie.pos));
+        let stolen = try_steal_flag(
+            &mut hippies,
+            &index,
+            vec2(2.5, 0.0),
+            4.0,
+            &mut flag_state,
+            &HippieTuning::default(),
+        );
         assert!(stolen);
         assert_eq!(hippies[1].carried_flags, 1);
         assert_eq!(hippies[0].carried_flags, 1);
@@ -899,15 +1081,58 @@ mod tests {
             camp_index: 0,
             target: vec2(0.0, 0.0),
             speed: HIPPIE_SPEED,
-            rng_state: 1,
+            path: Vec::new(),
+            path_goal: Vec2::ZERO,
+            repath_timer: 0.0,
         }];
 
         let mut flag_state = FlagState::new(Vec::new(), 0, 0);
-        let stolen = try_steal_flag(&mut hippies, vec2(0.0, 0.0), 4.0, &mut flag_state);
+        let index = SpatialHash::from_positions(10.0, hippies.iter().map(|hippie| hippie.pos));
+        let stolen = try_steal_flag(
+            &mut hippies,
+            &index,
+            vec2(0.0, 0.0),
+            4.0,
+            &mut flag_state,
+            &HippieTuning::default(),
+        );
         assert!(!stolen);
         assert_eq!(flag_state.player_inventory(), 0);
     }
 
+    #[test]
+    fn calm_only_reaches_angry_hippies_in_range() {
+        let square = vec![
+            vec2(0.0, 0.0),
+            vec2(200.0, 0.0),
+            vec2(200.0, 200.0),
+            vec2(0.0, 200.0),
+        ];
+        let mut hippies = spawn_hippies_with_flags(
+            &[
+                (vec2(10.0, 10.0), 0),
+                (vec2(20.0, 10.0), 0),
+                (vec2(150.0, 150.0), 0),
+            ],
+            0,
+            &square,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+        );
+        hippies[0].angry = true;
+        hippies[0].anger_timer = 5.0;
+        hippies[2].angry = true;
+
+        let index = SpatialHash::from_positions(50.0, hippies.iter().map(|hippie| hippie.pos));
+        assert_eq!(
+            calm_hippies(&mut hippies, &index, vec2(15.0, 10.0), 30.0),
+            1
+        );
+        assert!(!hippies[0].angry);
+        assert_eq!(hippies[0].anger_timer, 0.0);
+        assert!(hippies[2].angry);
+    }
+
     #[test]
     fn anger_clears_when_timer_elapsed_and_far() {
         let square = vec![
@@ -916,7 +1141,13 @@ mod tests {
             vec2(20.0, 20.0),
             vec2(0.0, 20.0),
         ];
-        let mut hippies = spawn_hippies_with_flags(&[(vec2(5.0, 5.0), 0)], 0, &square);
+        let mut hippies = spawn_hippies_with_flags(
+            &[(vec2(5.0, 5.0), 0)],
+            0,
+            &square,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+        );
         hippies[0].angry = true;
         hippies[0].anger_timer = 0.0;
         hippies[0].flee_timer = 0.0;
@@ -932,6 +1163,9 @@ mod tests {
             &mut flag_state,
             vec2(100.0, 100.0),
             100.0,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+            &nav::NavGrid::default(),
         );
         assert!(!hippies[0].angry);
     }
@@ -944,7 +1178,13 @@ mod tests {
             vec2(20.0, 20.0),
             vec2(0.0, 20.0),
         ];
-        let mut hippies = spawn_hippies_with_flags(&[(vec2(5.0, 5.0), 0)], 0, &square);
+        let mut hippies = spawn_hippies_with_flags(
+            &[(vec2(5.0, 5.0), 0)],
+            0,
+            &square,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+        );
         hippies[0].angry = true;
         hippies[0].anger_timer = 0.0;
         hippies[0].flee_timer = 0.0;
@@ -960,6 +1200,9 @@ mod tests {
             &mut flag_state,
             vec2(12.0, 12.0),
             100.0,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+            &nav::NavGrid::default(),
         );
         assert!(hippies[0].angry);
     }
@@ -972,7 +1215,13 @@ mod tests {
             vec2(20.0, 20.0),
             vec2(0.0, 20.0),
         ];
-        let mut hippies = spawn_hippies_with_flags(&[(vec2(5.0, 5.0), 0)], 0, &square);
+        let mut hippies = spawn_hippies_with_flags(
+            &[(vec2(5.0, 5.0), 0)],
+            0,
+            &square,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+        );
         hippies[0].angry = true;
         hippies[0].anger_timer = constants::HIPPIE_ANGER_DURATION;
         hippies[0].flee_timer = 0.0;
@@ -988,6 +1237,9 @@ mod tests {
             &mut flag_state,
             vec2(6.0, 6.0),
             100.0,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+            &nav::NavGrid::default(),
         );
         assert!(!hippies[0].angry);
         assert_eq!(hippies[0].anger_timer, 0.0);
@@ -1010,7 +1262,13 @@ mod tests {
             vec2(20.0, 20.0),
             vec2(0.0, 20.0),
         ];
-        let mut hippies = spawn_hippies_with_flags(&[(vec2(5.0, 5.0), 0)], 0, &square);
+        let mut hippies = spawn_hippies_with_flags(
+            &[(vec2(5.0, 5.0), 0)],
+            0,
+            &square,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+        );
         hippies[0].angry = true;
         hippies[0].anger_timer = constants::HIPPIE_ANGER_DURATION;
         hippies[0].flee_timer = 0.0;
@@ -1028,6 +1286,9 @@ mod tests {
             &mut flag_state,
             vec2(5.0, 5.0),
             100.0,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+            &nav::NavGrid::default(),
         );
         assert_eq!(flag_state.player_inventory(), 1);
         assert_eq!(hippies[0].carried_flags, 2);
@@ -1047,7 +1308,13 @@ mod tests {
             vec2(20.0, 20.0),
             vec2(0.0, 20.0),
         ];
-        let mut hippies = spawn_hippies_with_flags(&[(vec2(5.0, 5.0), 2)], 0, &square);
+        let mut hippies = spawn_hippies_with_flags(
+            &[(vec2(5.0, 5.0), 2)],
+            0,
+            &square,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+        );
         hippies[0].angry = true;
         hippies[0].anger_timer = constants::HIPPIE_ANGER_DURATION;
         let mut flag_state = FlagState::new(Vec::new(), 2, 4);
@@ -1061,6 +1328,9 @@ mod tests {
             &mut flag_state,
             vec2(5.0, 5.0),
             100.0,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+            &nav::NavGrid::default(),
         );
         assert_eq!(flag_state.player_inventory(), 0);
         assert_eq!(hippies[0].carried_flags, 2);
@@ -1078,7 +1348,13 @@ mod tests {
             vec2(20.0, 20.0),
             vec2(0.0, 20.0),
         ];
-        let mut hippies = spawn_hippies_with_flags(&[(vec2(5.0, 5.0), 0)], 0, &square);
+        let mut hippies = spawn_hippies_with_flags(
+            &[(vec2(5.0, 5.0), 0)],
+            0,
+            &square,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+        );
         hippies[0].angry = true;
         hippies[0].anger_timer = constants::HIPPIE_ANGER_DURATION;
         hippies[0].flee_timer = 0.0;
@@ -1094,6 +1370,9 @@ mod tests {
             &mut flag_state,
             vec2(5.0, 5.0),
             100.0,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+            &nav::NavGrid::default(),
         );
         let after_first = flag_state.player_inventory();
         update_hippies(
@@ -1103,13 +1382,16 @@ mod tests {
             &mut flag_state,
             vec2(5.0, 5.0),
             100.0,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+            &nav::NavGrid::default(),
         );
         assert_eq!(flag_state.player_inventory(), after_first);
     }
 
     #[test]
     fn chase_speed_uses_player_speed_factor() {
-        let speed = chase_speed(100.0);
+        let speed = chase_speed(100.0, &HippieTuning::default());
         assert!((speed - 100.0 * constants::HIPPIE_CHASE_SPEED_FACTOR).abs() < 1e-6);
     }
 
@@ -1121,7 +1403,13 @@ mod tests {
             vec2(200.0, 200.0),
             vec2(0.0, 200.0),
         ];
-        let mut hippies = spawn_hippies_with_flags(&[(vec2(50.0, 50.0), 0)], 0, &square);
+        let mut hippies = spawn_hippies_with_flags(
+            &[(vec2(50.0, 50.0), 0)],
+            0,
+            &square,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+        );
         hippies[0].angry = true;
         hippies[0].anger_timer = constants::HIPPIE_ANGER_DURATION;
         hippies[0].anger_delay = 0.0;
@@ -1140,6 +1428,9 @@ mod tests {
             &mut flag_state,
             player_pos,
             1000.0,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+            &nav::NavGrid::default(),
         );
 
         let dist_to_player = hippies[0].pos.distance(player_pos);
@@ -1155,7 +1446,13 @@ mod tests {
             vec2(10.0, 10.0),
             vec2(0.0, 10.0),
         ];
-        let mut hippies = spawn_hippies_with_flags(&[(vec2(9.0, 5.0), 0)], 0, &camp);
+        let mut hippies = spawn_hippies_with_flags(
+            &[(vec2(9.0, 5.0), 0)],
+            0,
+            &camp,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+        );
         hippies[0].angry = true;
         hippies[0].anger_timer = constants::HIPPIE_ANGER_DURATION;
         hippies[0].anger_delay = 0.0;
@@ -1172,6 +1469,9 @@ mod tests {
             &mut flag_state,
             vec2(30.0, 5.0),
             100.0,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+            &nav::NavGrid::default(),
         );
 
         assert!(!geom::point_in_polygon(hippies[0].pos, &camp));
@@ -1185,7 +1485,13 @@ mod tests {
             vec2(10.0, 10.0),
             vec2(0.0, 10.0),
         ];
-        let mut hippies = spawn_hippies_with_flags(&[(vec2(15.0, 5.0), 0)], 0, &camp);
+        let mut hippies = spawn_hippies_with_flags(
+            &[(vec2(15.0, 5.0), 0)],
+            0,
+            &camp,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+        );
         hippies[0].angry = false;
         hippies[0].anger_timer = 0.0;
         hippies[0].anger_delay = 0.0;
@@ -1204,6 +1510,9 @@ mod tests {
             &mut flag_state,
             vec2(100.0, 100.0),
             100.0,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+            &nav::NavGrid::default(),
         );
         let after = hippies[0].pos.distance(hippies[0].target);
         assert!(after < before);
@@ -1217,8 +1526,13 @@ mod tests {
             vec2(200.0, 200.0),
             vec2(0.0, 200.0),
         ];
-        let mut hippies =
-            spawn_hippies_with_flags(&[(vec2(40.0, 50.0), 0), (vec2(60.0, 50.0), 0)], 0, &camp);
+        let mut hippies = spawn_hippies_with_flags(
+            &[(vec2(40.0, 50.0), 0), (vec2(60.0, 50.0), 0)],
+            0,
+            &camp,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+        );
         for hippie in &mut hippies {
             hippie.angry = true;
             hippie.anger_timer = constants::HIPPIE_ANGER_DURATION;
@@ -1237,6 +1551,9 @@ mod tests {
             &mut flag_state,
             vec2(50.0, 50.0),
             200.0,
+            &mut Rng::new(1),
+            &HippieTuning::default(),
+            &nav::NavGrid::default(),
         );
 
         let distance = hippies[0].pos.distance(hippies[1].pos);
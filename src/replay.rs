@@ -0,0 +1,560 @@
+use macroquad::prelude::{vec2, Rect, Vec2};
+use std::fmt;
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::Path;
+
+use crate::movement;
+use crate::spells::Spell;
+use crate::world::{self, ClassChoice, InputFrame, World, WorldConfig};
+
+const REPLAY_MAGIC: &str = "flaghack2-replay";
+pub const REPLAY_VERSION: u32 = 2;
+/// Version 1 replays carry no world config and play back on the built-in one.
+const REPLAY_VERSION_NO_CONFIG: u32 = 1;
+
+/// One recorded dungeon frame: the simulation input plus the camera state
+/// that was on screen when it was captured.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReplayFrame {
+    pub dt: f32,
+    pub input: InputFrame,
+    pub zoom: f32,
+    pub pan: Vec2,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u32,
+    pub class_choice: ClassChoice,
+    pub field: Rect,
+    /// Tuning, camps and scenery the recording started with.
+    pub config: WorldConfig,
+    pub frames: Vec<ReplayFrame>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    Parse { line: usize, message: String },
+    Serialize(toml::ser::Error),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "replay io error: {}", err),
+            ReplayError::Parse { line, message } => {
+                write!(f, "replay parse error on line {}: {}", line, message)
+            }
+            ReplayError::Serialize(err) => write!(f, "replay serialize error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(err: std::io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+impl Replay {
+    pub fn new(seed: u32, class_choice: ClassChoice, field: Rect, config: WorldConfig) -> Self {
+        Self {
+            seed,
+            class_choice,
+            field,
+            config,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ReplayError> {
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        std::fs::write(path, self.to_text()?)?;
+        Ok(())
+    }
+
+    pub fn to_text(&self) -> Result<String, ReplayError> {
+        let mut text = header_text(self.seed, self.class_choice, self.field, &self.config)?;
+        for frame in &self.frames {
+            text.push_str(&frame_line(frame));
+        }
+        Ok(text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ReplayError> {
+        let mut seed = None;
+        let mut class_choice = None;
+        let mut field = None;
+        let mut version = REPLAY_VERSION;
+        let mut config_text = String::new();
+        let mut config_line = 0;
+        let mut frames = Vec::new();
+
+        for (index, untrimmed) in text.lines().enumerate() {
+            let line = index + 1;
+            // Config lines are raw TOML, so their whitespace is kept as is.
+            if let Some(toml_line) = untrimmed.strip_prefix("config") {
+                if toml_line.is_empty() || toml_line.starts_with(' ') {
+                    if config_line == 0 {
+                        config_line = line;
+                    }
+                    config_text.push_str(toml_line.strip_prefix(' ').unwrap_or(toml_line));
+                    config_text.push('\n');
+                    continue;
+                }
+            }
+            let raw = untrimmed.trim();
+            if raw.is_empty() {
+                continue;
+            }
+            let err = |message: &str| ReplayError::Parse {
+                line,
+                message: message.to_string(),
+            };
+            let mut parts = raw.split_whitespace();
+            let key = parts.next().unwrap_or_default();
+            let values: Vec<&str> = parts.collect();
+
+            if line == 1 {
+                if key != REPLAY_MAGIC {
+                    return Err(err("missing replay header"));
+                }
+                version = values
+                    .first()
+                    .and_then(|v| v.parse::<u32>().ok())
+                    .ok_or_else(|| err("missing replay version"))?;
+                if version != REPLAY_VERSION && version != REPLAY_VERSION_NO_CONFIG {
+                    return Err(err(&format!("unsupported replay version {}", version)));
+                }
+                continue;
+            }
+
+            match (key, values.as_slice()) {
+                ("seed", [value]) => {
+                    seed = Some(value.parse().map_err(|_| err("invalid seed"))?);
+                }
+                ("class", [value]) => {
+                    let index: usize = value.parse().map_err(|_| err("invalid class"))?;
+                    if index >= world::CLASS_OPTIONS.len() {
+                        return Err(err("unknown class"));
+                    }
+                    class_choice = Some(world::class_choice_from_index(index));
+                }
+                ("field", [w, h]) => {
+                    let w = parse_f32(w).ok_or_else(|| err("invalid field width"))?;
+                    let h = parse_f32(h).ok_or_else(|| err("invalid field height"))?;
+                    if !(w > 0.0 && h > 0.0) {
+                        return Err(err(&format!("bad field size {}x{}", w, h)));
+                    }
+                    field = Some(Rect::new(0.0, 0.0, w, h));
+                }
+                ("frame", [dt, buttons, zoom, pan_x, pan_y, move_to @ ..])
+                    if move_to.is_empty() || move_to.len() == 2 =>
+                {
+                    let dt = parse_f32(dt).ok_or_else(|| err("invalid dt"))?;
+                    let mut input = parse_buttons(buttons).ok_or_else(|| err("invalid buttons"))?;
+                    if let [x, y] = move_to {
+                        let x = parse_f32(x).ok_or_else(|| err("invalid move target"))?;
+                        let y = parse_f32(y).ok_or_else(|| err("invalid move target"))?;
+                        input.move_to = Some(vec2(x, y));
+                    }
+                    let zoom = parse_f32(zoom).ok_or_else(|| err("invalid zoom"))?;
+                    let pan_x = parse_f32(pan_x).ok_or_else(|| err("invalid pan"))?;
+                    let pan_y = parse_f32(pan_y).ok_or_else(|| err("invalid pan"))?;
+                    frames.push(ReplayFrame {
+                        dt,
+                        input,
+                        zoom,
+                        pan: vec2(pan_x, pan_y),
+                    });
+                }
+                _ => return Err(err(&format!("unexpected entry '{}'", raw))),
+            }
+        }
+
+        let missing = |what: &str| ReplayError::Parse {
+            line: 0,
+            message: format!("missing {}", what),
+        };
+        let config = if version == REPLAY_VERSION_NO_CONFIG {
+            WorldConfig::default()
+        } else if config_text.is_empty() {
+            return Err(missing("config"));
+        } else {
+            let config_err = |message: String| ReplayError::Parse {
+                line: config_line,
+                message: format!("invalid config: {}", message),
+            };
+            let config: WorldConfig =
+                toml::from_str(&config_text).map_err(|error| config_err(error.to_string()))?;
+            config
+                .tuning
+                .validate()
+                .map_err(|error| config_err(error.to_string()))?;
+            config
+        };
+        Ok(Self {
+            seed: seed.ok_or_else(|| missing("seed"))?,
+            class_choice: class_choice.ok_or_else(|| missing("class"))?,
+            field: field.ok_or_else(|| missing("field"))?,
+            config,
+            frames,
+        })
+    }
+
+    pub fn new_world(&self) -> World {
+        World::new_with_config(
+            self.class_choice,
+            self.field,
+            self.seed,
+            self.config.clone(),
+        )
+    }
+
+    /// Replays every frame against a fresh world without a window.
+    pub fn simulate(&self) -> World {
+        let mut world = self.new_world();
+        for frame in &self.frames {
+            world.step(frame.dt, frame.input);
+        }
+        world
+    }
+}
+
+/// Streams frames to disk as they are captured, flushing each line so the
+/// file is complete even if the game panics mid-run.
+pub struct ReplayRecorder {
+    writer: LineWriter<File>,
+}
+
+impl ReplayRecorder {
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        seed: u32,
+        class_choice: ClassChoice,
+        field: Rect,
+        config: &WorldConfig,
+    ) -> Result<Self, ReplayError> {
+        let header = header_text(seed, class_choice, field, config)?;
+        let mut writer = LineWriter::new(File::create(path)?);
+        writer.write_all(header.as_bytes())?;
+        Ok(Self { writer })
+    }
+
+    pub fn record(&mut self, frame: &ReplayFrame) -> Result<(), ReplayError> {
+        self.writer.write_all(frame_line(frame).as_bytes())?;
+        Ok(())
+    }
+}
+
+pub struct ReplayPlayer {
+    replay: Replay,
+    cursor: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        Self { replay, cursor: 0 }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn next_frame(&mut self) -> Option<ReplayFrame> {
+        let frame = self.replay.frames.get(self.cursor).copied()?;
+        self.cursor += 1;
+        Some(frame)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.replay.frames.len()
+    }
+
+    pub fn progress(&self) -> (usize, usize) {
+        (self.cursor, self.replay.frames.len())
+    }
+}
+
+/// The world config follows the fixed entries as TOML, one `config` line per
+/// line of the document.
+fn header_text(
+    seed: u32,
+    class_choice: ClassChoice,
+    field: Rect,
+    config: &WorldConfig,
+) -> Result<String, ReplayError> {
+    let mut text = format!(
+        "{} {}\nseed {}\nclass {}\nfield {} {}\n",
+        REPLAY_MAGIC,
+        REPLAY_VERSION,
+        seed,
+        world::class_choice_index(class_choice),
+        field.w,
+        field.h
+    );
+    let config = toml::to_string(config).map_err(ReplayError::Serialize)?;
+    for line in config.lines() {
+        text.push_str("config ");
+        text.push_str(line);
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// Frames with a click to move carry its destination as two extra values.
+fn frame_line(frame: &ReplayFrame) -> String {
+    let move_to = frame
+        .input
+        .move_to
+        .map(|pos| format!(" {} {}", pos.x, pos.y))
+        .unwrap_or_default();
+    format!(
+        "frame {} {} {} {} {}{}\n",
+        frame.dt,
+        format_buttons(frame.input),
+        frame.zoom,
+        frame.pan.x,
+        frame.pan.y,
+        move_to
+    )
+}
+
+/// One digit per button, then the spell cast that frame: 0 for none, or its
+/// bar slot counted from 1.
+fn format_buttons(input: InputFrame) -> String {
+    let mut text: String = [
+        input.movement.up,
+        input.movement.down,
+        input.movement.left,
+        input.movement.right,
+        input.place_flag,
+        input.pickup_flag,
+    ]
+    .iter()
+    .map(|&pressed| if pressed { '1' } else { '0' })
+    .collect();
+    let cast = input.cast.map_or(0, |spell| spell.index() + 1);
+    text.push_str(&cast.to_string());
+    text
+}
+
+/// Recordings from before spells have no cast digit.
+fn parse_buttons(text: &str) -> Option<InputFrame> {
+    let (buttons, cast) = match text.len() {
+        6 => (text, None),
+        7 => {
+            let (buttons, digit) = text.split_at(6);
+            let slot = digit.parse::<usize>().ok()?;
+            let cast = match slot {
+                0 => None,
+                _ => Some(Spell::from_index(slot - 1)?),
+            };
+            (buttons, cast)
+        }
+        _ => return None,
+    };
+    let bits: Vec<bool> = buttons
+        .chars()
+        .map(|c| match c {
+            '0' => Some(false),
+            '1' => Some(true),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    let [up, down, left, right, place_flag, pickup_flag] = bits.as_slice() else {
+        return None;
+    };
+    Some(InputFrame {
+        movement: movement::InputState {
+            up: *up,
+            down: *down,
+            left: *left,
+            right: *right,
+        },
+        place_flag: *place_flag,
+        pickup_flag: *pickup_flag,
+        move_to: None,
+        cast,
+    })
+}
+
+fn parse_f32(text: &str) -> Option<f32> {
+    text.parse::<f32>().ok().filter(|v| v.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_config() -> WorldConfig {
+        let mut config = WorldConfig::default();
+        config.tuning.player.speed_multiplier *= 1.5;
+        config.camps[0].notice_text = "Welcome\n  to camp".to_string();
+        config
+    }
+
+    fn sample_replay() -> Replay {
+        let mut replay = Replay::new(
+            11,
+            ClassChoice::Vexillomancer,
+            Rect::new(0.0, 0.0, 9000.0, 6000.0),
+            sample_config(),
+        );
+        for i in 0..240u32 {
+            replay.frames.push(ReplayFrame {
+                dt: 1.0 / 60.0 + (i % 7) as f32 * 0.0013,
+                input: InputFrame {
+                    movement: movement::InputState {
+                        up: i % 50 < 10,
+                        down: (25..35).contains(&(i % 50)),
+                        left: i % 80 < 30,
+                        right: (40..70).contains(&(i % 80)),
+                    },
+                    place_flag: i % 19 == 0,
+                    pickup_flag: i % 23 == 0,
+                    move_to: (i % 60 == 45).then(|| vec2(i as f32 * 2.5, 300.0 - i as f32)),
+                    cast: if i % 40 == 20 {
+                        Spell::from_index((i / 40) as usize)
+                    } else {
+                        None
+                    },
+                },
+                zoom: 4.0 - (i % 5) as f32 * 0.1,
+                pan: vec2(i as f32 * 0.3, -(i as f32) * 0.7),
+            });
+        }
+        replay
+    }
+
+    #[test]
+    fn text_round_trip_is_exact() {
+        let replay = sample_replay();
+        let parsed = Replay::parse(&replay.to_text().expect("write replay")).expect("parse replay");
+        assert_eq!(parsed, replay);
+    }
+
+    #[test]
+    fn parsed_replay_reproduces_world() {
+        let replay = sample_replay();
+        let parsed = Replay::parse(&replay.to_text().expect("write replay")).expect("parse replay");
+        let a = replay.simulate();
+        let b = parsed.simulate();
+        assert_eq!(a.player.pos, b.player.pos);
+        assert_eq!(a.flag_state.ground_flags(), b.flag_state.ground_flags());
+        let a_hippies: Vec<Vec2> = a.hippies.iter().map(|h| h.pos).collect();
+        let b_hippies: Vec<Vec2> = b.hippies.iter().map(|h| h.pos).collect();
+        assert_eq!(a_hippies, b_hippies);
+    }
+
+    #[test]
+    fn playback_uses_the_recorded_config() {
+        let mut replay = sample_replay();
+        replay.frames.truncate(30);
+        let tuned = replay.simulate();
+        replay.config = WorldConfig::default();
+        let builtin = replay.simulate();
+        assert!((tuned.player_speed - builtin.player_speed * 1.5).abs() < 1e-3);
+        assert_ne!(tuned.player.pos, builtin.player.pos);
+    }
+
+    #[test]
+    fn version_one_replays_use_the_builtin_config() {
+        let text = "flaghack2-replay 1\nseed 1\nclass 0\nfield 10 10\n";
+        let replay = Replay::parse(text).expect("parse replay");
+        assert_eq!(replay.config, WorldConfig::default());
+        let missing = "flaghack2-replay 2\nseed 1\nclass 0\nfield 10 10\n";
+        assert!(Replay::parse(missing).is_err());
+    }
+
+    #[test]
+    fn parse_rejects_bad_field_and_tuning() {
+        let zero_field = "flaghack2-replay 1\nseed 1\nclass 0\nfield 0 0\n";
+        match Replay::parse(zero_field) {
+            Err(ReplayError::Parse { line, .. }) => assert_eq!(line, 4),
+            other => panic!("expected parse error, got {:?}", other.map(|_| ())),
+        }
+
+        let mut replay = sample_replay();
+        replay.config.tuning.ley.max_distance = -5.0;
+        let text = replay.to_text().expect("write replay");
+        let config_line = text
+            .lines()
+            .position(|line| line.starts_with("config"))
+            .expect("config line")
+            + 1;
+        match Replay::parse(&text) {
+            Err(ReplayError::Parse { line, .. }) => assert_eq!(line, config_line),
+            other => panic!("expected parse error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn recorder_output_loads_back() {
+        let replay = sample_replay();
+        let path = std::env::temp_dir().join(format!(
+            "flaghack2-replay-test-{}.replay",
+            std::process::id()
+        ));
+        let mut recorder = ReplayRecorder::create(
+            &path,
+            replay.seed,
+            replay.class_choice,
+            replay.field,
+            &replay.config,
+        )
+        .expect("create recorder");
+        for frame in &replay.frames {
+            recorder.record(frame).expect("record frame");
+        }
+        drop(recorder);
+
+        let loaded = Replay::load(&path).expect("load replay");
+        std::fs::remove_file(&path).ok();
+        assert_eq!(loaded, replay);
+    }
+
+    #[test]
+    fn player_yields_frames_in_order() {
+        let replay = sample_replay();
+        let first = replay.frames[0];
+        let count = replay.frames.len();
+        let mut player = ReplayPlayer::new(replay);
+        assert_eq!(player.next_frame(), Some(first));
+        while player.next_frame().is_some() {}
+        assert!(player.is_finished());
+        assert_eq!(player.progress(), (count, count));
+    }
+
+    #[test]
+    fn parse_rejects_bad_input() {
+        assert!(Replay::parse("nonsense 1\n").is_err());
+        assert!(Replay::parse("flaghack2-replay 99\n").is_err());
+        let missing_seed = "flaghack2-replay 1\nclass 0\nfield 10 10\n";
+        assert!(Replay::parse(missing_seed).is_err());
+        let bad_frame = "flaghack2-replay 1\nseed 1\nclass 0\nfield 10 10\nframe 0.1 01x 4 0 0\n";
+        match Replay::parse(bad_frame) {
+            Err(ReplayError::Parse { line, .. }) => assert_eq!(line, 5),
+            other => panic!("expected parse error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn frames_without_a_cast_digit_still_parse() {
+        let text = "flaghack2-replay 1\nseed 1\nclass 0\nfield 10 10\nframe 0.1 100000 4 0 0\nframe 0.1 0000013 4 0 0\n";
+        let replay = Replay::parse(text).expect("parse replay");
+        assert!(replay.frames[0].input.movement.up);
+        assert_eq!(replay.frames[0].input.cast, None);
+        assert_eq!(replay.frames[1].input.cast, Some(Spell::Gust));
+        let unknown = "flaghack2-replay 1\nseed 1\nclass 0\nfield 10 10\nframe 0.1 0000009 4 0 0\n";
+        assert!(Replay::parse(unknown).is_err());
+    }
+}
@@ -0,0 +1,539 @@
+use macroquad::prelude::{vec2, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+use crate::constants::WIND_START_STRENGTH;
+use crate::flag_state::FlagState;
+use crate::flags;
+use crate::npc;
+use crate::player::Facing;
+use crate::rng::Rng;
+use crate::spells::Spellbook;
+use crate::world::{self, ClassChoice, World, WorldConfig};
+
+pub const SAVE_VERSION: u32 = 1;
+pub const AUTOSAVE_PATH: &str = "saves/autosave.toml";
+
+/// On-disk snapshot of everything in a `World` that cannot be rebuilt from
+/// the seed. Camps, scenery and the ley graph are regenerated on load.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SaveData {
+    pub version: u32,
+    pub seed: u32,
+    pub class_choice: ClassChoice,
+    pub field: [f32; 2],
+    pub rng_state: u32,
+    pub player: PlayerSave,
+    /// Click-to-move waypoints still ahead of the player.
+    #[serde(default)]
+    pub player_path: Vec<[f32; 2]>,
+    /// Camp names in index order, so hippies find their camps again even if
+    /// camp files were added or renamed since. Saves made before this field
+    /// existed fall back to the bare indices.
+    #[serde(default)]
+    pub camps: Vec<String>,
+    pub flagic: u8,
+    pub flagic_accum: f32,
+    /// Missing from saves made before spells; those load with every spell
+    /// ready and the starting wind.
+    #[serde(default)]
+    pub spellbook: Spellbook,
+    #[serde(default)]
+    pub wind: WindSave,
+    pub flags: FlagStateSave,
+    pub hippies: Vec<HippieSave>,
+    pub camp_notices: Vec<CampNoticeSave>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerSave {
+    pub pos: [f32; 2],
+    pub facing: Facing,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WindSave {
+    pub direction: [f32; 2],
+    pub strength: f32,
+}
+
+impl Default for WindSave {
+    fn default() -> Self {
+        Self {
+            direction: [1.0, 0.0],
+            strength: WIND_START_STRENGTH,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FlagStateSave {
+    pub player: u32,
+    pub total: u32,
+    pub ground: Vec<FlagSave>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FlagSave {
+    pub pos: [f32; 2],
+    pub phase: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HippieSave {
+    pub pos: [f32; 2],
+    pub facing: Facing,
+    pub carried_flags: u8,
+    pub angry: bool,
+    pub anger_timer: f32,
+    pub anger_delay: f32,
+    pub steal_cooldown: f32,
+    pub flee_timer: f32,
+    pub drop_check_timer: f32,
+    pub ignore_flags_timer: f32,
+    pub camp_index: usize,
+    pub target: [f32; 2],
+    pub speed: f32,
+    /// Saves made before nav paths were kept load with no path and plan
+    /// again on the next step.
+    #[serde(default)]
+    pub path: Vec<[f32; 2]>,
+    #[serde(default)]
+    pub path_goal: [f32; 2],
+    #[serde(default)]
+    pub repath_timer: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CampNoticeSave {
+    pub camp_name: String,
+    pub inside: bool,
+    pub timer: f32,
+}
+
+#[derive(Deserialize)]
+struct VersionProbe {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    UnsupportedVersion(u32),
+    Invalid(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "save io error: {}", err),
+            SaveError::Parse(err) => write!(f, "save parse error: {}", err),
+            SaveError::Serialize(err) => write!(f, "save serialize error: {}", err),
+            SaveError::UnsupportedVersion(version) => {
+                write!(f, "unsupported save version {}", version)
+            }
+            SaveError::Invalid(message) => write!(f, "invalid save: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<std::io::Error> for SaveError {
+    fn from(err: std::io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl SaveData {
+    pub fn capture(world: &World) -> Self {
+        let field = world.field_rect();
+        Self {
+            version: SAVE_VERSION,
+            seed: world.seed,
+            class_choice: world.class_choice,
+            field: [field.w, field.h],
+            rng_state: world.rng.state(),
+            player: PlayerSave {
+                pos: to_array(world.player.pos),
+                facing: world.player.facing,
+            },
+            player_path: world.player_path.iter().copied().map(to_array).collect(),
+            camps: world
+                .camp_regions
+                .iter()
+                .map(|camp| camp.name.clone())
+                .collect(),
+            flagic: world.flagic,
+            flagic_accum: world.flagic_accum,
+            spellbook: world.spellbook.clone(),
+            wind: WindSave {
+                direction: to_array(world.wind.direction),
+                strength: world.wind.strength,
+            },
+            flags: FlagStateSave {
+                player: world.flag_state.player_inventory(),
+                total: world.flag_state.total(),
+                ground: world
+                    .flag_state
+                    .ground_flags()
+                    .iter()
+                    .map(|flag| FlagSave {
+                        pos: to_array(flag.pos),
+                        phase: flag.phase,
+                    })
+                    .collect(),
+            },
+            hippies: world
+                .hippies
+                .iter()
+                .map(|hippie| HippieSave {
+                    pos: to_array(hippie.pos),
+                    facing: hippie.facing,
+                    carried_flags: hippie.carried_flags,
+                    angry: hippie.angry,
+                    anger_timer: hippie.anger_timer,
+                    anger_delay: hippie.anger_delay,
+                    steal_cooldown: hippie.steal_cooldown,
+                    flee_timer: hippie.flee_timer,
+                    drop_check_timer: hippie.drop_check_timer,
+                    ignore_flags_timer: hippie.ignore_flags_timer,
+                    camp_index: hippie.camp_index,
+                    target: to_array(hippie.target),
+                    speed: hippie.speed,
+                    path: hippie.path.iter().copied().map(to_array).collect(),
+                    path_goal: to_array(hippie.path_goal),
+                    repath_timer: hippie.repath_timer,
+                })
+                .collect(),
+            camp_notices: world
+                .camp_notices
+                .iter()
+                .map(|notice| CampNoticeSave {
+                    camp_name: notice.camp_name.clone(),
+                    inside: notice.inside,
+                    timer: notice.timer,
+                })
+                .collect(),
+        }
+    }
+
+    /// Rebuilds a world from the seed and `config`, then overlays the saved
+    /// state and recomputes the ley graph from the restored ground flags.
+    /// Hippies are matched to their camps by name; a save whose camps are
+    /// missing from `config` is rejected.
+    pub fn restore(&self, config: WorldConfig) -> Result<World, SaveError> {
+        if self.version != SAVE_VERSION {
+            return Err(SaveError::UnsupportedVersion(self.version));
+        }
+        let [w, h] = self.field;
+        if !(w > 0.0 && h > 0.0) {
+            return Err(SaveError::Invalid(format!("bad field size {}x{}", w, h)));
+        }
+
+        let field = Rect::new(0.0, 0.0, w, h);
+        let mut world = World::new_with_config(self.class_choice, field, self.seed, config);
+
+        let hippie_flags: u32 = self
+            .hippies
+            .iter()
+            .map(|hippie| hippie.carried_flags as u32)
+            .sum();
+        let counted = self.flags.ground.len() as u32 + self.flags.player + hippie_flags;
+        if counted != self.flags.total {
+            return Err(SaveError::Invalid(format!(
+                "flag total {} does not match {} counted flags",
+                self.flags.total, counted
+            )));
+        }
+
+        let camp_indices = self.camp_indices(&world)?;
+        for hippie in &self.hippies {
+            if hippie.camp_index >= camp_indices.len() {
+                return Err(SaveError::Invalid(format!(
+                    "hippie camp index {} out of range",
+                    hippie.camp_index
+                )));
+            }
+        }
+
+        let hippies = self
+            .hippies
+            .iter()
+            .map(|saved| npc::Hippie {
+                pos: from_array(saved.pos),
+                facing: saved.facing,
+                carried_flags: saved.carried_flags,
+                angry: saved.angry,
+                anger_timer: saved.anger_timer,
+                anger_delay: saved.anger_delay,
+                steal_cooldown: saved.steal_cooldown,
+                flee_timer: saved.flee_timer,
+                drop_check_timer: saved.drop_check_timer,
+                ignore_flags_timer: saved.ignore_flags_timer,
+                camp_index: camp_indices[saved.camp_index],
+                target: from_array(saved.target),
+                speed: saved.speed,
+                path: saved.path.iter().copied().map(from_array).collect(),
+                path_goal: from_array(saved.path_goal),
+                repath_timer: saved.repath_timer,
+            })
+            .collect();
+
+        world.player.pos = from_array(self.player.pos);
+        world.player.facing = self.player.facing;
+        world.player_path = self.player_path.iter().copied().map(from_array).collect();
+        world.flag_state = FlagState::new(
+            self.flags
+                .ground
+                .iter()
+                .map(|flag| flags::Flag {
+                    pos: from_array(flag.pos),
+                    phase: flag.phase,
+                })
+                .collect(),
+            self.flags.player,
+            self.flags.total,
+        );
+        world.hippies = hippies;
+        world.flagic = self.flagic;
+        world.flagic_accum = self.flagic_accum;
+        world.spellbook = self.spellbook.clone();
+        world.wind = flags::Wind::new(from_array(self.wind.direction), self.wind.strength);
+        for saved in &self.camp_notices {
+            if let Some(notice) = world
+                .camp_notices
+                .iter_mut()
+                .find(|notice| notice.camp_name == saved.camp_name)
+            {
+                notice.inside = saved.inside;
+                notice.timer = saved.timer;
+            }
+        }
+        world.rng = Rng::new(self.rng_state);
+        world.recompute_ley_state();
+        world
+            .flag_state
+            .debug_assert_invariant(world::total_hippie_flags(&world.hippies));
+
+        Ok(world)
+    }
+
+    /// Maps each saved camp index to the index of the same camp in `world`.
+    /// Fails if a saved camp no longer exists.
+    fn camp_indices(&self, world: &World) -> Result<Vec<usize>, SaveError> {
+        if self.camps.is_empty() {
+            return Ok((0..world.camp_regions.len()).collect());
+        }
+        self.camps
+            .iter()
+            .map(|name| {
+                world
+                    .camp_regions
+                    .iter()
+                    .position(|camp| &camp.name == name)
+                    .ok_or_else(|| SaveError::Invalid(format!("camp {} no longer exists", name)))
+            })
+            .collect()
+    }
+
+    pub fn to_toml(&self) -> Result<String, SaveError> {
+        toml::to_string(self).map_err(SaveError::Serialize)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, SaveError> {
+        let probe: VersionProbe = toml::from_str(text).map_err(SaveError::Parse)?;
+        if probe.version != SAVE_VERSION {
+            return Err(SaveError::UnsupportedVersion(probe.version));
+        }
+        toml::from_str(text).map_err(SaveError::Parse)
+    }
+}
+
+/// Writes through a temporary file so a crash mid-save never leaves a
+/// truncated slot behind.
+pub fn save_world<P: AsRef<Path>>(world: &World, path: P) -> Result<(), SaveError> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let text = SaveData::capture(world).to_toml()?;
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, text)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+pub fn load_world<P: AsRef<Path>>(path: P, config: WorldConfig) -> Result<World, SaveError> {
+    let text = std::fs::read_to_string(path)?;
+    SaveData::from_toml(&text)?.restore(config)
+}
+
+fn to_array(v: Vec2) -> [f32; 2] {
+    [v.x, v.y]
+}
+
+fn from_array(v: [f32; 2]) -> Vec2 {
+    vec2(v[0], v[1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::InputFrame;
+
+    fn played_world() -> World {
+        let mut world = World::new(
+            ClassChoice::Vexillomancer,
+            Rect::new(0.0, 0.0, 9000.0, 6000.0),
+            5,
+        );
+        world.flagic = 40;
+        for i in 0..120 {
+            let mut input = InputFrame::default();
+            input.movement.right = i < 60;
+            input.movement.down = i >= 60;
+            input.place_flag = i % 30 == 0;
+            if i == 90 {
+                input.cast = Some(crate::spells::Spell::Gust);
+            }
+            world.step(1.0 / 60.0, input);
+        }
+        world
+    }
+
+    #[test]
+    fn toml_round_trip_preserves_save() {
+        let save = SaveData::capture(&played_world());
+        let text = save.to_toml().expect("serialize save");
+        let parsed = SaveData::from_toml(&text).expect("parse save");
+        assert_eq!(parsed, save);
+    }
+
+    #[test]
+    fn restored_world_matches_and_keeps_simulating_identically() {
+        let mut original = played_world();
+        let mut restored = SaveData::capture(&original)
+            .restore(WorldConfig::default())
+            .expect("restore save");
+
+        assert_eq!(restored.player.pos, original.player.pos);
+        assert_eq!(restored.current_total_flags(), original.flag_state.total());
+        assert_eq!(restored.ley_lines.len(), original.ley_lines.len());
+        assert_eq!(restored.pentagram_centers, original.pentagram_centers);
+        assert_eq!(restored.wind, original.wind);
+        assert_eq!(restored.spellbook, original.spellbook);
+
+        for _ in 0..180 {
+            let input = InputFrame {
+                pickup_flag: true,
+                ..InputFrame::default()
+            };
+            original.step(1.0 / 60.0, input);
+            restored.step(1.0 / 60.0, input);
+        }
+        assert_eq!(restored.player.pos, original.player.pos);
+        assert_eq!(
+            restored.flag_state.ground_flags(),
+            original.flag_state.ground_flags()
+        );
+        let a: Vec<Vec2> = original.hippies.iter().map(|h| h.pos).collect();
+        let b: Vec<Vec2> = restored.hippies.iter().map(|h| h.pos).collect();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn restore_keeps_paths_in_progress() {
+        let mut original = played_world();
+        let target = original.player.pos + vec2(900.0, 400.0);
+        original.step(
+            1.0 / 60.0,
+            InputFrame {
+                move_to: Some(target),
+                ..InputFrame::default()
+            },
+        );
+        for _ in 0..30 {
+            original.step(1.0 / 60.0, InputFrame::default());
+        }
+        assert!(!original.player_path.is_empty());
+        let save = SaveData::capture(&original);
+        let parsed =
+            SaveData::from_toml(&save.to_toml().expect("serialize save")).expect("parse save");
+        let mut restored = parsed
+            .restore(WorldConfig::default())
+            .expect("restore save");
+        assert_eq!(restored.player_path, original.player_path);
+        for (a, b) in original.hippies.iter().zip(&restored.hippies) {
+            assert_eq!(a.path, b.path);
+            assert_eq!(a.path_goal, b.path_goal);
+            assert_eq!(a.repath_timer, b.repath_timer);
+        }
+
+        for _ in 0..120 {
+            original.step(1.0 / 60.0, InputFrame::default());
+            restored.step(1.0 / 60.0, InputFrame::default());
+        }
+        assert_eq!(restored.player.pos, original.player.pos);
+        let a: Vec<Vec2> = original.hippies.iter().map(|h| h.pos).collect();
+        let b: Vec<Vec2> = restored.hippies.iter().map(|h| h.pos).collect();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn restore_matches_camps_by_name() {
+        let original = played_world();
+        let save = SaveData::capture(&original);
+
+        let mut config = WorldConfig::default();
+        let mut extra = config.camps[0].clone();
+        extra.name = "Aardvark Acres".to_string();
+        config.camps.insert(0, extra);
+        let restored = save.restore(config.clone()).expect("restore save");
+        for (a, b) in original.hippies.iter().zip(&restored.hippies) {
+            assert_eq!(
+                original.camp_regions[a.camp_index].name,
+                restored.camp_regions[b.camp_index].name
+            );
+        }
+
+        config.camps.retain(|camp| camp.name != save.camps[0]);
+        assert!(matches!(save.restore(config), Err(SaveError::Invalid(_))));
+    }
+
+    #[test]
+    fn restore_rejects_broken_flag_total() {
+        let mut save = SaveData::capture(&played_world());
+        save.flags.total += 1;
+        assert!(matches!(
+            save.restore(WorldConfig::default()),
+            Err(SaveError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn from_toml_rejects_other_versions() {
+        let mut save = SaveData::capture(&played_world());
+        save.version = SAVE_VERSION + 1;
+        let text = toml::to_string(&save).expect("serialize save");
+        assert!(matches!(
+            SaveData::from_toml(&text),
+            Err(SaveError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn save_world_writes_a_loadable_slot() {
+        let world = played_world();
+        let dir = std::env::temp_dir().join(format!("flaghack2-save-test-{}", std::process::id()));
+        let path = dir.join("slot.toml");
+        save_world(&world, &path).expect("save world");
+        let loaded = load_world(&path, WorldConfig::default()).expect("load world");
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(loaded.player.pos, world.player.pos);
+        assert_eq!(loaded.flagic, world.flagic);
+    }
+}
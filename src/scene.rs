@@ -0,0 +1,207 @@
+/// What a scene asks the stack to do after its update.
+pub enum Transition<C> {
+    None,
+    Push(Box<dyn Scene<C>>),
+    Pop,
+    Replace(Box<dyn Scene<C>>),
+    /// Clears the whole stack and starts over from the given scene.
+    Reset(Box<dyn Scene<C>>),
+    Quit,
+}
+
+/// One screen of the game. Only the top scene of a `SceneStack` updates;
+/// overlays let the scenes beneath them keep drawing, frozen in place.
+pub trait Scene<C> {
+    fn update(&mut self, ctx: &mut C) -> Transition<C>;
+
+    fn draw(&mut self, ctx: &mut C);
+
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    /// Called when the scene leaves the stack, including on quit.
+    fn on_exit(&mut self, _ctx: &mut C) {}
+}
+
+pub struct SceneStack<C> {
+    scenes: Vec<Box<dyn Scene<C>>>,
+}
+
+impl<C> SceneStack<C> {
+    pub fn new(root: Box<dyn Scene<C>>) -> Self {
+        Self { scenes: vec![root] }
+    }
+
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    /// Updates the top scene and applies its transition. Returns `false` once
+    /// the game should exit.
+    pub fn update(&mut self, ctx: &mut C) -> bool {
+        let Some(top) = self.scenes.last_mut() else {
+            return false;
+        };
+        match top.update(ctx) {
+            Transition::None => {}
+            Transition::Push(scene) => self.scenes.push(scene),
+            Transition::Pop => {
+                self.pop(ctx);
+            }
+            Transition::Replace(scene) => {
+                self.pop(ctx);
+                self.scenes.push(scene);
+            }
+            Transition::Reset(scene) => {
+                self.clear(ctx);
+                self.scenes.push(scene);
+            }
+            Transition::Quit => {
+                self.clear(ctx);
+            }
+        }
+        !self.scenes.is_empty()
+    }
+
+    /// Draws from the highest opaque scene upward so overlays sit on top of
+    /// whatever they cover.
+    pub fn draw(&mut self, ctx: &mut C) {
+        let first = self
+            .scenes
+            .iter()
+            .rposition(|scene| !scene.is_overlay())
+            .unwrap_or(0);
+        for scene in &mut self.scenes[first..] {
+            scene.draw(ctx);
+        }
+    }
+
+    /// Exits every scene, top first.
+    pub fn clear(&mut self, ctx: &mut C) {
+        while self.pop(ctx) {}
+    }
+
+    fn pop(&mut self, ctx: &mut C) -> bool {
+        match self.scenes.pop() {
+            Some(mut scene) => {
+                scene.on_exit(ctx);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Log {
+        events: Vec<String>,
+        next: Vec<&'static str>,
+    }
+
+    struct Named {
+        name: &'static str,
+        overlay: bool,
+    }
+
+    fn named(name: &'static str, overlay: bool) -> Box<dyn Scene<Log>> {
+        Box::new(Named { name, overlay })
+    }
+
+    impl Scene<Log> for Named {
+        fn update(&mut self, ctx: &mut Log) -> Transition<Log> {
+            ctx.events.push(format!("update {}", self.name));
+            match ctx.next.pop() {
+                Some("push") => Transition::Push(named("pause", true)),
+                Some("pop") => Transition::Pop,
+                Some("replace") => Transition::Replace(named("other", false)),
+                Some("reset") => Transition::Reset(named("title", false)),
+                Some("quit") => Transition::Quit,
+                _ => Transition::None,
+            }
+        }
+
+        fn draw(&mut self, ctx: &mut Log) {
+            ctx.events.push(format!("draw {}", self.name));
+        }
+
+        fn is_overlay(&self) -> bool {
+            self.overlay
+        }
+
+        fn on_exit(&mut self, ctx: &mut Log) {
+            ctx.events.push(format!("exit {}", self.name));
+        }
+    }
+
+    #[test]
+    fn overlay_draws_over_frozen_scene() {
+        let mut log = Log {
+            next: vec!["push"],
+            ..Log::default()
+        };
+        let mut stack = SceneStack::new(named("dungeon", false));
+        assert!(stack.update(&mut log));
+        assert_eq!(stack.len(), 2);
+
+        log.events.clear();
+        stack.update(&mut log);
+        stack.draw(&mut log);
+        assert_eq!(
+            log.events,
+            vec!["update pause", "draw dungeon", "draw pause"]
+        );
+    }
+
+    #[test]
+    fn pop_returns_to_scene_below() {
+        let mut log = Log {
+            next: vec!["pop", "push"],
+            ..Log::default()
+        };
+        let mut stack = SceneStack::new(named("dungeon", false));
+        stack.update(&mut log);
+        stack.update(&mut log);
+        assert_eq!(stack.len(), 1);
+        assert!(log.events.contains(&"exit pause".to_string()));
+        assert!(!log.events.contains(&"exit dungeon".to_string()));
+    }
+
+    #[test]
+    fn reset_exits_everything_and_quit_empties_stack() {
+        let mut log = Log {
+            next: vec!["quit", "reset", "push"],
+            ..Log::default()
+        };
+        let mut stack = SceneStack::new(named("dungeon", false));
+        stack.update(&mut log);
+        stack.update(&mut log);
+        assert_eq!(stack.len(), 1);
+        assert!(log.events.contains(&"exit dungeon".to_string()));
+
+        assert!(!stack.update(&mut log));
+        assert!(stack.is_empty());
+        assert_eq!(log.events.last().map(String::as_str), Some("exit title"));
+    }
+
+    #[test]
+    fn replace_swaps_top_scene() {
+        let mut log = Log {
+            next: vec!["replace"],
+            ..Log::default()
+        };
+        let mut stack = SceneStack::new(named("title", false));
+        stack.update(&mut log);
+        log.events.clear();
+        stack.draw(&mut log);
+        assert_eq!(log.events, vec!["draw other"]);
+    }
+}
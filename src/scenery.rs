@@ -1,9 +1,14 @@
 use crate::fire;
+use crate::geom;
+use crate::rng::Rng;
 use crate::scale;
 use macroquad::prelude::*;
-use macroquad::rand::gen_range;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SceneryKind {
     Tree,
     Tent,
@@ -14,7 +19,38 @@ pub enum SceneryKind {
     Dome,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub const ALL_SCENERY_KINDS: [SceneryKind; 7] = [
+    SceneryKind::Tree,
+    SceneryKind::Tent,
+    SceneryKind::Chair,
+    SceneryKind::Campfire,
+    SceneryKind::CrowBase,
+    SceneryKind::Crow,
+    SceneryKind::Dome,
+];
+
+impl SceneryKind {
+    /// Whether the model is drawn turned by `rotation`. Other kinds always
+    /// stand upright.
+    pub fn rotates(self) -> bool {
+        self == SceneryKind::Chair
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SceneryKind::Tree => "tree",
+            SceneryKind::Tent => "tent",
+            SceneryKind::Chair => "chair",
+            SceneryKind::Campfire => "campfire",
+            SceneryKind::CrowBase => "crow_base",
+            SceneryKind::Crow => "crow",
+            SceneryKind::Dome => "dome",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum DomeDecoration {
     Crystal,
 }
@@ -29,17 +65,55 @@ pub struct SceneryItem {
     pub decorations: Vec<DomeDecoration>,
 }
 
-#[derive(Clone, Debug)]
+impl SceneryItem {
+    /// The rotation the item is drawn and collided with.
+    pub fn drawn_rotation(&self) -> f32 {
+        if self.kind.rotates() {
+            self.rotation
+        } else {
+            0.0
+        }
+    }
+}
+
+/// A hand-placed scenery item. In data files only `kind` and `pos` are
+/// required.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ScenerySpawn {
     pub kind: SceneryKind,
+    #[serde(with = "geom::xy")]
     pub pos: Vec2,
+    #[serde(default = "default_spawn_scale")]
     pub scale: f32,
+    #[serde(default)]
     pub rotation: f32,
+    #[serde(default)]
     pub variant: u8,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub decorations: Vec<DomeDecoration>,
 }
 
+fn default_spawn_scale() -> f32 {
+    1.0
+}
+
 impl ScenerySpawn {
+    /// A spawn with the defaults the scenery editor places for `kind`.
+    pub fn new(kind: SceneryKind, pos: Vec2) -> Self {
+        Self {
+            kind,
+            pos,
+            scale: if kind == SceneryKind::Tree {
+                tree_scale_from_pos(pos)
+            } else {
+                1.0
+            },
+            rotation: 0.0,
+            variant: 0,
+            decorations: Vec::new(),
+        }
+    }
+
     pub fn campfire(pos: Vec2, scale: f32) -> Self {
         Self {
             kind: SceneryKind::Campfire,
@@ -74,6 +148,94 @@ impl ScenerySpawn {
     }
 }
 
+pub const SCENERY_PATH: &str = "assets/scenery.toml";
+
+/// Hand-placed scenery outside the camps, as saved by the scenery editor.
+/// `random_domes` domes are also scattered from the world seed.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneryLayout {
+    pub random_domes: usize,
+    pub items: Vec<ScenerySpawn>,
+}
+
+#[derive(Debug)]
+pub enum SceneryError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    OutOfMap { kind: SceneryKind, pos: Vec2 },
+}
+
+impl fmt::Display for SceneryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneryError::Io(err) => write!(f, "scenery io error: {}", err),
+            SceneryError::Parse(err) => write!(f, "scenery parse error: {}", err),
+            SceneryError::Serialize(err) => write!(f, "scenery serialize error: {}", err),
+            SceneryError::OutOfMap { kind, pos } => write!(
+                f,
+                "{} outside the map at ({}, {})",
+                kind.name(),
+                pos.x,
+                pos.y
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SceneryError {}
+
+impl From<std::io::Error> for SceneryError {
+    fn from(err: std::io::Error) -> Self {
+        SceneryError::Io(err)
+    }
+}
+
+impl SceneryLayout {
+    /// The layout shipped at `SCENERY_PATH`, compiled in like the built-in
+    /// camps.
+    pub fn builtin() -> Self {
+        Self::from_toml(include_str!("../assets/scenery.toml"))
+            .expect("built-in scenery file should parse")
+    }
+
+    pub fn to_toml(&self) -> Result<String, SceneryError> {
+        toml::to_string(self).map_err(SceneryError::Serialize)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, SceneryError> {
+        toml::from_str(text).map_err(SceneryError::Parse)
+    }
+
+    pub fn validate(&self, field: Rect) -> Result<(), SceneryError> {
+        match self.items.iter().find(|item| !field.contains(item.pos)) {
+            Some(item) => Err(SceneryError::OutOfMap {
+                kind: item.kind,
+                pos: item.pos,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P, field: Rect) -> Result<Self, SceneryError> {
+        let text = std::fs::read_to_string(path)?;
+        let layout = Self::from_toml(&text)?;
+        layout.validate(field)?;
+        Ok(layout)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P, field: Rect) -> Result<(), SceneryError> {
+        self.validate(field)?;
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+}
+
 pub fn apply_spawns(items: &mut Vec<SceneryItem>, spawns: &[ScenerySpawn]) {
     for spawn in spawns {
         items.push(SceneryItem {
@@ -87,15 +249,16 @@ pub fn apply_spawns(items: &mut Vec<SceneryItem>, spawns: &[ScenerySpawn]) {
     }
 }
 
-const BASE_W: f32 = 800.0;
-const BASE_H: f32 = 600.0;
-const DOME_COUNT: usize = 2;
 const DOME_PADDING: f32 = 120.0 * scale::MODEL_SCALE;
 const DOME_SCALE: f32 = 1.5;
 const CRYSTAL_SCALE: f32 = 1.5;
 pub const DOME_RADIUS: f32 = 100.0 * scale::MODEL_SCALE * DOME_SCALE;
 pub const DOME_HEIGHT: f32 = 100.0 * scale::MODEL_SCALE * DOME_SCALE;
-const CRYSTAL_DOME_POS: Vec2 = Vec2::new(4900.0, 3184.0);
+/// How much the dome's ground ring is flattened vertically on screen.
+pub const DOME_SQUASH: f32 = 0.4;
+/// Half the angle of the ground ring left open for the doorway, centered on
+/// the front of the dome.
+pub const DOME_DOOR_HALF_ANGLE: f32 = 0.3;
 
 const TENT_COLORS: [Color; 5] = [
     Color::new(0.88, 0.48, 0.22, 1.0),
@@ -106,113 +269,17 @@ const TENT_COLORS: [Color; 5] = [
 ];
 pub const TENT_VARIANT_COUNT: u8 = TENT_COLORS.len() as u8;
 
-pub fn spawn_scenery(field: Rect, camp_spawns: &[ScenerySpawn]) -> Vec<SceneryItem> {
+pub fn spawn_scenery(
+    field: Rect,
+    layout: &SceneryLayout,
+    camp_spawns: &[ScenerySpawn],
+    rng: &mut Rng,
+) -> Vec<SceneryItem> {
     let mut items = Vec::new();
+    apply_spawns(&mut items, &layout.items);
 
-    let tents = [
-        vec2(80.0, 120.0),
-        vec2(700.0, 100.0),
-        vec2(650.0, 480.0),
-        vec2(100.0, 450.0),
-        vec2(400.0, 80.0),
-    ];
-
-    for (i, base) in tents.iter().enumerate() {
-        let pos = map_position(field, *base);
-        items.push(SceneryItem {
-            kind: SceneryKind::Tent,
-            pos,
-            scale: 1.0,
-            rotation: 0.0,
-            variant: i as u8 % TENT_COLORS.len() as u8,
-            decorations: Vec::new(),
-        });
-    }
-
-    let chairs = [
-        vec2(150.0, 200.0),
-        vec2(620.0, 180.0),
-        vec2(180.0, 380.0),
-        vec2(580.0, 420.0),
-        vec2(350.0, 520.0),
-    ];
-    let chair_rotations = [-0.4, 0.6, 0.2, -0.7, 1.1];
-
-    for (i, base) in chairs.iter().enumerate() {
-        let pos = map_position(field, *base);
-        items.push(SceneryItem {
-            kind: SceneryKind::Chair,
-            pos,
-            scale: 1.0,
-            rotation: chair_rotations[i % chair_rotations.len()],
-            variant: 0,
-            decorations: Vec::new(),
-        });
-    }
-
-    let campfires = [vec2(200.0, 300.0), vec2(550.0, 350.0)];
-    for base in campfires {
-        let pos = map_position(field, base);
-        items.push(SceneryItem {
-            kind: SceneryKind::Campfire,
-            pos,
-            scale: 1.0,
-            rotation: 0.0,
-            variant: 0,
-            decorations: Vec::new(),
-        });
-    }
-
-    let crow_base_pos = vec2(5065.0, 3327.0);
-    items.push(SceneryItem {
-        kind: SceneryKind::CrowBase,
-        pos: crow_base_pos,
-        scale: 1.0,
-        rotation: 0.0,
-        variant: 0,
-        decorations: Vec::new(),
-    });
-    items.push(SceneryItem {
-        kind: SceneryKind::Crow,
-        pos: crow_base_pos,
-        scale: 1.0,
-        rotation: 0.0,
-        variant: 0,
-        decorations: Vec::new(),
-    });
-
-    items.push(SceneryItem {
-        kind: SceneryKind::Dome,
-        pos: CRYSTAL_DOME_POS,
-        scale: 1.0,
-        rotation: 0.0,
-        variant: 0,
-        decorations: vec![DomeDecoration::Crystal],
-    });
-
-    let trees = [
-        vec2(30.0, 50.0),
-        vec2(770.0, 40.0),
-        vec2(20.0, 550.0),
-        vec2(750.0, 560.0),
-        vec2(400.0, 30.0),
-    ];
-
-    for base in trees {
-        let pos = map_position(field, base);
-        let scale = tree_scale_from_pos(pos);
-        items.push(SceneryItem {
-            kind: SceneryKind::Tree,
-            pos,
-            scale,
-            rotation: 0.0,
-            variant: 0,
-            decorations: Vec::new(),
-        });
-    }
-
-    for i in 0..DOME_COUNT {
-        let pos = random_position(field, DOME_PADDING);
+    for i in 0..layout.random_domes {
+        let pos = random_position(field, DOME_PADDING, rng);
         let decorations = if i == 0 {
             vec![DomeDecoration::Crystal]
         } else {
@@ -233,33 +300,38 @@ pub fn spawn_scenery(field: Rect, camp_spawns: &[ScenerySpawn]) -> Vec<SceneryIt
     items
 }
 
+/// Swaps the first `old_count` items, which came from a layout, for `spawns`.
+/// Random domes and camp scenery after them are kept.
+pub fn replace_layout_items(
+    items: &mut Vec<SceneryItem>,
+    old_count: usize,
+    spawns: &[ScenerySpawn],
+) {
+    let mut layout = Vec::with_capacity(spawns.len());
+    apply_spawns(&mut layout, spawns);
+    items.splice(0..old_count.min(items.len()), layout);
+}
+
 pub fn draw_scenery(items: &[SceneryItem], time: f32) {
     for item in items {
         match item.kind {
             SceneryKind::Tree => draw_tree(item.pos, item.scale),
-            SceneryKind::Tent => draw_tent(item.pos, item.variant),
-            SceneryKind::Chair => draw_chair(item.pos, item.rotation),
+            SceneryKind::Tent => draw_tent(item.pos, item.variant, item.scale),
+            SceneryKind::Chair => draw_chair(item.pos, item.drawn_rotation(), item.scale),
             SceneryKind::Campfire => draw_campfire(item.pos, time, item.scale),
-            SceneryKind::CrowBase => draw_crow_base(item.pos, time),
-            SceneryKind::Crow => draw_crow(item.pos, time),
-            SceneryKind::Dome => draw_geodesic_dome(item.pos, time, &item.decorations),
+            SceneryKind::CrowBase => draw_crow_base(item.pos, time, item.scale),
+            SceneryKind::Crow => draw_crow(item.pos, time, item.scale),
+            SceneryKind::Dome => draw_geodesic_dome(item.pos, time, &item.decorations, item.scale),
         }
     }
 }
 
-fn map_position(field: Rect, base: Vec2) -> Vec2 {
-    vec2(
-        field.x + (base.x / BASE_W) * field.w,
-        field.y + (base.y / BASE_H) * field.h,
-    )
-}
-
 fn tree_scale_from_pos(pos: Vec2) -> f32 {
     let seed = (pos.x * 0.037 + pos.y * 0.051).sin().abs();
     0.8 + seed * 0.4
 }
 
-fn random_position(field: Rect, padding: f32) -> Vec2 {
+fn random_position(field: Rect, padding: f32, rng: &mut Rng) -> Vec2 {
     let min_x = field.x + padding;
     let max_x = field.x + field.w - padding;
     let min_y = field.y + padding;
@@ -269,12 +341,13 @@ fn random_position(field: Rect, padding: f32) -> Vec2 {
         return vec2(field.x + field.w * 0.5, field.y + field.h * 0.5);
     }
 
-    vec2(gen_range(min_x, max_x), gen_range(min_y, max_y))
+    vec2(rng.range(min_x, max_x), rng.range(min_y, max_y))
 }
 
-fn draw_tent(pos: Vec2, variant: u8) {
+fn draw_tent(pos: Vec2, variant: u8, scale: f32) {
     let color = TENT_COLORS[variant as usize % TENT_COLORS.len()];
-    let size = 28.0 * scale::MODEL_SCALE;
+    let s = scale::MODEL_SCALE * scale;
+    let size = 28.0 * s;
 
     draw_triangle(
         vec2(pos.x, pos.y - size * 0.9),
@@ -294,16 +367,16 @@ fn draw_tent(pos: Vec2, variant: u8) {
         vec2(pos.x, pos.y - size * 0.9),
         vec2(pos.x - size, pos.y),
         vec2(pos.x + size, pos.y),
-        1.5 * scale::MODEL_SCALE,
+        1.5 * s,
         Color::new(0.0, 0.0, 0.0, 0.35),
     );
 }
 
-fn draw_chair(pos: Vec2, rotation: f32) {
+fn draw_chair(pos: Vec2, rotation: f32, scale: f32) {
     let seat_color = Color::new(0.29, 0.33, 0.39, 1.0);
     let back_color = Color::new(0.18, 0.22, 0.28, 1.0);
     let leg_color = Color::new(0.12, 0.16, 0.20, 1.0);
-    let s = scale::MODEL_SCALE;
+    let s = scale::MODEL_SCALE * scale;
     let seat = vec2(20.0 * s, 12.0 * s);
     let back = vec2(20.0 * s, 10.0 * s);
 
@@ -365,8 +438,8 @@ fn draw_campfire(pos: Vec2, time: f32, scale: f32) {
     fire::draw_fire(fire::Fire::new(fire_pos, fire_size), time);
 }
 
-fn draw_crow_base(pos: Vec2, time: f32) {
-    let s = scale::MODEL_SCALE;
+fn draw_crow_base(pos: Vec2, time: f32, scale: f32) {
+    let s = scale::MODEL_SCALE * scale;
     let bottom_w = 280.0 * s;
     let top_w = 170.0 * s;
     let height = 140.0 * s;
@@ -428,13 +501,12 @@ fn draw_crow_base(pos: Vec2, time: f32) {
         inner_bl.x, inner_bl.y, inner_tl.x, inner_tl.y, inner_w, inner_edge,
     );
 
-    draw_crow_base_flame(tl, tr, bl, br, time);
-    draw_crow_base_runes(inner_tl, inner_tr, inner_bl, inner_br, time);
+    draw_crow_base_flame(tl, tr, bl, br, time, s);
+    draw_crow_base_runes(inner_tl, inner_tr, inner_bl, inner_br, time, s);
 }
 
-fn draw_crow_base_flame(tl: Vec2, tr: Vec2, bl: Vec2, br: Vec2, time: f32) {
+fn draw_crow_base_flame(tl: Vec2, tr: Vec2, bl: Vec2, br: Vec2, time: f32, s: f32) {
     let center = trapezoid_point(tl, tr, bl, br, 0.5, 0.58);
-    let s = scale::MODEL_SCALE;
     let width = 28.0 * s;
     let height = 36.0 * s;
     let flicker = (time * 3.2).sin() * 1.8 * s;
@@ -457,8 +529,8 @@ fn draw_crow_base_flame(tl: Vec2, tr: Vec2, bl: Vec2, br: Vec2, time: f32) {
     );
 }
 
-fn draw_crow(pos: Vec2, time: f32) {
-    let s = scale::MODEL_SCALE;
+fn draw_crow(pos: Vec2, time: f32, scale: f32) {
+    let s = scale::MODEL_SCALE * scale;
     let base_height = 140.0 * s;
     let perch = vec2(pos.x, pos.y - base_height - 16.0 * s);
 
@@ -586,7 +658,7 @@ fn draw_crow_glow_line(a: Vec2, b: Vec2, width: f32, core: Color, glow: Color) {
     draw_line(a.x, a.y, b.x, b.y, width, core);
 }
 
-fn draw_crow_base_runes(tl: Vec2, tr: Vec2, bl: Vec2, br: Vec2, time: f32) {
+fn draw_crow_base_runes(tl: Vec2, tr: Vec2, bl: Vec2, br: Vec2, time: f32, s: f32) {
     let rows = [(0.32, 7, 0.55, 1usize), (0.72, 10, 0.5, 5usize)];
 
     for (v, count, size_scale, seed) in rows {
@@ -595,11 +667,12 @@ fn draw_crow_base_runes(tl: Vec2, tr: Vec2, bl: Vec2, br: Vec2, time: f32) {
 
     let left = trapezoid_point(tl, tr, bl, br, 0.12, 0.68);
     let right = trapezoid_point(tl, tr, bl, br, 0.88, 0.68);
-    let side_size = vec2(14.0 * scale::MODEL_SCALE, 20.0 * scale::MODEL_SCALE);
+    let side_size = vec2(14.0 * s, 20.0 * s);
     draw_rune_glyph(6, left, side_size, time, 2.4);
     draw_rune_glyph(3, right, side_size, time, 4.1);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_rune_row(
     tl: Vec2,
     tr: Vec2,
@@ -757,12 +830,13 @@ fn draw_tree(pos: Vec2, scale: f32) {
     );
 }
 
-fn draw_geodesic_dome(center: Vec2, time: f32, decorations: &[DomeDecoration]) {
-    let radius = DOME_RADIUS;
-    let height = DOME_HEIGHT;
-    let squash = 0.4;
-    let line_w = 1.0 * scale::MODEL_SCALE;
-    let rim = 5.0 * scale::MODEL_SCALE;
+fn draw_geodesic_dome(center: Vec2, time: f32, decorations: &[DomeDecoration], scale: f32) {
+    let radius = DOME_RADIUS * scale;
+    let height = DOME_HEIGHT * scale;
+    let squash = DOME_SQUASH;
+    let s = scale::MODEL_SCALE * scale;
+    let line_w = 1.0 * s;
+    let rim = 5.0 * s;
 
     #[derive(Clone, Copy)]
     struct DomeVertex {
@@ -826,6 +900,7 @@ fn draw_geodesic_dome(center: Vec2, time: f32, decorations: &[DomeDecoration]) {
         }
     };
 
+    draw_dome_doorway(center, scale);
     ring(&base, &mut edge);
     ring(&mid, &mut edge);
     ring(&top, &mut edge);
@@ -837,7 +912,7 @@ fn draw_geodesic_dome(center: Vec2, time: f32, decorations: &[DomeDecoration]) {
 
     draw_ellipse_lines(
         center.x,
-        center.y + 2.0 * scale::MODEL_SCALE,
+        center.y + 2.0 * s,
         radius + rim,
         (radius + rim) * squash,
         0.0,
@@ -846,14 +921,35 @@ fn draw_geodesic_dome(center: Vec2, time: f32, decorations: &[DomeDecoration]) {
     );
 
     if decorations.contains(&DomeDecoration::Crystal) {
-        draw_big_red_crystal(vec2(center.x, center.y - height * 0.35), time);
+        draw_big_red_crystal(vec2(center.x, center.y - height * 0.35), time, scale);
     }
 }
 
-fn draw_big_red_crystal(center: Vec2, time: f32) {
+fn draw_dome_doorway(center: Vec2, scale: f32) {
+    let half_width = DOME_RADIUS * scale * DOME_DOOR_HALF_ANGLE.sin();
+    let sill = center.y + DOME_RADIUS * scale * DOME_SQUASH * DOME_DOOR_HALF_ANGLE.cos();
+    let height = DOME_HEIGHT * scale * 0.4;
+    let color = Color::new(0.02, 0.03, 0.06, 0.55);
+
+    draw_rectangle(
+        center.x - half_width,
+        sill - height,
+        half_width * 2.0,
+        height,
+        color,
+    );
+    draw_triangle(
+        vec2(center.x - half_width, sill - height),
+        vec2(center.x + half_width, sill - height),
+        vec2(center.x, sill - height - half_width * 0.6),
+        color,
+    );
+}
+
+fn draw_big_red_crystal(center: Vec2, time: f32, scale: f32) {
     let pulse = ((time * 1.1).sin() + 1.0) * 0.5;
     let glow_alpha = 0.18 + pulse * 0.12;
-    let s = scale::MODEL_SCALE * CRYSTAL_SCALE;
+    let s = scale::MODEL_SCALE * CRYSTAL_SCALE * scale;
 
     let tip_h = 22.0 * s;
     let body_h = 56.0 * s;
@@ -1032,7 +1128,12 @@ mod tests {
         let field = Rect::new(0.0, 0.0, 10000.0, 7000.0);
         let camps = camps::camp_configs();
         let camp_spawns = camps::collect_scenery_spawns(&camps);
-        let items = spawn_scenery(field, &camp_spawns);
+        let items = spawn_scenery(
+            field,
+            &SceneryLayout::builtin(),
+            &camp_spawns,
+            &mut Rng::new(1),
+        );
 
         let tents = items.iter().filter(|i| i.kind == SceneryKind::Tent).count();
         let chairs = items
@@ -1084,7 +1185,12 @@ mod tests {
         let field = Rect::new(0.0, 0.0, 10000.0, 7000.0);
         let camps = camps::camp_configs();
         let camp_spawns = camps::collect_scenery_spawns(&camps);
-        let items = spawn_scenery(field, &camp_spawns);
+        let items = spawn_scenery(
+            field,
+            &SceneryLayout::builtin(),
+            &camp_spawns,
+            &mut Rng::new(1),
+        );
 
         for item in items {
             assert!(item.pos.x >= field.x && item.pos.x <= field.x + field.w);
@@ -1105,4 +1211,44 @@ mod tests {
         assert!(diameter >= player::PLAYER_WIDTH * 4.0);
         assert!(DOME_HEIGHT >= player::PLAYER_HEIGHT * 2.0);
     }
+
+    #[test]
+    fn builtin_layout_round_trips_and_fits_the_map() {
+        let layout = SceneryLayout::builtin();
+        layout
+            .validate(Rect::new(0.0, 0.0, 9216.0, 6144.0))
+            .expect("layout inside map");
+        let text = layout.to_toml().expect("serialize layout");
+        assert_eq!(
+            SceneryLayout::from_toml(&text).expect("parse layout"),
+            layout
+        );
+
+        let mut stray = layout.clone();
+        stray
+            .items
+            .push(ScenerySpawn::new(SceneryKind::Chair, vec2(-1.0, 0.0)));
+        assert!(matches!(
+            stray.validate(Rect::new(0.0, 0.0, 9216.0, 6144.0)),
+            Err(SceneryError::OutOfMap {
+                kind: SceneryKind::Chair,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn replace_layout_items_keeps_generated_scenery() {
+        let layout = SceneryLayout::builtin();
+        let camp_spawns = vec![ScenerySpawn::tent(vec2(10.0, 10.0), 1)];
+        let field = Rect::new(0.0, 0.0, 9216.0, 6144.0);
+        let mut items = spawn_scenery(field, &layout, &camp_spawns, &mut Rng::new(3));
+        let generated = items[layout.items.len()..].to_vec();
+
+        let edited = vec![ScenerySpawn::new(SceneryKind::Tree, vec2(50.0, 60.0))];
+        replace_layout_items(&mut items, layout.items.len(), &edited);
+        assert_eq!(items.len(), 1 + generated.len());
+        assert_eq!(items[0].kind, SceneryKind::Tree);
+        assert_eq!(&items[1..], generated.as_slice());
+    }
 }
@@ -0,0 +1,250 @@
+use macroquad::prelude::*;
+
+use crate::scenery::{
+    DomeDecoration, SceneryKind, SceneryLayout, ScenerySpawn, ALL_SCENERY_KINDS, TENT_VARIANT_COUNT,
+};
+
+pub const SCENERY_SCALE_MIN: f32 = 0.25;
+pub const SCENERY_SCALE_MAX: f32 = 4.0;
+
+/// Editing state for the scenery layout. Clicking empty ground places an
+/// item of the current `kind`; clicking an item selects and drags it.
+#[derive(Clone, Debug)]
+pub struct SceneryEditor {
+    pub items: Vec<ScenerySpawn>,
+    pub random_domes: usize,
+    pub kind: SceneryKind,
+    pub selected: Option<usize>,
+    dragging: Option<Vec2>,
+}
+
+impl SceneryEditor {
+    pub fn new(layout: &SceneryLayout) -> Self {
+        Self {
+            items: layout.items.clone(),
+            random_domes: layout.random_domes,
+            kind: SceneryKind::Tent,
+            selected: None,
+            dragging: None,
+        }
+    }
+
+    pub fn to_layout(&self) -> SceneryLayout {
+        SceneryLayout {
+            random_domes: self.random_domes,
+            items: self.items.clone(),
+        }
+    }
+
+    pub fn selected_item(&self) -> Option<&ScenerySpawn> {
+        self.selected.and_then(|index| self.items.get(index))
+    }
+
+    /// Nearest item within `radius` of `pos`.
+    pub fn item_at(&self, pos: Vec2, radius: f32) -> Option<usize> {
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| (index, item.pos.distance(pos)))
+            .filter(|(_, distance)| *distance <= radius)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index)
+    }
+
+    /// Selects and grabs the item under the cursor, or places a new one.
+    /// Returns `true` if the layout changed.
+    pub fn press(&mut self, pos: Vec2, radius: f32) -> bool {
+        match self.item_at(pos, radius) {
+            Some(index) => {
+                self.selected = Some(index);
+                self.dragging = Some(pos - self.items[index].pos);
+                false
+            }
+            None => {
+                self.items.push(ScenerySpawn::new(self.kind, pos));
+                self.selected = Some(self.items.len() - 1);
+                self.dragging = Some(Vec2::ZERO);
+                true
+            }
+        }
+    }
+
+    /// Moves the grabbed item, keeping the offset from where it was grabbed.
+    pub fn drag_to(&mut self, pos: Vec2) -> bool {
+        match (self.dragging, self.selected) {
+            (Some(offset), Some(index)) if self.items[index].pos != pos - offset => {
+                self.items[index].pos = pos - offset;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn release(&mut self) {
+        self.dragging = None;
+    }
+
+    pub fn delete_at(&mut self, pos: Vec2, radius: f32) -> bool {
+        match self.item_at(pos, radius) {
+            Some(index) => {
+                self.delete_item(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn delete_selected(&mut self) -> bool {
+        match self.selected {
+            Some(index) => {
+                self.delete_item(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Changes the kind placed by the next click.
+    pub fn cycle_kind(&mut self, step: isize) {
+        let count = ALL_SCENERY_KINDS.len() as isize;
+        let current = ALL_SCENERY_KINDS
+            .iter()
+            .position(|kind| *kind == self.kind)
+            .unwrap_or(0) as isize;
+        self.kind = ALL_SCENERY_KINDS[(current + step).rem_euclid(count) as usize];
+    }
+
+    /// Turns the selected item. Kinds that are drawn upright are left as
+    /// they are.
+    pub fn rotate_selected(&mut self, radians: f32) -> bool {
+        self.edit_selected(|item| {
+            if item.kind.rotates() {
+                item.rotation += radians;
+            }
+        })
+    }
+
+    pub fn scale_selected(&mut self, factor: f32) -> bool {
+        self.edit_selected(|item| {
+            item.scale = (item.scale * factor).clamp(SCENERY_SCALE_MIN, SCENERY_SCALE_MAX);
+        })
+    }
+
+    pub fn cycle_variant(&mut self) -> bool {
+        self.edit_selected(|item| {
+            if item.kind == SceneryKind::Tent {
+                item.variant = (item.variant + 1) % TENT_VARIANT_COUNT;
+            }
+        })
+    }
+
+    /// Adds or removes a decoration on the selected dome.
+    pub fn toggle_decoration(&mut self, decoration: DomeDecoration) -> bool {
+        self.edit_selected(|item| {
+            if item.kind != SceneryKind::Dome {
+                return;
+            }
+            match item.decorations.iter().position(|d| *d == decoration) {
+                Some(index) => {
+                    item.decorations.remove(index);
+                }
+                None => item.decorations.push(decoration),
+            }
+        })
+    }
+
+    fn edit_selected(&mut self, edit: impl FnOnce(&mut ScenerySpawn)) -> bool {
+        let Some(item) = self.selected.and_then(|index| self.items.get_mut(index)) else {
+            return false;
+        };
+        let before = item.clone();
+        edit(item);
+        *item != before
+    }
+
+    fn delete_item(&mut self, index: usize) {
+        self.items.remove(index);
+        self.dragging = None;
+        self.selected = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_editor() -> SceneryEditor {
+        SceneryEditor::new(&SceneryLayout::default())
+    }
+
+    #[test]
+    fn clicks_place_select_and_drag() {
+        let mut editor = empty_editor();
+        editor.cycle_kind(-1);
+        assert_eq!(editor.kind, SceneryKind::Tree);
+        assert!(editor.press(vec2(100.0, 100.0), 5.0));
+        editor.release();
+        assert_eq!(editor.items[0].kind, SceneryKind::Tree);
+
+        assert!(!editor.press(vec2(103.0, 100.0), 5.0));
+        assert_eq!(editor.selected, Some(0));
+        assert!(editor.drag_to(vec2(113.0, 120.0)));
+        editor.release();
+        assert_eq!(editor.items[0].pos, vec2(110.0, 120.0));
+        assert_eq!(editor.items.len(), 1);
+    }
+
+    #[test]
+    fn edits_apply_to_selected_item() {
+        let mut editor = empty_editor();
+        editor.press(vec2(0.0, 0.0), 5.0);
+        editor.release();
+        assert!(!editor.rotate_selected(0.5));
+        assert!(editor.scale_selected(100.0));
+        for _ in 0..TENT_VARIANT_COUNT {
+            editor.cycle_variant();
+        }
+        let tent = editor.selected_item().expect("selected tent");
+        assert_eq!(tent.rotation, 0.0);
+        assert_eq!(tent.scale, SCENERY_SCALE_MAX);
+        assert_eq!(tent.variant, 0);
+        assert!(!editor.toggle_decoration(DomeDecoration::Crystal));
+
+        editor.kind = SceneryKind::Dome;
+        editor.press(vec2(200.0, 0.0), 5.0);
+        assert!(editor.toggle_decoration(DomeDecoration::Crystal));
+        assert_eq!(editor.items[1].decorations, vec![DomeDecoration::Crystal]);
+        assert!(editor.toggle_decoration(DomeDecoration::Crystal));
+        assert!(editor.items[1].decorations.is_empty());
+    }
+
+    #[test]
+    fn only_rotating_kinds_turn() {
+        let mut editor = empty_editor();
+        editor.kind = SceneryKind::Dome;
+        editor.press(vec2(0.0, 0.0), 5.0);
+        editor.release();
+        assert!(!editor.rotate_selected(0.5));
+        assert_eq!(editor.items[0].rotation, 0.0);
+
+        editor.kind = SceneryKind::Chair;
+        editor.press(vec2(200.0, 0.0), 5.0);
+        editor.release();
+        assert!(editor.rotate_selected(0.5));
+        assert_eq!(editor.items[1].rotation, 0.5);
+    }
+
+    #[test]
+    fn deleting_and_exporting_layout() {
+        let layout = SceneryLayout::builtin();
+        let mut editor = SceneryEditor::new(&layout);
+        assert_eq!(editor.to_layout(), layout);
+
+        let first = editor.items[0].pos;
+        assert!(editor.delete_at(first, 1.0));
+        assert!(!editor.delete_selected());
+        let exported = editor.to_layout();
+        assert_eq!(exported.items.len(), layout.items.len() - 1);
+        assert_eq!(exported.random_domes, layout.random_domes);
+    }
+}
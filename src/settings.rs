@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+use crate::constants::{CAMERA_ZOOM_MAX, CAMERA_ZOOM_MIN};
+
+pub const SETTINGS_PATH: &str = "config/settings.toml";
+pub const ZOOM_LIMIT_FLOOR: f32 = 0.1;
+pub const ZOOM_LIMIT_CEIL: f32 = 20.0;
+
+/// Player-facing options edited from the settings scene. Missing fields in
+/// the file fall back to the defaults.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub zoom_min: f32,
+    pub zoom_max: f32,
+    pub volume: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            zoom_min: CAMERA_ZOOM_MIN,
+            zoom_max: CAMERA_ZOOM_MAX,
+            volume: 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(err) => write!(f, "settings io error: {}", err),
+            SettingsError::Parse(err) => write!(f, "settings parse error: {}", err),
+            SettingsError::Serialize(err) => write!(f, "settings serialize error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<std::io::Error> for SettingsError {
+    fn from(err: std::io::Error) -> Self {
+        SettingsError::Io(err)
+    }
+}
+
+impl Settings {
+    pub fn clamp_zoom(&self, zoom: f32) -> f32 {
+        zoom.clamp(self.zoom_min, self.zoom_max)
+    }
+
+    /// Keeps values usable after hand edits: limits stay positive and
+    /// ordered, volume stays in `[0, 1]`.
+    pub fn sanitized(mut self) -> Self {
+        let finite_or = |v: f32, fallback: f32| if v.is_finite() { v } else { fallback };
+        let defaults = Self::default();
+        self.zoom_min =
+            finite_or(self.zoom_min, defaults.zoom_min).clamp(ZOOM_LIMIT_FLOOR, ZOOM_LIMIT_CEIL);
+        self.zoom_max =
+            finite_or(self.zoom_max, defaults.zoom_max).clamp(ZOOM_LIMIT_FLOOR, ZOOM_LIMIT_CEIL);
+        if self.zoom_max < self.zoom_min {
+            std::mem::swap(&mut self.zoom_min, &mut self.zoom_max);
+        }
+        self.volume = finite_or(self.volume, defaults.volume).clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn to_toml(&self) -> Result<String, SettingsError> {
+        toml::to_string(self).map_err(SettingsError::Serialize)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, SettingsError> {
+        let settings: Self = toml::from_str(text).map_err(SettingsError::Parse)?;
+        Ok(settings.sanitized())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SettingsError> {
+        let text = std::fs::read_to_string(path)?;
+        Self::from_toml(&text)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SettingsError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+
+    /// Loads the settings file, or returns the defaults if it is missing or
+    /// broken. Only a broken file reports an error.
+    pub fn load_or_default<P: AsRef<Path>>(path: P) -> (Self, Option<SettingsError>) {
+        let path = path.as_ref();
+        if !path.exists() {
+            return (Self::default(), None);
+        }
+        match Self::load(path) {
+            Ok(settings) => (settings, None),
+            Err(err) => (Self::default(), Some(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toml_round_trip_preserves_settings() {
+        let settings = Settings {
+            zoom_min: 0.5,
+            zoom_max: 6.0,
+            volume: 0.3,
+        };
+        let text = settings.to_toml().expect("serialize settings");
+        assert_eq!(
+            Settings::from_toml(&text).expect("parse settings"),
+            settings
+        );
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let settings = Settings::from_toml("volume = 0.5\n").expect("parse settings");
+        assert_eq!(settings.zoom_min, CAMERA_ZOOM_MIN);
+        assert_eq!(settings.zoom_max, CAMERA_ZOOM_MAX);
+        assert_eq!(settings.volume, 0.5);
+    }
+
+    #[test]
+    fn sanitized_orders_limits_and_clamps_volume() {
+        let settings = Settings {
+            zoom_min: 8.0,
+            zoom_max: 2.0,
+            volume: 3.0,
+        }
+        .sanitized();
+        assert_eq!(settings.zoom_min, 2.0);
+        assert_eq!(settings.zoom_max, 8.0);
+        assert_eq!(settings.volume, 1.0);
+        assert_eq!(settings.clamp_zoom(20.0), 8.0);
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+
+use macroquad::prelude::*;
+
+type CellKey = (i32, i32);
+type Buckets = HashMap<CellKey, Vec<usize>, BuildHasherDefault<CellHasher>>;
+
+/// Multiplicative hash for cell keys. The default SipHash is built to
+/// resist hostile keys and costs more than the rest of a query.
+#[derive(Default)]
+struct CellHasher(u64);
+
+impl Hasher for CellHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_u64(u64::from(*byte));
+        }
+    }
+
+    fn write_i32(&mut self, value: i32) {
+        self.write_u64(value as u32 as u64);
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.0 = (self.0.rotate_left(5) ^ value).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+}
+
+/// Uniform grid over item positions, indexed like the `Vec` the items live
+/// in. Mutations mirror the `Vec` operations (`push`, `swap_remove`) so the
+/// two stay in step, and `within` only visits the cells a query touches.
+#[derive(Clone, Debug)]
+pub struct SpatialHash {
+    cell_size: f32,
+    positions: Vec<Vec2>,
+    buckets: Buckets,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size: cell_size.max(f32::EPSILON),
+            positions: Vec::new(),
+            buckets: Buckets::default(),
+        }
+    }
+
+    pub fn from_positions(cell_size: f32, positions: impl IntoIterator<Item = Vec2>) -> Self {
+        let mut hash = Self::new(cell_size);
+        for pos in positions {
+            hash.push(pos);
+        }
+        hash
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn position(&self, index: usize) -> Vec2 {
+        self.positions[index]
+    }
+
+    /// Adds an item at the next index.
+    pub fn push(&mut self, pos: Vec2) {
+        let index = self.positions.len();
+        self.positions.push(pos);
+        self.buckets.entry(self.key(pos)).or_default().push(index);
+    }
+
+    /// Removes `index`, moving the last item into its place like
+    /// `Vec::swap_remove`.
+    pub fn swap_remove(&mut self, index: usize) {
+        let last = self.positions.len() - 1;
+        self.unlink(index, self.positions[index]);
+        if index != last {
+            let moved = self.positions[last];
+            self.unlink(last, moved);
+            self.buckets.entry(self.key(moved)).or_default().push(index);
+        }
+        self.positions.swap_remove(index);
+    }
+
+    /// Moves `index` to `pos`, touching the buckets only when it changes
+    /// cell.
+    pub fn set_position(&mut self, index: usize, pos: Vec2) {
+        let old = self.positions[index];
+        if self.key(old) != self.key(pos) {
+            self.unlink(index, old);
+            self.buckets.entry(self.key(pos)).or_default().push(index);
+        }
+        self.positions[index] = pos;
+    }
+
+    /// Brings the index up to date with `positions`, growing or shrinking
+    /// at the end as needed.
+    pub fn sync(&mut self, positions: impl IntoIterator<Item = Vec2>) {
+        let mut count = 0;
+        for (index, pos) in positions.into_iter().enumerate() {
+            if index < self.positions.len() {
+                self.set_position(index, pos);
+            } else {
+                self.push(pos);
+            }
+            count = index + 1;
+        }
+        while self.positions.len() > count {
+            self.swap_remove(self.positions.len() - 1);
+        }
+    }
+
+    /// Indices of the items within `radius` of `center`, in ascending order
+    /// so callers break ties the same way a linear scan would.
+    pub fn within(&self, center: Vec2, radius: f32) -> Vec<usize> {
+        let mut found = Vec::new();
+        let (min, max) = (
+            self.key(center - Vec2::splat(radius)),
+            self.key(center + Vec2::splat(radius)),
+        );
+        let radius_sq = radius * radius;
+        for y in min.1..=max.1 {
+            for x in min.0..=max.0 {
+                let Some(bucket) = self.buckets.get(&(x, y)) else {
+                    continue;
+                };
+                found.extend(
+                    bucket.iter().copied().filter(|index| {
+                        self.positions[*index].distance_squared(center) <= radius_sq
+                    }),
+                );
+            }
+        }
+        found.sort_unstable();
+        found
+    }
+
+    fn key(&self, pos: Vec2) -> CellKey {
+        let cell = (pos / self.cell_size).floor();
+        (cell.x as i32, cell.y as i32)
+    }
+
+    fn unlink(&mut self, index: usize, pos: Vec2) {
+        let key = self.key(pos);
+        if let Some(bucket) = self.buckets.get_mut(&key) {
+            if let Some(slot) = bucket.iter().position(|item| *item == index) {
+                bucket.swap_remove(slot);
+            }
+            if bucket.is_empty() {
+                self.buckets.remove(&key);
+            }
+        }
+    }
+}
+
+impl Default for SpatialHash {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    fn brute_force(positions: &[Vec2], center: Vec2, radius: f32) -> Vec<usize> {
+        (0..positions.len())
+            .filter(|index| positions[*index].distance_squared(center) <= radius * radius)
+            .collect()
+    }
+
+    #[test]
+    fn within_matches_a_linear_scan() {
+        let mut rng = Rng::new(3);
+        let positions: Vec<Vec2> = (0..300)
+            .map(|_| vec2(rng.range(-200.0, 200.0), rng.range(-200.0, 200.0)))
+            .collect();
+        let hash = SpatialHash::from_positions(25.0, positions.iter().copied());
+        for _ in 0..50 {
+            let center = vec2(rng.range(-250.0, 250.0), rng.range(-250.0, 250.0));
+            let radius = rng.range(0.0, 80.0);
+            assert_eq!(
+                hash.within(center, radius),
+                brute_force(&positions, center, radius)
+            );
+        }
+    }
+
+    #[test]
+    fn edits_mirror_vec_operations() {
+        let mut positions = vec![vec2(0.0, 0.0), vec2(50.0, 0.0), vec2(100.0, 0.0)];
+        let mut hash = SpatialHash::from_positions(10.0, positions.iter().copied());
+
+        positions.swap_remove(0);
+        hash.swap_remove(0);
+        assert_eq!(hash.within(vec2(100.0, 0.0), 1.0), vec![0]);
+        assert!(hash.within(Vec2::ZERO, 1.0).is_empty());
+
+        positions[1] = vec2(5.0, 5.0);
+        positions.push(vec2(-30.0, 0.0));
+        hash.sync(positions.iter().copied());
+        assert_eq!(hash.len(), 3);
+        assert_eq!(hash.within(Vec2::ZERO, 10.0), vec![1]);
+        assert_eq!(hash.within(vec2(-30.0, 0.0), 1.0), vec![2]);
+        hash.sync(positions[..1].iter().copied());
+        assert_eq!(hash.within(Vec2::ZERO, 1000.0), vec![0]);
+    }
+}
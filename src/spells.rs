@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::tuning::SpellTuning;
+
+/// Flagic spells the Vexillomancer can cast.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Spell {
+    /// Angry hippies nearby forget about the player.
+    Calm,
+    /// The nearest ground flag in range flies back to the inventory.
+    Summon,
+    /// The wind turns.
+    Gust,
+    /// A short burst of speed.
+    Haste,
+}
+
+pub const ALL_SPELLS: [Spell; 4] = [Spell::Calm, Spell::Summon, Spell::Gust, Spell::Haste];
+
+impl Spell {
+    pub fn name(self) -> &'static str {
+        match self {
+            Spell::Calm => "Calm",
+            Spell::Summon => "Summon",
+            Spell::Gust => "Gust",
+            Spell::Haste => "Haste",
+        }
+    }
+
+    /// Slot on the spell bar.
+    pub fn index(self) -> usize {
+        match self {
+            Spell::Calm => 0,
+            Spell::Summon => 1,
+            Spell::Gust => 2,
+            Spell::Haste => 3,
+        }
+    }
+
+    pub fn from_index(index: usize) -> Option<Self> {
+        ALL_SPELLS.get(index).copied()
+    }
+
+    pub fn cost(self, tuning: &SpellTuning) -> u8 {
+        match self {
+            Spell::Calm => tuning.calm_cost,
+            Spell::Summon => tuning.summon_cost,
+            Spell::Gust => tuning.gust_cost,
+            Spell::Haste => tuning.haste_cost,
+        }
+    }
+
+    /// Seconds before the spell can be cast again.
+    pub fn cooldown(self, tuning: &SpellTuning) -> f32 {
+        match self {
+            Spell::Calm => tuning.calm_cooldown,
+            Spell::Summon => tuning.summon_cooldown,
+            Spell::Gust => tuning.gust_cooldown,
+            Spell::Haste => tuning.haste_cooldown,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CastError {
+    /// Only the Vexillomancer knows any spells.
+    WrongClass,
+    CoolingDown,
+    NotEnoughFlagic,
+    /// Nothing in range for the spell to act on.
+    NoTarget,
+}
+
+impl fmt::Display for CastError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CastError::WrongClass => write!(f, "this class cannot cast spells"),
+            CastError::CoolingDown => write!(f, "spell is cooling down"),
+            CastError::NotEnoughFlagic => write!(f, "not enough flagic"),
+            CastError::NoTarget => write!(f, "nothing in range"),
+        }
+    }
+}
+
+impl std::error::Error for CastError {}
+
+/// Cooldowns left per spell and the effects still running.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Spellbook {
+    /// Seconds left per spell, by `Spell::index`.
+    pub cooldowns: [f32; ALL_SPELLS.len()],
+    /// Seconds of haste left.
+    pub haste_timer: f32,
+}
+
+impl Spellbook {
+    pub fn cooldown_left(&self, spell: Spell) -> f32 {
+        self.cooldowns[spell.index()]
+    }
+
+    pub fn tick(&mut self, dt: f32) {
+        for cooldown in &mut self.cooldowns {
+            *cooldown = (*cooldown - dt).max(0.0);
+        }
+        self.haste_timer = (self.haste_timer - dt).max(0.0);
+    }
+
+    /// Whether `spell` can be cast with `flagic` in the pool right now.
+    pub fn check(&self, spell: Spell, flagic: u8, tuning: &SpellTuning) -> Result<(), CastError> {
+        if self.cooldown_left(spell) > 0.0 {
+            return Err(CastError::CoolingDown);
+        }
+        if flagic < spell.cost(tuning) {
+            return Err(CastError::NotEnoughFlagic);
+        }
+        Ok(())
+    }
+
+    /// Pays for a cast that `check` allowed and starts its cooldown.
+    pub fn commit(&mut self, spell: Spell, flagic: &mut u8, tuning: &SpellTuning) {
+        *flagic = flagic.saturating_sub(spell.cost(tuning));
+        self.cooldowns[spell.index()] = spell.cooldown(tuning);
+        if spell == Spell::Haste {
+            self.haste_timer = tuning.haste_duration;
+        }
+    }
+
+    /// What the player's speed is multiplied by.
+    pub fn speed_factor(&self, tuning: &SpellTuning) -> f32 {
+        if self.haste_timer > 0.0 {
+            tuning.haste_multiplier
+        } else {
+            1.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn casting_costs_flagic_and_starts_cooldown() {
+        let tuning = SpellTuning::default();
+        let mut book = Spellbook::default();
+        let mut flagic = tuning.haste_cost + 1;
+        assert_eq!(book.check(Spell::Haste, flagic, &tuning), Ok(()));
+        book.commit(Spell::Haste, &mut flagic, &tuning);
+        assert_eq!(flagic, 1);
+        assert_eq!(book.cooldown_left(Spell::Haste), tuning.haste_cooldown);
+        assert_eq!(book.speed_factor(&tuning), tuning.haste_multiplier);
+        assert_eq!(
+            book.check(Spell::Haste, 100, &tuning),
+            Err(CastError::CoolingDown)
+        );
+        assert_eq!(
+            book.check(Spell::Calm, 1, &tuning),
+            Err(CastError::NotEnoughFlagic)
+        );
+    }
+
+    #[test]
+    fn cooldowns_and_haste_run_out() {
+        let tuning = SpellTuning::default();
+        let mut book = Spellbook::default();
+        let mut flagic = 100;
+        book.commit(Spell::Haste, &mut flagic, &tuning);
+        book.commit(Spell::Gust, &mut flagic, &tuning);
+        book.tick(tuning.haste_duration);
+        assert_eq!(book.speed_factor(&tuning), 1.0);
+        assert_eq!(book.cooldown_left(Spell::Gust), 0.0);
+        assert!(book.cooldown_left(Spell::Haste) > 0.0);
+        book.tick(tuning.haste_cooldown);
+        assert_eq!(book.check(Spell::Haste, flagic, &tuning), Ok(()));
+    }
+
+    #[test]
+    fn spells_round_trip_through_their_index() {
+        for spell in ALL_SPELLS {
+            assert_eq!(Spell::from_index(spell.index()), Some(spell));
+        }
+        assert_eq!(Spell::from_index(ALL_SPELLS.len()), None);
+    }
+}
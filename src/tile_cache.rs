@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::SystemTime;
+
+use crate::assets::{self, AssetError, RasterImage};
+use crate::map::TileKey;
+
+/// Fixed-capacity map that evicts the least recently used entry. Capacities
+/// are small (a screenful of tiles), so eviction scans instead of keeping an
+/// ordered list.
+#[derive(Debug)]
+pub struct LruCache<K, V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<K, (V, u64)>,
+}
+
+impl<K: Eq + Hash + Clone, V> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            tick: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Looks up `key` and marks it as most recently used.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        self.tick += 1;
+        let tick = self.tick;
+        self.entries.get_mut(key).map(|(value, used)| {
+            *used = tick;
+            &*value
+        })
+    }
+
+    /// Inserts `value` as most recently used and returns whatever had to be
+    /// evicted to make room.
+    pub fn insert(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        self.tick += 1;
+        self.entries.insert(key, (value, self.tick));
+        self.evict_to(self.capacity)
+    }
+
+    /// Changes the capacity, evicting down to it if needed.
+    pub fn set_capacity(&mut self, capacity: usize) -> Vec<(K, V)> {
+        self.capacity = capacity.max(1);
+        self.evict_to(self.capacity)
+    }
+
+    fn evict_to(&mut self, capacity: usize) -> Vec<(K, V)> {
+        let mut evicted = Vec::new();
+        while self.entries.len() > capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone())
+                .expect("cache over capacity is not empty");
+            let (value, _) = self.entries.remove(&oldest).expect("oldest key exists");
+            evicted.push((oldest, value));
+        }
+        evicted
+    }
+}
+
+/// A tile the loader finished with.
+#[derive(Debug)]
+pub struct LoadedTile {
+    pub key: TileKey,
+    pub raster: Result<RasterImage, AssetError>,
+}
+
+/// Decodes tile PNGs on a background thread, building coarser levels that
+/// are missing from disk. Textures still have to be created on the main
+/// thread, so results come back as raw pixels.
+#[derive(Debug)]
+pub struct TileLoader {
+    requests: Sender<TileKey>,
+    results: Receiver<LoadedTile>,
+}
+
+impl TileLoader {
+    pub fn spawn(dir: PathBuf, tile_px: u16) -> Self {
+        let (requests, request_rx) = mpsc::channel::<TileKey>();
+        let (result_tx, results) = mpsc::channel();
+        thread::Builder::new()
+            .name("tile-loader".to_string())
+            .spawn(move || {
+                // Ends once the map drops its sender.
+                for key in request_rx {
+                    let raster = load_or_build_tile(&dir, key, tile_px);
+                    if result_tx.send(LoadedTile { key, raster }).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("Failed to spawn tile loader thread");
+        Self { requests, results }
+    }
+
+    pub fn request(&self, key: TileKey) {
+        // A dead worker only means tiles stay as placeholders.
+        let _ = self.requests.send(key);
+    }
+
+    /// Finished tiles, without blocking.
+    pub fn poll(&self) -> Vec<LoadedTile> {
+        self.results.try_iter().collect()
+    }
+}
+
+/// Reads the tile for `key` from `dir`. A coarser tile that is missing,
+/// unreadable or older than any base tile under it is built from its four
+/// children and written back, so each level is only regenerated after the
+/// base tiles change.
+pub fn load_or_build_tile(
+    dir: &Path,
+    key: TileKey,
+    tile_px: u16,
+) -> Result<RasterImage, AssetError> {
+    let path = dir.join(key.file_name());
+    let stale = key.level > 0 && path.exists() && modified(&path) < newest_base_tile(dir, key);
+    if stale {
+        eprintln!(
+            "rebuilding tile {}: it is older than its base tiles",
+            path.display()
+        );
+    }
+    if path.exists() && !stale {
+        let loaded = assets::load_png_rgba(&path).and_then(|raster| {
+            if raster.width == tile_px && raster.height == tile_px {
+                Ok(raster)
+            } else {
+                Err(AssetError::TileSize {
+                    path: path.clone(),
+                    expected: tile_px,
+                    width: raster.width as u32,
+                    height: raster.height as u32,
+                })
+            }
+        });
+        match loaded {
+            Ok(raster) => return Ok(raster),
+            Err(err) if key.level == 0 => return Err(err),
+            Err(err) => eprintln!(
+                "rebuilding tile {}: the saved file is unusable: {}",
+                path.display(),
+                err
+            ),
+        }
+    }
+    if key.level == 0 {
+        return Err(AssetError::MissingTile(path));
+    }
+
+    let children = key
+        .children()
+        .map(|child| load_or_build_tile(dir, child, tile_px).ok());
+    if children.iter().all(Option::is_none) {
+        return Err(AssetError::MissingTile(path));
+    }
+    let raster = downsample_quad(&children, tile_px);
+    if let Err(err) = assets::save_png_rgba(&path, &raster) {
+        eprintln!("failed to save tile {}: {}", path.display(), err);
+    }
+    Ok(raster)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+/// Modification time of the newest base tile covered by `key`.
+fn newest_base_tile(dir: &Path, key: TileKey) -> Option<SystemTime> {
+    if key.level == 0 {
+        return modified(&dir.join(key.file_name()));
+    }
+    key.children()
+        .into_iter()
+        .filter_map(|child| newest_base_tile(dir, child))
+        .max()
+}
+
+/// Halves four `tile_px` tiles (in reading order) into one tile of the same
+/// size, averaging 2x2 blocks. Missing children stay transparent.
+pub fn downsample_quad(children: &[Option<RasterImage>; 4], tile_px: u16) -> RasterImage {
+    let size = tile_px as usize;
+    let half = size / 2;
+    let mut pixels = vec![0u8; size * size * 4];
+    for (quadrant, child) in children.iter().enumerate() {
+        let Some(child) = child else {
+            continue;
+        };
+        let (offset_x, offset_y) = ((quadrant % 2) * half, (quadrant / 2) * half);
+        for y in 0..half {
+            for x in 0..half {
+                let out = ((offset_y + y) * size + offset_x + x) * 4;
+                for channel in 0..4 {
+                    let sum: u32 = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                        .iter()
+                        .map(|(dx, dy)| {
+                            let src = ((y * 2 + dy) * size + x * 2 + dx) * 4 + channel;
+                            child.pixels[src] as u32
+                        })
+                        .sum();
+                    pixels[out + channel] = ((sum + 2) / 4) as u8;
+                }
+            }
+        }
+    }
+    RasterImage {
+        width: tile_px,
+        height: tile_px,
+        pixels,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn lru_evicts_least_recently_used() {
+        let mut cache = LruCache::new(2);
+        assert!(cache.insert(1, "a").is_empty());
+        assert!(cache.insert(2, "b").is_empty());
+        assert_eq!(cache.get(&1), Some(&"a"));
+        assert_eq!(cache.insert(3, "c"), vec![(2, "b")]);
+        assert!(cache.contains(&1));
+        assert!(cache.contains(&3));
+
+        assert_eq!(cache.set_capacity(1), vec![(1, "a")]);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&2), None);
+    }
+
+    fn solid_tile(tile_px: u16, rgba: [u8; 4]) -> RasterImage {
+        RasterImage {
+            width: tile_px,
+            height: tile_px,
+            pixels: rgba.repeat(tile_px as usize * tile_px as usize),
+        }
+    }
+
+    #[test]
+    fn loader_decodes_tiles_in_background() {
+        let dir =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(crate::constants::MAP_TILE_DIR);
+        let (width, _) = image::image_dimensions(dir.join("tile_0_0.png")).expect("tile header");
+        let loader = TileLoader::spawn(dir, width as u16);
+        let base = TileKey {
+            level: 0,
+            x: 0,
+            y: 0,
+        };
+        let missing = TileKey {
+            level: 0,
+            x: 999,
+            y: 0,
+        };
+        loader.request(base);
+        loader.request(missing);
+
+        let mut loaded = Vec::new();
+        while loaded.len() < 2 {
+            let tile = loader
+                .results
+                .recv_timeout(Duration::from_secs(10))
+                .expect("tile loader result");
+            loaded.push(tile);
+        }
+        let raster = loaded[0].raster.as_ref().expect("decoded tile");
+        assert_eq!(loaded[0].key, base);
+        assert_eq!(raster.width as u32, width);
+        assert_eq!(loaded[1].key, missing);
+        assert!(matches!(loaded[1].raster, Err(AssetError::MissingTile(_))));
+    }
+
+    #[test]
+    fn downsample_averages_blocks_into_quadrants() {
+        let mut red = solid_tile(4, [255, 0, 0, 255]);
+        // Top-left 2x2 block of the first child averages to half red.
+        for index in [0, 1, 4, 5] {
+            red.pixels[index * 4] = if index < 2 { 255 } else { 0 };
+        }
+        let children = [Some(red), None, None, Some(solid_tile(4, [0, 0, 255, 255]))];
+        let out = downsample_quad(&children, 4);
+        assert_eq!(&out.pixels[0..4], &[128, 0, 0, 255]);
+        assert_eq!(&out.pixels[4..8], &[255, 0, 0, 255]);
+        // Top-right quadrant has no child.
+        assert_eq!(&out.pixels[8..12], &[0, 0, 0, 0]);
+        let bottom_right = (3 * 4 + 3) * 4;
+        assert_eq!(
+            &out.pixels[bottom_right..bottom_right + 4],
+            &[0, 0, 255, 255]
+        );
+    }
+
+    #[test]
+    fn missing_levels_are_built_and_saved() {
+        let dir = std::env::temp_dir().join(format!("flaghack2-tiles-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create tile dir");
+        let green = solid_tile(4, [0, 200, 0, 255]);
+        assets::save_png_rgba(dir.join("tile_0_0.png"), &green).expect("save base tile");
+        assets::save_png_rgba(dir.join("tile_1_1.png"), &green).expect("save base tile");
+
+        let key = TileKey {
+            level: 2,
+            x: 0,
+            y: 0,
+        };
+        let top = load_or_build_tile(&dir, key, 4).expect("built tile");
+        assert_eq!(&top.pixels[0..4], &[0, 200, 0, 255]);
+        assert_eq!(&top.pixels[4..8], &[0, 0, 0, 0]);
+        assert!(dir.join("tile_1_0_0.png").exists());
+        assert!(dir.join("tile_2_0_0.png").exists());
+        assert!(load_or_build_tile(
+            &dir,
+            TileKey {
+                level: 1,
+                x: 1,
+                y: 1
+            },
+            4
+        )
+        .is_err());
+
+        // A base tile edited after the build makes every level above stale.
+        let earlier = SystemTime::now() - Duration::from_secs(10);
+        for name in ["tile_1_0_0.png", "tile_2_0_0.png"] {
+            std::fs::File::options()
+                .write(true)
+                .open(dir.join(name))
+                .and_then(|file| file.set_modified(earlier))
+                .expect("backdate built tile");
+        }
+        let base = dir.join("tile_0_0.png");
+        let red = solid_tile(4, [200, 0, 0, 255]);
+        assets::save_png_rgba(&base, &red).expect("save base tile");
+        let top = load_or_build_tile(&dir, key, 4).expect("rebuilt tile");
+        assert_eq!(&top.pixels[0..4], &[200, 0, 0, 255]);
+        assert!(modified(&dir.join("tile_1_0_0.png")) >= modified(&base));
+
+        std::fs::remove_dir_all(&dir).expect("remove tile dir");
+    }
+}
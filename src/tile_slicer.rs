@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use image::RgbaImage;
+
+use crate::assets::{self, AssetError, RasterImage};
+use crate::map::{self, MapManifest, TileKey, MANIFEST_FILE};
+use crate::tile_cache;
+
+pub const DEFAULT_TILE_SIZE: u16 = 1024;
+
+/// Cuts `source` into `tile_px` base tiles, padding the right and bottom
+/// edges with transparency.
+pub fn slice_base(source: &RgbaImage, tile_px: u16) -> Vec<(TileKey, RasterImage)> {
+    let size = tile_px as u32;
+    let columns = source.width().div_ceil(size);
+    let rows = source.height().div_ceil(size);
+    let mut tiles = Vec::new();
+    for y in 0..rows {
+        for x in 0..columns {
+            let (left, top) = (x * size, y * size);
+            let part = image::imageops::crop_imm(
+                source,
+                left,
+                top,
+                size.min(source.width() - left),
+                size.min(source.height() - top),
+            )
+            .to_image();
+            let mut tile = RgbaImage::new(size, size);
+            image::imageops::replace(&mut tile, &part, 0, 0);
+            let key = TileKey {
+                level: 0,
+                x: x as usize,
+                y: y as usize,
+            };
+            tiles.push((
+                key,
+                RasterImage {
+                    width: tile_px,
+                    height: tile_px,
+                    pixels: tile.into_raw(),
+                },
+            ));
+        }
+    }
+    tiles
+}
+
+/// Builds the level above `tiles`, which must all share one level.
+pub fn build_level(
+    tiles: Vec<(TileKey, RasterImage)>,
+    tile_px: u16,
+) -> Vec<(TileKey, RasterImage)> {
+    let mut parents: Vec<TileKey> = tiles.iter().map(|(key, _)| key.ancestor(1)).collect();
+    let mut by_key: HashMap<TileKey, RasterImage> = tiles.into_iter().collect();
+    parents.sort_by_key(|key| (key.y, key.x));
+    parents.dedup();
+    parents
+        .into_iter()
+        .map(|parent| {
+            let children = parent.children().map(|child| by_key.remove(&child));
+            (parent, tile_cache::downsample_quad(&children, tile_px))
+        })
+        .collect()
+}
+
+/// Slices the image at `source` into `out_dir`, with up to `levels` coarser
+/// levels, and writes the manifest. Tiles already in `out_dir` are removed
+/// first so stale levels are not mixed with the new map.
+pub fn slice_map(
+    source: &Path,
+    out_dir: &Path,
+    tile_px: u16,
+    levels: usize,
+) -> Result<MapManifest, AssetError> {
+    let image = image::open(source)
+        .map_err(|error| AssetError::Decode {
+            path: source.to_path_buf(),
+            error,
+        })?
+        .to_rgba8();
+    let io_error = |error| AssetError::Io {
+        path: out_dir.to_path_buf(),
+        error,
+    };
+    std::fs::create_dir_all(out_dir).map_err(io_error)?;
+    for entry in std::fs::read_dir(out_dir).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        let is_tile = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(map::parse_tile_key)
+            .is_some();
+        if is_tile {
+            std::fs::remove_file(&path).map_err(|error| AssetError::Io { path, error })?;
+        }
+    }
+
+    let mut tiles = slice_base(&image, tile_px);
+    let columns = tiles.iter().map(|(key, _)| key.x + 1).max().unwrap_or(0);
+    let rows = tiles.iter().map(|(key, _)| key.y + 1).max().unwrap_or(0);
+    let mut written_levels = 0;
+    loop {
+        for (key, raster) in &tiles {
+            let path = out_dir.join(key.file_name());
+            assets::save_png_rgba(&path, raster)
+                .map_err(|error| AssetError::Write { path, error })?;
+        }
+        if written_levels == levels || tiles.len() <= 1 {
+            break;
+        }
+        tiles = build_level(tiles, tile_px);
+        written_levels += 1;
+    }
+
+    let manifest = MapManifest {
+        tile_size: tile_px as u32,
+        columns,
+        rows,
+        width: image.width(),
+        height: image.height(),
+        levels: written_levels,
+    };
+    manifest.save(out_dir.join(MANIFEST_FILE))?;
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            image::Rgba([x as u8, y as u8, 0, 255])
+        })
+    }
+
+    #[test]
+    fn slicing_pads_edge_tiles() {
+        let tiles = slice_base(&gradient(10, 6), 4);
+        assert_eq!(tiles.len(), 3 * 2);
+        let (key, edge) = &tiles[2];
+        assert_eq!(
+            *key,
+            TileKey {
+                level: 0,
+                x: 2,
+                y: 0
+            }
+        );
+        // Column 8 of the source is the first column of the third tile.
+        assert_eq!(&edge.pixels[0..4], &[8, 0, 0, 255]);
+        // Past the source edge the tile is transparent.
+        assert_eq!(&edge.pixels[2 * 4..3 * 4], &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn slice_map_writes_tiles_levels_and_manifest() {
+        let dir = std::env::temp_dir().join(format!("flaghack2-slice-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create slice dir");
+        let source = dir.join("source.png");
+        gradient(10, 6).save(&source).expect("save source");
+        let out = dir.join("tiles");
+        std::fs::create_dir_all(&out).expect("create out dir");
+        std::fs::write(out.join("tile_3_0_0.png"), b"stale").expect("write stale tile");
+
+        let manifest = slice_map(&source, &out, 4, 5).expect("slice map");
+        assert_eq!(
+            manifest,
+            MapManifest {
+                tile_size: 4,
+                columns: 3,
+                rows: 2,
+                width: 10,
+                height: 6,
+                levels: 2,
+            }
+        );
+        assert!(out.join("tile_2_1.png").exists());
+        assert!(out.join("tile_1_1_0.png").exists());
+        assert!(out.join("tile_2_0_0.png").exists());
+        assert!(!out.join("tile_3_0_0.png").exists());
+        assert_eq!(
+            MapManifest::load(out.join(MANIFEST_FILE)).expect("manifest"),
+            manifest
+        );
+        assert!(map::parse_tile_filename("tile_2_1.png").is_some());
+
+        std::fs::remove_dir_all(&dir).expect("remove slice dir");
+    }
+}
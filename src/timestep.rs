@@ -0,0 +1,73 @@
+/// Fixed-rate simulation clock. Render frames feed in wall-clock time and get
+/// back how many simulation steps to run, plus the blend factor for drawing
+/// between the last two steps.
+#[derive(Clone, Debug)]
+pub struct FixedTimestep {
+    step: f32,
+    max_steps: u32,
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    pub fn new(step: f32, max_steps: u32) -> Self {
+        debug_assert!(step > 0.0);
+        Self {
+            step,
+            max_steps: max_steps.max(1),
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Adds `frame_dt` and returns the number of whole steps now due. Time
+    /// beyond `max_steps` is dropped so a long hitch cannot snowball.
+    pub fn advance(&mut self, frame_dt: f32) -> u32 {
+        self.accumulator += frame_dt.max(0.0);
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < self.max_steps {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        if self.accumulator >= self.step {
+            self.accumulator %= self.step;
+        }
+        steps
+    }
+
+    /// Fraction of a step left in the accumulator, in `[0, 1)`.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advance_runs_whole_steps_and_keeps_remainder() {
+        let mut clock = FixedTimestep::new(0.01, 10);
+        assert_eq!(clock.advance(0.025), 2);
+        assert!((clock.alpha() - 0.5).abs() < 1e-3);
+        assert_eq!(clock.advance(0.005), 1);
+        assert!(clock.alpha() < 1e-3);
+    }
+
+    #[test]
+    fn short_frames_accumulate() {
+        let mut clock = FixedTimestep::new(1.0 / 60.0, 5);
+        let steps: u32 = (0..240).map(|_| clock.advance(1.0 / 240.0)).sum();
+        assert!((59..=60).contains(&steps));
+    }
+
+    #[test]
+    fn hitch_is_capped_at_max_steps() {
+        let mut clock = FixedTimestep::new(1.0 / 60.0, 5);
+        assert_eq!(clock.advance(2.0), 5);
+        assert!(clock.alpha() < 1.0);
+        assert!(clock.advance(0.0) <= 1);
+    }
+}
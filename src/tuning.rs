@@ -0,0 +1,500 @@
+use macroquad::prelude::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::constants::*;
+
+pub const TUNING_PATH: &str = "config/tuning.toml";
+
+/// Gameplay numbers designers balance without recompiling. Every section and
+/// field is optional in the file and falls back to the values in
+/// `constants.rs`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tuning {
+    pub player: PlayerTuning,
+    pub flags: FlagTuning,
+    pub ley: LeyTuning,
+    pub flagic: FlagicTuning,
+    pub spells: SpellTuning,
+    pub hippie: HippieTuning,
+    pub stress_test: StressTestTuning,
+    pub camera: CameraTuning,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerTuning {
+    pub spawn_pos: [f32; 2],
+    pub speed_multiplier: f32,
+    pub map_travel_minutes: f32,
+    pub starting_flags: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlagTuning {
+    pub start_count: usize,
+    pub interact_radius: f32,
+    pub place_offset: [f32; 2],
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LeyTuning {
+    pub max_distance: f32,
+    pub pentagram_center_radius: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlagicTuning {
+    pub max: u8,
+    pub gain_rate: f32,
+    pub score_floor: f32,
+    pub overlap_bonus: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpellTuning {
+    pub calm_cost: u8,
+    pub calm_cooldown: f32,
+    pub calm_radius: f32,
+    pub summon_cost: u8,
+    pub summon_cooldown: f32,
+    pub summon_radius: f32,
+    pub gust_cost: u8,
+    pub gust_cooldown: f32,
+    pub gust_turn: f32,
+    pub haste_cost: u8,
+    pub haste_cooldown: f32,
+    pub haste_duration: f32,
+    pub haste_multiplier: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HippieTuning {
+    pub steal_radius: f32,
+    pub start_one_flag_chance: f32,
+    pub start_two_flag_chance: f32,
+    pub anger_duration: f32,
+    pub anger_radius: f32,
+    pub anger_delay: f32,
+    pub steal_back_radius: f32,
+    pub steal_cooldown: f32,
+    pub chase_speed_factor: f32,
+    pub flee_duration: f32,
+    pub flag_drop_interval: f32,
+    pub flag_drop_chance: f32,
+    pub flag_ignore_duration: f32,
+    pub collision_radius: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StressTestTuning {
+    pub hippie_count: usize,
+    pub flags_per_hippie: u8,
+}
+
+/// Zoom limits are player settings, not tuning; see `Settings`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraTuning {
+    pub zoom_step: f32,
+}
+
+impl Default for PlayerTuning {
+    fn default() -> Self {
+        Self {
+            spawn_pos: [PLAYER_SPAWN_POS.x, PLAYER_SPAWN_POS.y],
+            speed_multiplier: SPEED_MULTIPLIER,
+            map_travel_minutes: MAP_TRAVEL_MINUTES,
+            starting_flags: STARTING_FLAG_INVENTORY,
+        }
+    }
+}
+
+impl Default for FlagTuning {
+    fn default() -> Self {
+        Self {
+            start_count: FLAG_COUNT_START,
+            interact_radius: FLAG_INTERACT_RADIUS,
+            place_offset: [FLAG_PLACE_OFFSET.x, FLAG_PLACE_OFFSET.y],
+        }
+    }
+}
+
+impl Default for LeyTuning {
+    fn default() -> Self {
+        Self {
+            max_distance: LEY_MAX_DISTANCE,
+            pentagram_center_radius: PENTAGRAM_CENTER_RADIUS,
+        }
+    }
+}
+
+impl Default for FlagicTuning {
+    fn default() -> Self {
+        Self {
+            max: FLAGIC_MAX,
+            gain_rate: FLAGIC_GAIN_RATE,
+            score_floor: FLAGIC_SCORE_FLOOR,
+            overlap_bonus: FLAGIC_OVERLAP_BONUS,
+        }
+    }
+}
+
+impl Default for SpellTuning {
+    fn default() -> Self {
+        Self {
+            calm_cost: SPELL_CALM_COST,
+            calm_cooldown: SPELL_CALM_COOLDOWN,
+            calm_radius: SPELL_CALM_RADIUS,
+            summon_cost: SPELL_SUMMON_COST,
+            summon_cooldown: SPELL_SUMMON_COOLDOWN,
+            summon_radius: SPELL_SUMMON_RADIUS,
+            gust_cost: SPELL_GUST_COST,
+            gust_cooldown: SPELL_GUST_COOLDOWN,
+            gust_turn: SPELL_GUST_TURN,
+            haste_cost: SPELL_HASTE_COST,
+            haste_cooldown: SPELL_HASTE_COOLDOWN,
+            haste_duration: SPELL_HASTE_DURATION,
+            haste_multiplier: SPELL_HASTE_MULTIPLIER,
+        }
+    }
+}
+
+impl Default for HippieTuning {
+    fn default() -> Self {
+        Self {
+            steal_radius: HIPPIE_STEAL_RADIUS,
+            start_one_flag_chance: HIPPIE_START_ONE_FLAG_CHANCE,
+            start_two_flag_chance: HIPPIE_START_TWO_FLAG_CHANCE,
+            anger_duration: HIPPIE_ANGER_DURATION,
+            anger_radius: HIPPIE_ANGER_RADIUS,
+            anger_delay: HIPPIE_ANGER_DELAY,
+            steal_back_radius: HIPPIE_STEAL_BACK_RADIUS,
+            steal_cooldown: HIPPIE_STEAL_COOLDOWN,
+            chase_speed_factor: HIPPIE_CHASE_SPEED_FACTOR,
+            flee_duration: HIPPIE_FLEE_DURATION,
+            flag_drop_interval: HIPPIE_FLAG_DROP_INTERVAL,
+            flag_drop_chance: HIPPIE_FLAG_DROP_CHANCE,
+            flag_ignore_duration: HIPPIE_FLAG_IGNORE_DURATION,
+            collision_radius: HIPPIE_COLLISION_RADIUS,
+        }
+    }
+}
+
+impl Default for StressTestTuning {
+    fn default() -> Self {
+        Self {
+            hippie_count: STRESS_TEST_HIPPIE_COUNT,
+            flags_per_hippie: STRESS_TEST_FLAGS_PER_HIPPIE,
+        }
+    }
+}
+
+impl Default for CameraTuning {
+    fn default() -> Self {
+        Self {
+            zoom_step: CAMERA_ZOOM_STEP,
+        }
+    }
+}
+
+impl PlayerTuning {
+    pub fn spawn_pos(&self) -> Vec2 {
+        vec2(self.spawn_pos[0], self.spawn_pos[1])
+    }
+}
+
+impl FlagTuning {
+    pub fn place_offset(&self) -> Vec2 {
+        vec2(self.place_offset[0], self.place_offset[1])
+    }
+}
+
+#[derive(Debug)]
+pub enum TuningError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    Invalid {
+        field: &'static str,
+        reason: &'static str,
+    },
+}
+
+impl fmt::Display for TuningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TuningError::Io(err) => write!(f, "tuning io error: {}", err),
+            TuningError::Parse(err) => write!(f, "tuning parse error: {}", err),
+            TuningError::Serialize(err) => write!(f, "tuning serialize error: {}", err),
+            TuningError::Invalid { field, reason } => {
+                write!(f, "tuning value {} {}", field, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TuningError {}
+
+impl From<std::io::Error> for TuningError {
+    fn from(err: std::io::Error) -> Self {
+        TuningError::Io(err)
+    }
+}
+
+impl Tuning {
+    pub fn to_toml(&self) -> Result<String, TuningError> {
+        toml::to_string(self).map_err(TuningError::Serialize)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, TuningError> {
+        toml::from_str(text).map_err(TuningError::Parse)
+    }
+
+    /// Reads and validates the tuning file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, TuningError> {
+        let text = std::fs::read_to_string(path)?;
+        let tuning = Self::from_toml(&text)?;
+        tuning.validate()?;
+        Ok(tuning)
+    }
+
+    /// Checks that speeds, radii and zoom steps are positive, that timers
+    /// are not negative and that chances lie in `[0, 1]`.
+    pub fn validate(&self) -> Result<(), TuningError> {
+        let player = &self.player;
+        finite("player.spawn_pos", player.spawn_pos)?;
+        positive("player.speed_multiplier", player.speed_multiplier)?;
+        positive("player.map_travel_minutes", player.map_travel_minutes)?;
+
+        let flags = &self.flags;
+        positive("flags.interact_radius", flags.interact_radius)?;
+        finite("flags.place_offset", flags.place_offset)?;
+
+        positive("ley.max_distance", self.ley.max_distance)?;
+        positive(
+            "ley.pentagram_center_radius",
+            self.ley.pentagram_center_radius,
+        )?;
+
+        let flagic = &self.flagic;
+        non_negative("flagic.gain_rate", flagic.gain_rate)?;
+        chance("flagic.score_floor", flagic.score_floor)?;
+        non_negative("flagic.overlap_bonus", flagic.overlap_bonus)?;
+
+        let spells = &self.spells;
+        non_negative("spells.calm_cooldown", spells.calm_cooldown)?;
+        positive("spells.calm_radius", spells.calm_radius)?;
+        non_negative("spells.summon_cooldown", spells.summon_cooldown)?;
+        positive("spells.summon_radius", spells.summon_radius)?;
+        non_negative("spells.gust_cooldown", spells.gust_cooldown)?;
+        finite("spells.gust_turn", [spells.gust_turn])?;
+        non_negative("spells.haste_cooldown", spells.haste_cooldown)?;
+        non_negative("spells.haste_duration", spells.haste_duration)?;
+        positive("spells.haste_multiplier", spells.haste_multiplier)?;
+
+        let hippie = &self.hippie;
+        positive("hippie.steal_radius", hippie.steal_radius)?;
+        chance("hippie.start_one_flag_chance", hippie.start_one_flag_chance)?;
+        chance("hippie.start_two_flag_chance", hippie.start_two_flag_chance)?;
+        non_negative("hippie.anger_duration", hippie.anger_duration)?;
+        positive("hippie.anger_radius", hippie.anger_radius)?;
+        non_negative("hippie.anger_delay", hippie.anger_delay)?;
+        positive("hippie.steal_back_radius", hippie.steal_back_radius)?;
+        non_negative("hippie.steal_cooldown", hippie.steal_cooldown)?;
+        positive("hippie.chase_speed_factor", hippie.chase_speed_factor)?;
+        non_negative("hippie.flee_duration", hippie.flee_duration)?;
+        positive("hippie.flag_drop_interval", hippie.flag_drop_interval)?;
+        chance("hippie.flag_drop_chance", hippie.flag_drop_chance)?;
+        non_negative("hippie.flag_ignore_duration", hippie.flag_ignore_duration)?;
+        positive("hippie.collision_radius", hippie.collision_radius)?;
+
+        positive("camera.zoom_step", self.camera.zoom_step)
+    }
+
+    /// Loads the tuning file, or returns the built-in values if it is missing
+    /// or broken. Only a broken file reports an error.
+    pub fn load_or_default<P: AsRef<Path>>(path: P) -> (Self, Option<TuningError>) {
+        let path = path.as_ref();
+        if !path.exists() {
+            return (Self::default(), None);
+        }
+        match Self::load(path) {
+            Ok(tuning) => (tuning, None),
+            Err(err) => (Self::default(), Some(err)),
+        }
+    }
+}
+
+/// Polls the tuning file's modification time so edits can be picked up while
+/// the game runs.
+pub struct TuningWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    poll_timer: f32,
+}
+
+impl TuningWatcher {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let modified = modified_time(&path);
+        Self {
+            path,
+            modified,
+            poll_timer: 0.0,
+        }
+    }
+
+    /// Returns the reloaded tuning once the file has changed. A file that
+    /// fails to parse or validate is reported and the current tuning is kept.
+    pub fn poll(&mut self, dt: f32) -> Option<Result<Tuning, TuningError>> {
+        self.poll_timer -= dt;
+        if self.poll_timer > 0.0 {
+            return None;
+        }
+        self.poll_timer = TUNING_POLL_INTERVAL;
+
+        let modified = modified_time(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(Tuning::load(&self.path))
+    }
+}
+
+// Written as negated comparisons so NaN fails every check.
+fn positive(field: &'static str, value: f32) -> Result<(), TuningError> {
+    if !(value > 0.0 && value.is_finite()) {
+        return Err(TuningError::Invalid {
+            field,
+            reason: "must be positive",
+        });
+    }
+    Ok(())
+}
+
+fn non_negative(field: &'static str, value: f32) -> Result<(), TuningError> {
+    if !(value >= 0.0 && value.is_finite()) {
+        return Err(TuningError::Invalid {
+            field,
+            reason: "must not be negative",
+        });
+    }
+    Ok(())
+}
+
+fn chance(field: &'static str, value: f32) -> Result<(), TuningError> {
+    if !(0.0..=1.0).contains(&value) {
+        return Err(TuningError::Invalid {
+            field,
+            reason: "must be between 0 and 1",
+        });
+    }
+    Ok(())
+}
+
+fn finite<const N: usize>(field: &'static str, values: [f32; N]) -> Result<(), TuningError> {
+    if !values.iter().all(|v| v.is_finite()) {
+        return Err(TuningError::Invalid {
+            field,
+            reason: "must be a finite number",
+        });
+    }
+    Ok(())
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_match_constants() {
+        let tuning = Tuning::default();
+        assert_eq!(tuning.ley.max_distance, LEY_MAX_DISTANCE);
+        assert_eq!(tuning.flagic.gain_rate, FLAGIC_GAIN_RATE);
+        assert_eq!(tuning.hippie.anger_duration, HIPPIE_ANGER_DURATION);
+        assert_eq!(tuning.player.spawn_pos(), PLAYER_SPAWN_POS);
+        assert_eq!(tuning.camera.zoom_step, CAMERA_ZOOM_STEP);
+    }
+
+    #[test]
+    fn partial_file_falls_back_per_field() {
+        let text = "[hippie]\nanger_duration = 3.0\n\n[ley]\nmax_distance = 90.0\n";
+        let tuning = Tuning::from_toml(text).expect("parse tuning");
+        assert_eq!(tuning.hippie.anger_duration, 3.0);
+        assert_eq!(tuning.hippie.flee_duration, HIPPIE_FLEE_DURATION);
+        assert_eq!(tuning.ley.max_distance, 90.0);
+        assert_eq!(tuning.flagic, FlagicTuning::default());
+    }
+
+    #[test]
+    fn toml_round_trip_preserves_tuning() {
+        let mut tuning = Tuning::default();
+        tuning.player.speed_multiplier = 7.5;
+        tuning.stress_test.hippie_count = 3;
+        let text = tuning.to_toml().expect("serialize tuning");
+        assert_eq!(Tuning::from_toml(&text).expect("parse tuning"), tuning);
+    }
+
+    #[test]
+    fn validate_rejects_out_of_range_values() {
+        assert!(Tuning::default().validate().is_ok());
+        let bad = [
+            "[player]\nspeed_multiplier = -1.0\n",
+            "[ley]\nmax_distance = 0.0\n",
+            "[hippie]\ncollision_radius = nan\n",
+            "[hippie]\nflag_drop_chance = 1.5\n",
+            "[spells]\nhaste_cooldown = -2.0\n",
+            "[camera]\nzoom_step = 0.0\n",
+        ];
+        for text in bad {
+            let tuning = Tuning::from_toml(text).expect("parse tuning");
+            assert!(
+                matches!(tuning.validate(), Err(TuningError::Invalid { .. })),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn watcher_reports_changes_once() {
+        let path =
+            std::env::temp_dir().join(format!("flaghack2-tuning-test-{}.toml", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let mut watcher = TuningWatcher::new(&path);
+        assert!(watcher.poll(TUNING_POLL_INTERVAL).is_none());
+
+        std::fs::write(&path, "[flagic]\ngain_rate = 9.0\n").expect("write tuning");
+        let reloaded = watcher
+            .poll(TUNING_POLL_INTERVAL)
+            .expect("change detected")
+            .expect("parse tuning");
+        assert_eq!(reloaded.flagic.gain_rate, 9.0);
+        assert!(watcher.poll(TUNING_POLL_INTERVAL).is_none());
+
+        std::fs::write(&path, "[flagic]\ngain_rate = -9.0\n").expect("write tuning");
+        watcher.modified = None;
+        assert!(matches!(
+            watcher.poll(TUNING_POLL_INTERVAL),
+            Some(Err(TuningError::Invalid { .. }))
+        ));
+        std::fs::remove_file(&path).ok();
+    }
+}
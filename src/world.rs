@@ -0,0 +1,1066 @@
+use macroquad::prelude::{vec2, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::camps;
+use crate::collision;
+use crate::constants::*;
+use crate::flag_state;
+use crate::flags;
+use crate::ley_lines;
+use crate::map;
+use crate::movement;
+use crate::nav;
+use crate::npc;
+use crate::player;
+use crate::rng::Rng;
+use crate::scale;
+use crate::scenery;
+use crate::spatial::SpatialHash;
+use crate::spells::{CastError, Spell, Spellbook};
+use crate::tuning::{FlagicTuning, StressTestTuning, Tuning};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClassChoice {
+    Vexillomancer,
+    StressTest,
+}
+
+impl ClassChoice {
+    pub fn name(self) -> &'static str {
+        match self {
+            ClassChoice::Vexillomancer => "Vexillomancer",
+            ClassChoice::StressTest => "Stress Test",
+        }
+    }
+}
+
+pub const CLASS_OPTIONS: [ClassChoice; 2] = [ClassChoice::Vexillomancer, ClassChoice::StressTest];
+
+pub fn class_choice_index(choice: ClassChoice) -> usize {
+    match choice {
+        ClassChoice::Vexillomancer => 0,
+        ClassChoice::StressTest => 1,
+    }
+}
+
+pub fn class_choice_from_index(index: usize) -> ClassChoice {
+    CLASS_OPTIONS[index.min(CLASS_OPTIONS.len() - 1)]
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Player {
+    pub pos: Vec2,
+    pub facing: player::Facing,
+}
+
+impl Player {
+    pub fn center(&self) -> Vec2 {
+        self.pos + vec2(player::PLAYER_WIDTH * 0.5, player::PLAYER_HEIGHT * 0.5)
+    }
+
+    /// Center of the circle the player's feet take up, which is what
+    /// collides with scenery and follows nav paths.
+    pub fn feet(&self) -> Vec2 {
+        self.pos + feet_offset()
+    }
+}
+
+fn feet_offset() -> Vec2 {
+    vec2(
+        player::PLAYER_WIDTH * 0.5,
+        player::PLAYER_HEIGHT - PLAYER_COLLISION_RADIUS,
+    )
+}
+
+/// Everything the player can do in a single simulation step.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InputFrame {
+    pub movement: movement::InputState,
+    pub place_flag: bool,
+    pub pickup_flag: bool,
+    /// A clicked destination for the player's feet to walk to.
+    pub move_to: Option<Vec2>,
+    /// A spell to cast before moving.
+    pub cast: Option<Spell>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CampNotice {
+    pub camp_name: String,
+    pub text: String,
+    pub inside: bool,
+    pub timer: f32,
+}
+
+impl CampNotice {
+    pub fn new(camp_name: String, text: String) -> Self {
+        Self {
+            camp_name,
+            text,
+            inside: false,
+            timer: -1.0,
+        }
+    }
+}
+
+/// Positions captured before a simulation step, so rendering can blend
+/// between the previous and current fixed steps.
+#[derive(Clone, Debug, Default)]
+pub struct PositionSnapshot {
+    pub player: Vec2,
+    pub hippies: Vec<Vec2>,
+}
+
+impl PositionSnapshot {
+    pub fn player_pos(&self, world: &World, alpha: f32) -> Vec2 {
+        self.player.lerp(world.player.pos, alpha)
+    }
+
+    pub fn hippie_pos(&self, index: usize, current: Vec2, alpha: f32) -> Vec2 {
+        self.hippies
+            .get(index)
+            .map_or(current, |previous| previous.lerp(current, alpha))
+    }
+}
+
+/// Everything besides the seed that a new world is built from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WorldConfig {
+    pub tuning: Tuning,
+    pub camps: Vec<camps::CampConfig>,
+    pub scenery: scenery::SceneryLayout,
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        Self {
+            tuning: Tuning::default(),
+            camps: camps::camp_configs(),
+            scenery: scenery::SceneryLayout::builtin(),
+        }
+    }
+}
+
+/// Headless game simulation. Owns all gameplay state and never touches the
+/// window, so it can be stepped from tests and tools.
+pub struct World {
+    pub player: Player,
+    pub player_speed: f32,
+    pub flag_state: flag_state::FlagState,
+    pub hippies: Vec<npc::Hippie>,
+    pub ley_lines: Vec<ley_lines::LeyLine>,
+    pub pentagram_centers: Vec<Vec2>,
+    pub sigil_centers: Vec<ley_lines::SigilCenter>,
+    pub flagic: u8,
+    pub flagic_accum: f32,
+    pub spellbook: Spellbook,
+    pub wind: flags::Wind,
+    pub camp_notices: Vec<CampNotice>,
+    pub camp_regions: Vec<map::MapRegion>,
+    pub camp_vertices: Vec<Vec<Vec2>>,
+    pub scenery: Vec<scenery::SceneryItem>,
+    /// Waypoints the player is walking along after a click to move.
+    pub player_path: Vec<Vec2>,
+    pub class_choice: ClassChoice,
+    pub seed: u32,
+    pub rng: Rng,
+    pub tuning: Tuning,
+    field: Rect,
+    layout_scenery: usize,
+    nav: nav::NavGrid,
+    /// Hippie positions by index. `hippies` is public, so this is brought up
+    /// to date right before it is queried.
+    hippie_index: SpatialHash,
+}
+
+impl World {
+    pub fn new(class_choice: ClassChoice, field: Rect, seed: u32) -> Self {
+        Self::new_with_config(class_choice, field, seed, WorldConfig::default())
+    }
+
+    pub fn new_with_config(
+        class_choice: ClassChoice,
+        field: Rect,
+        seed: u32,
+        config: WorldConfig,
+    ) -> Self {
+        let WorldConfig {
+            tuning,
+            camps: camp_configs,
+            scenery: layout,
+        } = config;
+        let mut rng = Rng::new(seed);
+        let camp_regions = camp_configs
+            .iter()
+            .map(|camp| map::MapRegion::new(camp.name.clone(), camp.vertices.clone(), camp.color))
+            .collect::<Vec<_>>();
+        let camp_notices = camp_configs
+            .iter()
+            .map(|camp| CampNotice::new(camp.name.clone(), camp.notice_text.clone()))
+            .collect::<Vec<_>>();
+        let camp_vertices = camps::collect_camp_vertices(&camp_configs);
+        let hippies = match class_choice {
+            ClassChoice::Vexillomancer => {
+                let mut hippies = Vec::new();
+                for (camp_index, camp) in camp_configs.iter().enumerate() {
+                    if !camp.spawns.hippies.is_empty() {
+                        hippies.extend(npc::spawn_hippies(
+                            &camp.spawns.hippies,
+                            camp_index,
+                            &camp.vertices,
+                            &mut rng,
+                            &tuning.hippie,
+                        ));
+                    }
+                }
+                hippies
+            }
+            ClassChoice::StressTest => spawn_stress_test_hippies(&camp_configs, &tuning, &mut rng),
+        };
+        let mut ground_flags = flags::spawn_random_flags(
+            tuning.flags.start_count,
+            field,
+            40.0 * scale::MODEL_SCALE,
+            &mut rng,
+        );
+        for pos in camps::collect_flag_spawns(&camp_configs) {
+            ground_flags.push(flags::make_flag(pos));
+        }
+        let starting_flags = tuning.player.starting_flags;
+        let total_flags = ground_flags.len() as u32 + starting_flags + total_hippie_flags(&hippies);
+        let mut flag_state = flag_state::FlagState::new(ground_flags, starting_flags, total_flags);
+        flag_state.set_ley_max_distance(tuning.ley.max_distance);
+        let ley_state = flag_state.ley_state();
+        let camp_spawns = camps::collect_scenery_spawns(&camp_configs);
+        let scenery = scenery::spawn_scenery(field, &layout, &camp_spawns, &mut rng);
+        let nav = build_nav(field, &scenery, &camp_vertices);
+        let player_speed = player_speed_for(field, &tuning);
+
+        Self {
+            player: Player {
+                pos: tuning.player.spawn_pos(),
+                facing: player::Facing::Down,
+            },
+            player_speed,
+            flag_state,
+            hippies,
+            ley_lines: ley_state.lines,
+            pentagram_centers: ley_state.pentagram_centers,
+            sigil_centers: ley_state.sigil_centers,
+            flagic: 0,
+            flagic_accum: 0.0,
+            spellbook: Spellbook::default(),
+            wind: flags::Wind::new(Vec2::X, WIND_START_STRENGTH),
+            camp_notices,
+            camp_regions,
+            camp_vertices,
+            scenery,
+            player_path: Vec::new(),
+            class_choice,
+            seed,
+            rng,
+            tuning,
+            field,
+            layout_scenery: layout.items.len(),
+            nav,
+            hippie_index: SpatialHash::new(SPATIAL_CELL_SIZE),
+        }
+    }
+
+    /// Replaces the scenery that came from the layout file, keeping random
+    /// domes and camp scenery.
+    pub fn set_layout_scenery(&mut self, spawns: &[scenery::ScenerySpawn]) {
+        scenery::replace_layout_items(&mut self.scenery, self.layout_scenery, spawns);
+        self.layout_scenery = spawns.len();
+        self.nav = build_nav(self.field, &self.scenery, &self.camp_vertices);
+    }
+
+    /// Swaps in new tuning mid-run. Flags, hippies and the player keep their
+    /// state; derived values like ley lines and speed are recomputed.
+    pub fn set_tuning(&mut self, tuning: Tuning) {
+        self.tuning = tuning;
+        self.player_speed = player_speed_for(self.field, &self.tuning);
+        self.recompute_ley_state();
+    }
+
+    pub fn field_rect(&self) -> Rect {
+        self.field
+    }
+
+    pub fn position_snapshot(&self) -> PositionSnapshot {
+        PositionSnapshot {
+            player: self.player.pos,
+            hippies: self.hippies.iter().map(|hippie| hippie.pos).collect(),
+        }
+    }
+
+    pub fn step(&mut self, dt: f32, input: InputFrame) {
+        self.spellbook.tick(dt);
+        if let Some(spell) = input.cast {
+            // A failed cast costs nothing; the spell bar shows why.
+            self.cast_spell(spell).ok();
+        }
+        self.apply_movement(input, dt);
+        self.apply_flag_interactions(input);
+
+        let player_center = self.player.center();
+        update_camp_notices(
+            &mut self.camp_notices,
+            &self.camp_regions,
+            player_center,
+            dt,
+        );
+        npc::update_hippies(
+            &mut self.hippies,
+            dt,
+            &self.camp_vertices,
+            &mut self.flag_state,
+            player_center,
+            self.player_speed,
+            &mut self.rng,
+            &self.tuning.hippie,
+            &self.nav,
+        );
+        // Placing, pickups and hippies all edit the ground flags; the ley
+        // graph has followed each edit, so only its output needs copying.
+        if self.flag_state.take_ground_changed() {
+            self.recompute_ley_state();
+        }
+
+        let gain_rate = flagic_gain_rate(&self.pentagram_scores_here(), &self.tuning.flagic);
+        update_flagic(
+            &mut self.flagic,
+            &mut self.flagic_accum,
+            gain_rate,
+            dt,
+            &self.tuning.flagic,
+        );
+
+        self.flag_state
+            .debug_assert_invariant(total_hippie_flags(&self.hippies));
+    }
+
+    pub fn in_pentagram(&self) -> bool {
+        player_in_pentagram(
+            self.player.center(),
+            &self.pentagram_centers,
+            self.tuning.ley.pentagram_center_radius,
+        )
+    }
+
+    /// Scores of the pentagrams the player stands in the middle of, best
+    /// first.
+    pub fn pentagram_scores_here(&self) -> Vec<f32> {
+        let radius = self.tuning.ley.pentagram_center_radius;
+        let pos = self.player.center();
+        let mut scores: Vec<f32> = self
+            .sigil_centers
+            .iter()
+            .filter(|sigil| {
+                sigil.kind == ley_lines::SigilKind::Pentagram && sigil.pos.distance(pos) <= radius
+            })
+            .map(|sigil| sigil.score)
+            .collect();
+        scores.sort_by(|a, b| b.total_cmp(a));
+        scores
+    }
+
+    /// Casts `spell` if the class knows spells, it is off cooldown, there is
+    /// flagic to pay for it and it has something to act on. Nothing changes
+    /// when it fails.
+    pub fn cast_spell(&mut self, spell: Spell) -> Result<(), CastError> {
+        if self.class_choice != ClassChoice::Vexillomancer {
+            return Err(CastError::WrongClass);
+        }
+        let tuning = &self.tuning.spells;
+        self.spellbook.check(spell, self.flagic, tuning)?;
+        match spell {
+            Spell::Calm => {
+                self.hippie_index
+                    .sync(self.hippies.iter().map(|hippie| hippie.pos));
+                let calmed = npc::calm_hippies(
+                    &mut self.hippies,
+                    &self.hippie_index,
+                    self.player.center(),
+                    tuning.calm_radius,
+                );
+                if calmed == 0 {
+                    return Err(CastError::NoTarget);
+                }
+            }
+            Spell::Summon => {
+                if !self
+                    .flag_state
+                    .try_pickup_to_player(self.player.pos, tuning.summon_radius)
+                {
+                    return Err(CastError::NoTarget);
+                }
+            }
+            Spell::Gust => {
+                let direction = Vec2::from_angle(tuning.gust_turn).rotate(self.wind.direction);
+                self.wind = flags::Wind::new(direction, self.wind.strength);
+            }
+            Spell::Haste => {}
+        }
+        self.spellbook.commit(spell, &mut self.flagic, tuning);
+        Ok(())
+    }
+
+    pub fn current_total_flags(&self) -> u32 {
+        self.flag_state
+            .current_total(total_hippie_flags(&self.hippies))
+    }
+
+    /// Refreshes `ley_lines` and the sigil centers from the ley graph the
+    /// flag state keeps, rebuilding it if the tuned line length changed.
+    pub fn recompute_ley_state(&mut self) {
+        self.flag_state
+            .set_ley_max_distance(self.tuning.ley.max_distance);
+        self.flag_state.take_ground_changed();
+        let state = self.flag_state.ley_state();
+        self.ley_lines = state.lines;
+        self.pentagram_centers = state.pentagram_centers;
+        self.sigil_centers = state.sigil_centers;
+    }
+
+    fn apply_movement(&mut self, input: InputFrame, dt: f32) {
+        if let Some(destination) = input.move_to {
+            self.player_path = self
+                .nav
+                .find_path(self.player.feet(), destination, None, NAV_MAX_EXPANSIONS)
+                .unwrap_or_default();
+        }
+
+        let speed = self.player_speed * self.spellbook.speed_factor(&self.tuning.spells);
+        let direction = movement::input_direction(input.movement);
+        let delta = if direction.length() > 0.0 {
+            // Steering by hand cancels a click to move.
+            self.player_path.clear();
+            movement::movement_delta(input.movement, speed, dt)
+        } else {
+            let feet = self.player.feet();
+            nav::follow_path(feet, &mut self.player_path, speed * dt) - feet
+        };
+        if delta.length() > 0.0 {
+            self.player.facing = player::facing_from_direction(delta);
+        }
+
+        self.player.pos += delta;
+        self.player.pos = resolve_player_obstacles(self.player.pos, &self.nav);
+
+        let max_x = (self.field.w - player::PLAYER_WIDTH).max(0.0);
+        let max_y = (self.field.h - player::PLAYER_HEIGHT).max(0.0);
+        self.player.pos.x = self.player.pos.x.clamp(0.0, max_x);
+        self.player.pos.y = self.player.pos.y.clamp(0.0, max_y);
+    }
+
+    fn apply_flag_interactions(&mut self, input: InputFrame) {
+        if input.place_flag {
+            let placed = self.flag_state.try_place_from_player(
+                self.player.pos,
+                self.tuning.flags.place_offset(),
+                self.field,
+            );
+            if placed {
+                return;
+            }
+        }
+
+        if input.pickup_flag {
+            self.flag_state
+                .try_pickup_to_player(self.player.pos, self.tuning.flags.interact_radius);
+
+            self.hippie_index
+                .sync(self.hippies.iter().map(|hippie| hippie.pos));
+            npc::try_steal_flag(
+                &mut self.hippies,
+                &self.hippie_index,
+                self.player.center(),
+                self.tuning.hippie.steal_radius,
+                &mut self.flag_state,
+                &self.tuning.hippie,
+            );
+        }
+    }
+}
+
+pub fn total_hippie_flags(hippies: &[npc::Hippie]) -> u32 {
+    hippies.iter().map(|h| h.carried_flags as u32).sum()
+}
+
+/// Keeps the player's feet, a circle at the bottom of the sprite, out of
+/// the scenery.
+fn resolve_player_obstacles(pos: Vec2, nav: &nav::NavGrid) -> Vec2 {
+    let feet = feet_offset();
+    nav.resolve_circle(pos + feet, PLAYER_COLLISION_RADIUS) - feet
+}
+
+fn build_nav(field: Rect, scenery: &[scenery::SceneryItem], camps: &[Vec<Vec2>]) -> nav::NavGrid {
+    let clearance = PLAYER_COLLISION_RADIUS.max(HIPPIE_COLLISION_RADIUS);
+    let colliders = collision::build_colliders(scenery);
+    nav::NavGrid::new(field, NAV_CELL_SIZE, clearance, colliders, camps)
+}
+
+fn player_speed_for(field: Rect, tuning: &Tuning) -> f32 {
+    map::adjusted_travel_speed(
+        field.w,
+        field.h,
+        tuning.player.map_travel_minutes,
+        tuning.player.speed_multiplier,
+    )
+}
+
+fn spawn_stress_test_hippies(
+    camp_configs: &[camps::CampConfig],
+    tuning: &Tuning,
+    rng: &mut Rng,
+) -> Vec<npc::Hippie> {
+    let StressTestTuning {
+        hippie_count,
+        flags_per_hippie,
+    } = tuning.stress_test;
+    if camp_configs.is_empty() || hippie_count == 0 {
+        return Vec::new();
+    }
+
+    let camp_count = camp_configs.len();
+    let per_camp = hippie_count / camp_count;
+    let remainder = hippie_count % camp_count;
+    let mut hippies = Vec::with_capacity(hippie_count);
+
+    for (camp_index, camp) in camp_configs.iter().enumerate() {
+        let count = per_camp + usize::from(camp_index < remainder);
+        if count == 0 {
+            continue;
+        }
+
+        let mut spawns = Vec::with_capacity(count);
+        for _ in 0..count {
+            let pos = npc::random_point_in_polygon(&camp.vertices, rng);
+            spawns.push((pos, flags_per_hippie));
+        }
+
+        hippies.extend(npc::spawn_hippies_with_flags(
+            &spawns,
+            camp_index,
+            &camp.vertices,
+            rng,
+            &tuning.hippie,
+        ));
+    }
+
+    hippies
+}
+
+fn player_in_pentagram(pos: Vec2, centers: &[Vec2], radius: f32) -> bool {
+    centers.iter().any(|center| center.distance(pos) <= radius)
+}
+
+/// Flagic per second from standing in pentagrams with `scores`, best first:
+/// the best star's score sets the rate between `score_floor` and all of
+/// `gain_rate`, and every other star stood in adds `overlap_bonus` of that.
+fn flagic_gain_rate(scores: &[f32], tuning: &FlagicTuning) -> f32 {
+    let Some(best) = scores.first() else {
+        return 0.0;
+    };
+    let floor = tuning.score_floor.clamp(0.0, 1.0);
+    let quality = floor + (1.0 - floor) * best.clamp(0.0, 1.0);
+    let overlaps = (scores.len() - 1) as f32;
+    tuning.gain_rate * quality * (1.0 + tuning.overlap_bonus * overlaps)
+}
+
+fn update_flagic(flagic: &mut u8, accum: &mut f32, gain_rate: f32, dt: f32, tuning: &FlagicTuning) {
+    if gain_rate <= 0.0 || dt <= 0.0 {
+        return;
+    }
+    let max = tuning.max;
+
+    if *flagic >= max {
+        *flagic = max;
+        *accum = 0.0;
+        return;
+    }
+
+    *accum += dt * gain_rate;
+    let inc = accum.floor() as u32;
+    if inc == 0 {
+        return;
+    }
+    let next = (*flagic as u32 + inc).min(max as u32);
+    *flagic = next as u8;
+    *accum -= inc as f32;
+    if *flagic >= max {
+        *flagic = max;
+        *accum = 0.0;
+    }
+}
+
+fn update_camp_notices(
+    notices: &mut [CampNotice],
+    regions: &[map::MapRegion],
+    player_center: Vec2,
+    dt: f32,
+) {
+    let mut now_inside = Vec::with_capacity(notices.len());
+    for notice in notices.iter() {
+        let in_camp = regions
+            .iter()
+            .filter(|region| region.name == notice.camp_name)
+            .any(|region| region.contains_point(player_center));
+        now_inside.push(in_camp);
+    }
+
+    update_camp_notice_states(notices, &now_inside, dt);
+}
+
+fn update_camp_notice_states(notices: &mut [CampNotice], now_inside: &[bool], dt: f32) {
+    debug_assert_eq!(notices.len(), now_inside.len());
+
+    let mut entered_index = None;
+    for (i, notice) in notices.iter().enumerate() {
+        if now_inside[i] && !notice.inside {
+            entered_index = Some(i);
+            break;
+        }
+    }
+
+    if let Some(index) = entered_index {
+        for (i, notice) in notices.iter_mut().enumerate() {
+            notice.inside = now_inside[i];
+            if i == index {
+                notice.timer = 0.0;
+            } else {
+                notice.timer = -1.0;
+            }
+        }
+        return;
+    }
+
+    for (i, notice) in notices.iter_mut().enumerate() {
+        notice.inside = now_inside[i];
+        if notice.timer >= 0.0 {
+            notice.timer += dt;
+            if notice.timer > REGION_NOTICE_DURATION {
+                notice.timer = -1.0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_field() -> Rect {
+        Rect::new(0.0, 0.0, 9000.0, 6000.0)
+    }
+
+    #[test]
+    fn player_in_pentagram_center_respects_radius() {
+        let centers = vec![vec2(0.0, 0.0)];
+        assert!(player_in_pentagram(
+            vec2(PENTAGRAM_CENTER_RADIUS * 0.5, 0.0),
+            &centers,
+            PENTAGRAM_CENTER_RADIUS
+        ));
+        assert!(!player_in_pentagram(
+            vec2(PENTAGRAM_CENTER_RADIUS * 1.1, 0.0),
+            &centers,
+            PENTAGRAM_CENTER_RADIUS
+        ));
+    }
+
+    #[test]
+    fn camp_notice_switches_on_new_entry() {
+        let mut notices = vec![
+            CampNotice::new("a".to_string(), "A".to_string()),
+            CampNotice::new("b".to_string(), "B".to_string()),
+        ];
+
+        update_camp_notice_states(&mut notices, &[true, false], 0.1);
+        assert_eq!(notices[0].timer, 0.0);
+        assert!(notices[1].timer < 0.0);
+
+        update_camp_notice_states(&mut notices, &[false, true], 0.1);
+        assert!(notices[0].timer < 0.0);
+        assert_eq!(notices[1].timer, 0.0);
+    }
+
+    #[test]
+    fn flagic_increases_while_in_pentagram() {
+        let mut flagic = 0u8;
+        let mut accum = 0.0;
+        update_flagic(
+            &mut flagic,
+            &mut accum,
+            FLAGIC_GAIN_RATE,
+            0.2,
+            &FlagicTuning::default(),
+        );
+        assert_eq!(flagic, 1);
+        assert!(accum.abs() < 1e-6);
+    }
+
+    #[test]
+    fn flagic_does_not_increase_outside_pentagram() {
+        let mut flagic = 0u8;
+        let mut accum = 0.0;
+        update_flagic(&mut flagic, &mut accum, 0.0, 1.0, &FlagicTuning::default());
+        assert_eq!(flagic, 0);
+        assert!(accum.abs() < 1e-6);
+    }
+
+    #[test]
+    fn flagic_clamps_to_max() {
+        let mut flagic = 99u8;
+        let mut accum = 0.0;
+        update_flagic(
+            &mut flagic,
+            &mut accum,
+            FLAGIC_GAIN_RATE,
+            1.0,
+            &FlagicTuning::default(),
+        );
+        assert_eq!(flagic, FLAGIC_MAX);
+        assert!(accum.abs() < 1e-6);
+    }
+
+    #[test]
+    fn flagic_gain_scales_with_score_and_overlaps() {
+        let tuning = FlagicTuning::default();
+        assert_eq!(flagic_gain_rate(&[], &tuning), 0.0);
+        assert!((flagic_gain_rate(&[1.0], &tuning) - FLAGIC_GAIN_RATE).abs() < 1e-6);
+        let sloppy = flagic_gain_rate(&[0.0], &tuning);
+        assert!((sloppy - FLAGIC_GAIN_RATE * FLAGIC_SCORE_FLOOR).abs() < 1e-6);
+        let nested = flagic_gain_rate(&[1.0, 0.2], &tuning);
+        assert!((nested - FLAGIC_GAIN_RATE * (1.0 + FLAGIC_OVERLAP_BONUS)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn total_hippie_flags_sums_carried_flags() {
+        let camp = [vec2(0.0, 0.0), vec2(10.0, 0.0), vec2(0.0, 10.0)];
+        let hippies = npc::spawn_hippies_with_flags(
+            &[(vec2(1.0, 1.0), 1), (vec2(2.0, 2.0), 2)],
+            0,
+            &camp,
+            &mut Rng::new(1),
+            &crate::tuning::HippieTuning::default(),
+        );
+        assert_eq!(total_hippie_flags(&hippies), 3);
+    }
+
+    #[test]
+    fn stress_test_spawns_hippies_with_one_flag_each() {
+        let camp_configs = camps::camp_configs();
+        let hippies =
+            spawn_stress_test_hippies(&camp_configs, &Tuning::default(), &mut Rng::new(1));
+        assert_eq!(hippies.len(), STRESS_TEST_HIPPIE_COUNT);
+        assert!(hippies
+            .iter()
+            .all(|hippie| hippie.carried_flags == STRESS_TEST_FLAGS_PER_HIPPIE));
+
+        let mut counts = vec![0usize; camp_configs.len()];
+        for hippie in &hippies {
+            counts[hippie.camp_index] += 1;
+        }
+        let per_camp = STRESS_TEST_HIPPIE_COUNT / camp_configs.len();
+        let remainder = STRESS_TEST_HIPPIE_COUNT % camp_configs.len();
+        for (index, count) in counts.iter().enumerate() {
+            let expected = per_camp + usize::from(index < remainder);
+            assert_eq!(*count, expected);
+        }
+    }
+
+    #[test]
+    fn step_moves_player_and_sets_facing() {
+        let mut world = World::new(ClassChoice::Vexillomancer, test_field(), 1);
+        let start = world.player.pos;
+        let input = InputFrame {
+            movement: movement::InputState {
+                left: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        world.step(0.5, input);
+        assert!(world.player.pos.x < start.x);
+        assert_eq!(world.player.facing, player::Facing::Left);
+    }
+
+    #[test]
+    fn step_place_and_pickup_round_trip() {
+        let mut world = World::new(ClassChoice::Vexillomancer, test_field(), 1);
+        world.player.pos = vec2(100.0, 100.0);
+        world.step(0.0, InputFrame::default());
+        let inventory = world.flag_state.player_inventory();
+        let ground = world.flag_state.ground_flags().len();
+
+        world.step(
+            0.0,
+            InputFrame {
+                place_flag: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(world.flag_state.player_inventory(), inventory - 1);
+        assert_eq!(world.flag_state.ground_flags().len(), ground + 1);
+
+        world.step(
+            0.0,
+            InputFrame {
+                pickup_flag: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(world.flag_state.player_inventory(), inventory);
+        assert_eq!(world.flag_state.ground_flags().len(), ground);
+    }
+
+    #[test]
+    fn step_session_conserves_flags() {
+        let mut world = World::new(ClassChoice::StressTest, test_field(), 1);
+        let total = world.current_total_flags();
+        for frame in 0..600u32 {
+            let input = InputFrame {
+                movement: movement::InputState {
+                    up: frame % 120 < 30,
+                    down: (60..90).contains(&(frame % 120)),
+                    left: frame % 90 < 20,
+                    right: (45..70).contains(&(frame % 90)),
+                },
+                place_flag: frame % 37 == 0,
+                pickup_flag: frame % 53 == 0,
+                move_to: None,
+                cast: None,
+            };
+            world.step(1.0 / 60.0, input);
+            assert_eq!(world.current_total_flags(), total);
+        }
+    }
+
+    #[test]
+    fn same_seed_and_inputs_give_identical_worlds() {
+        let run = |seed| {
+            let mut world = World::new(ClassChoice::StressTest, test_field(), seed);
+            for frame in 0..300u32 {
+                let input = InputFrame {
+                    movement: movement::InputState {
+                        right: frame % 40 < 20,
+                        down: frame % 60 < 15,
+                        ..Default::default()
+                    },
+                    place_flag: frame % 25 == 0,
+                    pickup_flag: frame % 31 == 0,
+                    move_to: (frame % 100 == 50).then(|| vec2(frame as f32 * 3.0, 500.0)),
+                    cast: None,
+                };
+                world.step(1.0 / 60.0, input);
+            }
+            let hippies: Vec<Vec2> = world.hippies.iter().map(|h| h.pos).collect();
+            (
+                world.flag_state.ground_flags().to_vec(),
+                hippies,
+                world.scenery,
+            )
+        };
+
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7).0, run(8).0);
+    }
+
+    #[test]
+    fn player_slides_along_scenery() {
+        let mut world = World::new(ClassChoice::Vexillomancer, test_field(), 1);
+        world.player.pos = vec2(400.0, 400.0);
+        let feet = world.player.pos
+            + vec2(
+                player::PLAYER_WIDTH * 0.5,
+                player::PLAYER_HEIGHT - PLAYER_COLLISION_RADIUS,
+            );
+        let tent_top = feet.y + PLAYER_COLLISION_RADIUS + 1.0;
+        world.set_layout_scenery(&[scenery::ScenerySpawn {
+            kind: scenery::SceneryKind::Tent,
+            pos: vec2(feet.x, tent_top + 10.0 * scale::MODEL_SCALE),
+            scale: 1.0,
+            rotation: 0.0,
+            variant: 0,
+            decorations: Vec::new(),
+        }]);
+
+        let walk = |right| InputFrame {
+            movement: movement::InputState {
+                down: true,
+                right,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        for _ in 0..120 {
+            world.step(1.0 / 60.0, walk(false));
+        }
+        let blocked = world.player.pos;
+        assert!(blocked.y + player::PLAYER_HEIGHT <= tent_top + 0.01);
+        assert!((blocked.x - 400.0).abs() < 0.01);
+
+        world.step(1.0 / 60.0, walk(true));
+        assert!(world.player.pos.x > blocked.x);
+        assert!((world.player.pos.y - blocked.y).abs() < 0.01);
+    }
+
+    #[test]
+    fn click_to_move_walks_around_scenery() {
+        let mut world = World::new(ClassChoice::Vexillomancer, test_field(), 1);
+        world.player.pos = vec2(400.0, 400.0);
+        let feet = world.player.feet();
+        world.set_layout_scenery(&[scenery::ScenerySpawn {
+            kind: scenery::SceneryKind::CrowBase,
+            pos: feet + vec2(0.0, 30.0),
+            scale: 1.0,
+            rotation: 0.0,
+            variant: 0,
+            decorations: Vec::new(),
+        }]);
+        let destination = feet + vec2(0.0, 60.0);
+
+        world.step(
+            1.0 / 60.0,
+            InputFrame {
+                move_to: Some(destination),
+                ..Default::default()
+            },
+        );
+        assert!(world.player_path.len() > 1);
+        for _ in 0..600 {
+            world.step(1.0 / 60.0, InputFrame::default());
+        }
+        assert!(world.player_path.is_empty());
+        assert!(world.player.feet().distance(destination) < 0.5);
+    }
+
+    #[test]
+    fn standing_in_pentagram_gains_flagic() {
+        let mut world = World::new(ClassChoice::Vexillomancer, test_field(), 1);
+        let center = vec2(400.0, 400.0);
+        world.player.pos = center - vec2(player::PLAYER_WIDTH * 0.5, player::PLAYER_HEIGHT * 0.5);
+        let mut ground = Vec::new();
+        for i in 0..5 {
+            let angle = i as f32 * std::f32::consts::TAU / 5.0;
+            ground.push(flags::make_flag(
+                center + vec2(angle.cos(), angle.sin()) * 50.0,
+            ));
+        }
+        let hippie_flags = total_hippie_flags(&world.hippies);
+        world.flag_state = flag_state::FlagState::new(ground, 0, 5 + hippie_flags);
+        world.recompute_ley_state();
+        assert!(world.in_pentagram());
+        assert_eq!(world.pentagram_scores_here().len(), 1);
+
+        world.step(1.0, InputFrame::default());
+        assert!(world.flagic > 0);
+    }
+
+    #[test]
+    fn spells_pay_flagic_only_when_they_act() {
+        let mut world = World::new(ClassChoice::Vexillomancer, test_field(), 1);
+        world.flagic = 100;
+        for hippie in &mut world.hippies {
+            hippie.angry = false;
+        }
+        assert_eq!(world.cast_spell(Spell::Calm), Err(CastError::NoTarget));
+        assert_eq!(world.flagic, 100);
+
+        let before = world.wind.direction;
+        world.cast_spell(Spell::Gust).expect("gust");
+        assert!(before.dot(world.wind.direction).abs() < 1e-4);
+        assert_eq!(world.flagic, 100 - world.tuning.spells.gust_cost);
+        assert_eq!(world.cast_spell(Spell::Gust), Err(CastError::CoolingDown));
+
+        let pos = world.player.pos + vec2(world.tuning.spells.summon_radius * 0.5, 0.0);
+        let hippie_flags = total_hippie_flags(&world.hippies);
+        world.flag_state =
+            flag_state::FlagState::new(vec![flags::make_flag(pos)], 0, 1 + hippie_flags);
+        world.cast_spell(Spell::Summon).expect("summon");
+        assert_eq!(world.flag_state.player_inventory(), 1);
+        assert_eq!(world.cast_spell(Spell::Summon), Err(CastError::CoolingDown));
+
+        world.flagic = 0;
+        assert_eq!(
+            world.cast_spell(Spell::Haste),
+            Err(CastError::NotEnoughFlagic)
+        );
+        let mut stress = World::new(ClassChoice::StressTest, test_field(), 1);
+        stress.flagic = 100;
+        assert_eq!(stress.cast_spell(Spell::Gust), Err(CastError::WrongClass));
+    }
+
+    #[test]
+    fn haste_speeds_the_player_up_for_a_while() {
+        let walk = |haste: bool| {
+            let mut world = World::new(ClassChoice::Vexillomancer, test_field(), 1);
+            world.player.pos = vec2(300.0, 300.0);
+            world.flagic = 100;
+            let start = world.player.pos;
+            let mut input = InputFrame::default();
+            input.movement.right = true;
+            input.cast = haste.then_some(Spell::Haste);
+            world.step(0.1, input);
+            world.player.pos.distance(start)
+        };
+        let plain = walk(false);
+        let hasted = walk(true);
+        assert!(
+            (hasted - plain * SPELL_HASTE_MULTIPLIER).abs() < 1e-3,
+            "{} vs {}",
+            hasted,
+            plain
+        );
+    }
+
+    #[test]
+    fn position_snapshot_blends_towards_current_step() {
+        let mut world = World::new(ClassChoice::Vexillomancer, test_field(), 1);
+        let before = world.position_snapshot();
+        let input = InputFrame {
+            movement: movement::InputState {
+                right: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        world.step(0.1, input);
+
+        let halfway = before.player_pos(&world, 0.5);
+        assert_eq!(halfway, before.player.lerp(world.player.pos, 0.5));
+        assert_eq!(before.player_pos(&world, 0.0), before.player);
+        assert_eq!(before.player_pos(&world, 1.0), world.player.pos);
+        let hippie = world.hippies[0].pos;
+        assert_eq!(before.hippie_pos(usize::MAX, hippie, 0.5), hippie);
+    }
+
+    #[test]
+    fn tuning_drives_spawn_and_ley_lines() {
+        let mut tuning = Tuning::default();
+        tuning.player.spawn_pos = [120.0, 80.0];
+        tuning.flags.start_count = 0;
+        tuning.player.starting_flags = 2;
+        let mut world = World::new_with_config(
+            ClassChoice::Vexillomancer,
+            test_field(),
+            1,
+            WorldConfig {
+                tuning,
+                ..WorldConfig::default()
+            },
+        );
+        assert_eq!(world.player.pos, vec2(120.0, 80.0));
+        assert_eq!(world.flag_state.player_inventory(), 2);
+
+        let speed = world.player_speed;
+        let mut faster = world.tuning.clone();
+        faster.player.speed_multiplier *= 2.0;
+        faster.ley.max_distance = 0.0;
+        world.set_tuning(faster);
+        assert!((world.player_speed - speed * 2.0).abs() < 1e-3);
+        assert!(world.ley_lines.is_empty());
+    }
+}
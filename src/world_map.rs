@@ -0,0 +1,155 @@
+use macroquad::prelude::*;
+
+use crate::constants::{WORLD_MAP_ZOOM_MAX, WORLD_MAP_ZOOM_MIN};
+
+/// Pan and zoom of the full-screen map, kept apart from the dungeon
+/// `CameraState`. A zoom of 1 fits the whole field on screen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorldMapView {
+    pub center: Vec2,
+    pub zoom: f32,
+    field: Rect,
+}
+
+impl WorldMapView {
+    pub fn new(field: Rect) -> Self {
+        Self {
+            center: field.center(),
+            zoom: 1.0,
+            field,
+        }
+    }
+
+    /// Screen pixels per map unit.
+    pub fn scale(&self, screen: Vec2) -> f32 {
+        let fit = (screen.x / self.field.w.max(1.0)).min(screen.y / self.field.h.max(1.0));
+        fit * self.zoom
+    }
+
+    pub fn to_screen(&self, pos: Vec2, screen: Vec2) -> Vec2 {
+        (pos - self.center) * self.scale(screen) + screen * 0.5
+    }
+
+    pub fn to_world(&self, screen_pos: Vec2, screen: Vec2) -> Vec2 {
+        (screen_pos - screen * 0.5) / self.scale(screen) + self.center
+    }
+
+    /// The part of the map on screen.
+    pub fn view_rect(&self, screen: Vec2) -> Rect {
+        let size = screen / self.scale(screen);
+        Rect::new(
+            self.center.x - size.x * 0.5,
+            self.center.y - size.y * 0.5,
+            size.x,
+            size.y,
+        )
+    }
+
+    /// Zooms by `factor`, keeping the map point under `anchor` in place.
+    pub fn zoom_at(&mut self, factor: f32, anchor: Vec2, screen: Vec2) {
+        let before = self.to_world(anchor, screen);
+        self.zoom = (self.zoom * factor).clamp(WORLD_MAP_ZOOM_MIN, WORLD_MAP_ZOOM_MAX);
+        let after = self.to_world(anchor, screen);
+        self.center += before - after;
+        self.clamp_center();
+    }
+
+    /// Moves the map by a screen-space drag.
+    pub fn pan(&mut self, screen_delta: Vec2, screen: Vec2) {
+        self.center -= screen_delta / self.scale(screen);
+        self.clamp_center();
+    }
+
+    fn clamp_center(&mut self) {
+        let min = self.field.point();
+        let max = min + self.field.size();
+        self.center = self.center.clamp(min, max);
+    }
+}
+
+/// Centroid of a camp polygon's vertices, where its label goes.
+pub fn label_position(vertices: &[Vec2]) -> Option<Vec2> {
+    if vertices.is_empty() {
+        return None;
+    }
+    Some(vertices.iter().copied().sum::<Vec2>() / vertices.len() as f32)
+}
+
+/// Where to draw the arrow pointing at an off-screen `waypoint`: a point
+/// `inset` pixels inside the screen edge on the line from the screen center,
+/// and the arrow's angle in radians. `None` while the waypoint is in `view`.
+pub fn waypoint_arrow(waypoint: Vec2, view: Rect, screen: Vec2, inset: f32) -> Option<(Vec2, f32)> {
+    if view.contains(waypoint) {
+        return None;
+    }
+    let direction = waypoint - view.center();
+    if direction.length_squared() == 0.0 {
+        return None;
+    }
+    let half = (screen * 0.5 - Vec2::splat(inset)).max(Vec2::ZERO);
+    // Scale the direction until it touches the inset screen edge.
+    let reach = (half.x / direction.x.abs()).min(half.y / direction.y.abs());
+    Some((
+        screen * 0.5 + direction * reach,
+        direction.y.atan2(direction.x),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Vec2 = Vec2::new(960.0, 540.0);
+
+    #[test]
+    fn view_fits_field_and_round_trips() {
+        let view = WorldMapView::new(Rect::new(0.0, 0.0, 9216.0, 6144.0));
+        let rect = view.view_rect(SCREEN);
+        assert!(rect.w >= 9216.0 && rect.h >= 6144.0);
+        assert!((rect.h - 6144.0).abs() < 0.01);
+
+        let pos = vec2(1200.0, 3400.0);
+        let back = view.to_world(view.to_screen(pos, SCREEN), SCREEN);
+        assert!((back - pos).length() < 0.01);
+    }
+
+    #[test]
+    fn zoom_keeps_anchor_and_clamps() {
+        let mut view = WorldMapView::new(Rect::new(0.0, 0.0, 1000.0, 1000.0));
+        let anchor = vec2(700.0, 100.0);
+        let under = view.to_world(anchor, SCREEN);
+        view.zoom_at(2.0, anchor, SCREEN);
+        assert_eq!(view.zoom, 2.0);
+        assert!((view.to_world(anchor, SCREEN) - under).length() < 0.01);
+
+        view.zoom_at(1000.0, anchor, SCREEN);
+        assert_eq!(view.zoom, WORLD_MAP_ZOOM_MAX);
+        view.pan(vec2(-1.0e6, 0.0), SCREEN);
+        assert_eq!(view.center.x, 1000.0);
+    }
+
+    #[test]
+    fn arrow_points_to_offscreen_waypoints() {
+        let view = Rect::new(0.0, 0.0, 240.0, 135.0);
+        assert_eq!(waypoint_arrow(vec2(100.0, 100.0), view, SCREEN, 20.0), None);
+
+        let (pos, angle) = waypoint_arrow(vec2(1000.0, 67.5), view, SCREEN, 20.0).expect("arrow");
+        assert!((pos - vec2(940.0, 270.0)).length() < 0.01);
+        assert!(angle.abs() < 0.001);
+
+        let (pos, _) = waypoint_arrow(vec2(120.0, -500.0), view, SCREEN, 20.0).expect("arrow");
+        assert!((pos.y - 20.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn labels_sit_at_vertex_centroid() {
+        let square = [
+            vec2(0.0, 0.0),
+            vec2(10.0, 0.0),
+            vec2(10.0, 10.0),
+            vec2(0.0, 10.0),
+        ];
+        assert_eq!(label_position(&square), Some(vec2(5.0, 5.0)));
+        assert_eq!(label_position(&[]), None);
+    }
+}